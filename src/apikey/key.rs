@@ -0,0 +1,36 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+// Marks API keys apart from Auth0 tokens in the `Authorization` header.
+pub const API_KEY_PREFIX: &str = "qrk_";
+
+// Characters of a key kept in the clear to identify it, the marker included.
+const VISIBLE_LENGTH: usize = 12;
+
+pub fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    format!("{}{}", API_KEY_PREFIX, hex::encode(bytes))
+}
+
+pub fn visible_prefix(key: &str) -> &str {
+    &key[..key.len().min(VISIBLE_LENGTH)]
+}
+
+pub fn hash_key(key: &str) -> String {
+    /*
+        Hashes an API key for storage and lookup.
+
+        Keys are long random strings, so a plain SHA-256 is enough to keep a leaked database
+        from revealing working keys.
+
+        Params:
+            key (str): The key as sent by the client.
+
+        Returns:
+            String: The hex encoded SHA-256 of the key.
+    */
+
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
@@ -0,0 +1,3 @@
+pub mod key;
+pub mod models;
+pub mod routes;
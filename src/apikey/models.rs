@@ -0,0 +1,76 @@
+use crate::database::models::{ApiKeyId, Owner, MAX_NAME_LENGTH};
+
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+
+// Keys can be made to expire after at most this many days, or never.
+pub const MAX_KEY_EXPIRY_DAYS: i64 = 365;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ApiScope {
+    // Lists, searches and reads codes.
+    #[serde(rename = "qrcode:read")]
+    QrcodeRead,
+    // Creates, edits, pauses, clones and deletes codes.
+    #[serde(rename = "qrcode:write")]
+    QrcodeWrite,
+    // Reads the scan history of codes.
+    #[serde(rename = "analytics:read")]
+    AnalyticsRead,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    // Days until the key stops working, none for a key that doesn't expire.
+    #[serde(default)]
+    pub expires_in_days: Option<i64>,
+}
+
+impl NewApiKey {
+    pub fn normalize(&mut self) -> bool {
+        self.name = self.name.trim().to_string();
+        self.scopes.sort();
+        self.scopes.dedup();
+
+        !self.name.is_empty()
+            && self.name.chars().count() <= MAX_NAME_LENGTH
+            && !self.scopes.is_empty()
+            && self
+                .expires_in_days
+                .is_none_or(|days| (1..=MAX_KEY_EXPIRY_DAYS).contains(&days))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyResult {
+    pub id: ApiKeyId,
+    pub name: String,
+    // The start of the key, enough to tell keys apart without revealing them.
+    pub prefix: String,
+    pub scopes: Vec<ApiScope>,
+    // The user ID of the member who made it.
+    pub created_by: String,
+    pub created_at: Datetime,
+    pub expires_at: Option<Datetime>,
+    pub last_used_at: Option<Datetime>,
+}
+
+// Only returned when the key is created, the key isn't shown again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeySecretResult {
+    #[serde(flatten)]
+    pub details: ApiKeyResult,
+    pub key: String,
+}
+
+// What a valid key lets a request do.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyGrant {
+    // The user the key acts as.
+    pub user: String,
+    // The only user or organization whose codes the key reaches.
+    pub owner: Owner,
+    pub scopes: Vec<ApiScope>,
+}
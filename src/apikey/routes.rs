@@ -0,0 +1,137 @@
+use crate::apikey::key::{generate_key, hash_key, visible_prefix};
+use crate::apikey::models::{ApiKeySecretResult, NewApiKey};
+use crate::database::database::Database;
+use crate::database::models::{format_user_id, ApiKeyId};
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::organization::models::Role;
+use crate::routes::guard::Claims;
+use crate::routes::user::workspace;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{delete, get, post};
+use serde_json::json;
+
+#[post("/user/<user_id>/key?<org>", format = "json", data = "<key>")]
+pub async fn create_api_key(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org: Option<&str>,
+    key: Json<NewApiKey>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Creates an API key for the user's codes, or with `?org=` for an organization's codes, for
+        its admins and owners. Keys act as the user who made them.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org: optional organization ID, the key then only reaches the organization's codes.
+            key: the key's name, scopes and optional expiry in days.
+
+        Returns:
+            Response<Json<ApiResponse>>: the created key, the only response that includes the key itself.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let owner = workspace(db, user_id, org, Role::Admin).await?;
+
+    let mut key = key.into_inner();
+
+    if !key.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    let secret = generate_key();
+    let details = db
+        .insert_api_key(
+            user_id,
+            &owner,
+            key,
+            visible_prefix(&secret),
+            &hash_key(&secret),
+        )
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Created.code,
+        message: "API key created".to_string(),
+        data: json!({"key": ApiKeySecretResult { details, key: secret }}),
+    }))
+}
+
+#[get("/user/<user_id>/key?<org>")]
+pub async fn read_api_keys(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org: Option<&str>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Lists the user's API keys, or with `?org=` an organization's, for its admins and owners.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org: optional organization ID to list the organization's keys.
+
+        Returns:
+            Response<Json<ApiResponse>>: the keys, newest first, without the keys themselves.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let owner = workspace(db, user_id, org, Role::Admin).await?;
+
+    let keys = db.list_api_keys(&owner).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "API keys".to_string(),
+        data: json!({"keys": keys}),
+    }))
+}
+
+#[delete("/user/<user_id>/key/<key_id>?<org>")]
+pub async fn delete_api_key(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    key_id: ApiKeyId,
+    org: Option<&str>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Revokes one of the user's API keys, or with `?org=` one of an organization's, for its
+        admins and owners. Requests with the key fail from then on.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            key_id: the API key ID.
+            org: optional organization ID the key belongs to.
+
+        Returns:
+            Response<Json<ApiResponse>>: whether the key was revoked.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let owner = workspace(db, user_id, org, Role::Admin).await?;
+
+    let deleted = db.delete_api_key(&owner, &key_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "API key revoked".to_string(),
+        data: json!({"deleted": deleted}),
+    }))
+}
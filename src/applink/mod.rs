@@ -0,0 +1,4 @@
+pub mod models;
+pub mod platform;
+pub mod render;
+pub mod routes;
@@ -0,0 +1,138 @@
+use crate::applink::platform::Platform;
+use crate::bulk::import::validate_target_url;
+use crate::bulk::models::MAX_TARGET_URL_LENGTH;
+
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+
+// Schemes that run code or read local data in the browser, never allowed as deep links.
+const BLOCKED_SCHEMES: &[&str] = &["javascript", "vbscript", "data", "file", "blob", "about"];
+
+pub fn valid_deep_link(uri: &str) -> bool {
+    /*
+        Checks a deep link: a custom URI scheme such as `myapp://`, an Android `intent://` URI or
+        an http(s) universal link. Unlike redirect targets, any scheme is kept as written, apart
+        from the blocked ones.
+
+        Returns:
+            bool: Whether the deep link can be offered to a scanning phone.
+    */
+
+    let scheme = match uri.split_once("://") {
+        Some((scheme, rest)) if !rest.is_empty() => scheme.to_ascii_lowercase(),
+        _ => return false,
+    };
+
+    let mut chars = scheme.chars();
+    let well_formed = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+
+    well_formed
+        && !BLOCKED_SCHEMES.contains(&scheme.as_str())
+        && uri.len() <= MAX_TARGET_URL_LENGTH
+        && !uri.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+fn valid_store_link(url: &str) -> bool {
+    // Store listings are always https pages.
+    url.to_ascii_lowercase().starts_with("https://") && validate_target_url(url).is_ok()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppLink {
+    // Opened on iPhones and iPads, custom scheme or universal link.
+    #[serde(default)]
+    pub ios_url: Option<String>,
+    #[serde(default)]
+    pub android_url: Option<String>,
+    // Where phones go when the app doesn't open.
+    #[serde(default)]
+    pub ios_store_url: Option<String>,
+    #[serde(default)]
+    pub android_store_url: Option<String>,
+    // Where desktops, and phones without a store link, go.
+    pub fallback_url: String,
+}
+
+impl AppLink {
+    pub fn normalize(&mut self) -> bool {
+        /*
+            Trims the links and drops blank optional ones.
+
+            Returns:
+                bool: false if a deep link has a blocked or malformed scheme, a store link isn't
+                https or the fallback isn't an http(s) URL.
+        */
+
+        let trim = |link: &mut Option<String>| {
+            *link = link
+                .take()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty());
+        };
+
+        trim(&mut self.ios_url);
+        trim(&mut self.android_url);
+        trim(&mut self.ios_store_url);
+        trim(&mut self.android_store_url);
+        self.fallback_url = self.fallback_url.trim().to_string();
+
+        [&self.ios_url, &self.android_url]
+            .iter()
+            .all(|link| link.as_deref().is_none_or(valid_deep_link))
+            && [&self.ios_store_url, &self.android_store_url]
+                .iter()
+                .all(|link| link.as_deref().is_none_or(valid_store_link))
+            && validate_target_url(&self.fallback_url).is_ok()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppLinkResult {
+    #[serde(default)]
+    pub ios_url: Option<String>,
+    #[serde(default)]
+    pub android_url: Option<String>,
+    #[serde(default)]
+    pub ios_store_url: Option<String>,
+    #[serde(default)]
+    pub android_store_url: Option<String>,
+    pub fallback_url: String,
+    pub updated_at: Datetime,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AppLaunch {
+    // Try the deep link on a launch page, then go to the fallback.
+    Page { deep_link: String, fallback: String },
+    Redirect(String),
+}
+
+impl AppLinkResult {
+    pub fn launch(&self, platform: Platform) -> AppLaunch {
+        /*
+            Picks what a scan from a platform gets: phones with a deep link get the launch page
+            falling back to their store, or the web fallback without a store link. Phones without
+            a deep link go to their store, everything else to the web fallback.
+
+            Returns:
+                AppLaunch: The launch page or the redirect.
+        */
+
+        let (deep_link, store) = match platform {
+            Platform::Ios => (&self.ios_url, &self.ios_store_url),
+            Platform::Android => (&self.android_url, &self.android_store_url),
+            Platform::Other => return AppLaunch::Redirect(self.fallback_url.clone()),
+        };
+
+        let fallback = store.as_ref().unwrap_or(&self.fallback_url).clone();
+
+        match deep_link {
+            Some(deep_link) => AppLaunch::Page {
+                deep_link: deep_link.clone(),
+                fallback,
+            },
+            None => AppLaunch::Redirect(fallback),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use rocket::request::{FromRequest, Outcome};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Ios,
+    Android,
+    // Desktops and anything that can't open an app.
+    Other,
+}
+
+pub fn detect(user_agent: Option<&str>) -> Platform {
+    /*
+        Tells the phone platform apart from the User-Agent header.
+
+        Returns:
+            Platform: iOS for iPhones, iPads and iPods, Android, or Other.
+    */
+
+    let user_agent = match user_agent {
+        Some(user_agent) => user_agent,
+        None => return Platform::Other,
+    };
+
+    // Android is checked first, some Android browsers mention "like iPhone".
+    if user_agent.contains("Android") {
+        Platform::Android
+    } else if ["iPhone", "iPad", "iPod"]
+        .iter()
+        .any(|device| user_agent.contains(device))
+    {
+        Platform::Ios
+    } else {
+        Platform::Other
+    }
+}
+
+pub struct ScanPlatform {
+    pub platform: Platform,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ScanPlatform {
+    type Error = ();
+
+    async fn from_request(request: &'r rocket::Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(ScanPlatform {
+            platform: detect(request.headers().get_one("User-Agent")),
+        })
+    }
+}
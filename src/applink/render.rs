@@ -0,0 +1,78 @@
+use crate::routes::qrcode::redirect_location;
+use crate::scan::page::escape_html;
+
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder};
+use std::io::Cursor;
+
+// How long the page waits for the app to open before falling back.
+pub const FALLBACK_DELAY_MS: u32 = 1500;
+
+// The page answered to phones that tries to open the app, and falls back to the store or web.
+pub struct LaunchPage {
+    title: String,
+    deep_link: String,
+    fallback: String,
+}
+
+fn script_string(value: &str) -> String {
+    // A JSON string is a valid JavaScript literal, `<` is escaped so it can't close the script.
+    serde_json::to_string(value)
+        .unwrap_or_else(|_| "\"\"".to_string())
+        .replace('<', "\\u003c")
+}
+
+impl LaunchPage {
+    pub fn new(name: Option<&str>, deep_link: &str, fallback: &str) -> Self {
+        LaunchPage {
+            title: name.unwrap_or("Opening the app").to_string(),
+            deep_link: deep_link.to_string(),
+            fallback: redirect_location(fallback),
+        }
+    }
+
+    pub fn html(&self) -> String {
+        // The fallback only runs if the page is still visible, that is the app didn't take over.
+        format!(
+            "<!DOCTYPE html>\
+            <html lang=\"en\">\
+            <head>\
+            <meta charset=\"utf-8\">\
+            <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+            <meta name=\"robots\" content=\"noindex\">\
+            <title>{title}</title>\
+            <style>body{{font-family:system-ui,sans-serif;display:flex;min-height:100vh;margin:0;align-items:center;justify-content:center;text-align:center;color:#222}}main{{padding:24px;max-width:420px}}a{{display:block;margin:12px 0;color:#222}}</style>\
+            </head>\
+            <body><main><h1>{title}</h1>\
+            <a href=\"{deep_href}\">Open the app</a>\
+            <a href=\"{fallback_href}\">Continue without the app</a>\
+            </main>\
+            <script>\
+            var fallback={fallback_js};\
+            setTimeout(function(){{if(!document.hidden){{window.location.replace(fallback);}}}},{delay});\
+            window.location.href={deep_js};\
+            </script>\
+            </body>\
+            </html>",
+            title = escape_html(&self.title),
+            deep_href = escape_html(&self.deep_link),
+            fallback_href = escape_html(&self.fallback),
+            fallback_js = script_string(&self.fallback),
+            deep_js = script_string(&self.deep_link),
+            delay = FALLBACK_DELAY_MS,
+        )
+    }
+}
+
+impl<'r> Responder<'r, 'static> for LaunchPage {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> response::Result<'static> {
+        let body = self.html();
+
+        rocket::Response::build()
+            .status(Status::Ok)
+            .header(ContentType::HTML)
+            .header(Header::new("Cache-Control", "no-store"))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
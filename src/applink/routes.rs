@@ -0,0 +1,89 @@
+use crate::apikey::models::ApiScope;
+use crate::applink::models::AppLink;
+use crate::database::database::Database;
+use crate::database::models::QrCodeId;
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::routes::guard::Caller;
+use crate::routes::user::reach_code;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{get, put};
+use serde_json::json;
+
+#[get("/user/<user_id>/qrcode/<qrcode_id>/app")]
+pub async fn read_app_link(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Reads the deep links, store links and web fallback of an app code.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            qrcode_id: the app code ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the app links object, or null before they are first set, as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let app_link = db.select_app_link(user_id, &qrcode_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "App links".to_string(),
+        data: json!({"app": app_link}),
+    }))
+}
+
+#[put(
+    "/user/<user_id>/qrcode/<qrcode_id>/app",
+    format = "json",
+    data = "<app_link>"
+)]
+pub async fn update_app_link(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+    app_link: Json<AppLink>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Sets where an app code sends phones and everything else.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            qrcode_id: the app code ID.
+            app_link: optional `ios_url` and `android_url` deep links (custom schemes, `intent://` or
+                      universal links), optional https `ios_store_url` and `android_store_url`,
+                      and the required http(s) `fallback_url`.
+
+        Returns:
+            Response<Json<ApiResponse>>: the updated app links object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let mut app_link = app_link.into_inner();
+
+    if !app_link.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    let updated = db.update_app_link(user_id, &qrcode_id, &app_link).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "App links updated".to_string(),
+        data: json!({"app": updated}),
+    }))
+}
@@ -0,0 +1,152 @@
+use crate::bulk::models::{
+    ImportRow, NewQrCode, RowReport, RowStatus, MAX_IMPORT_ROWS, MAX_TARGET_URL_LENGTH,
+};
+use crate::database::models::{valid_slug, DynamicQr};
+
+use std::collections::HashSet;
+
+const COLUMNS: [&str; 4] = ["target_url", "name", "tags", "slug"];
+
+pub fn parse_csv(text: &str) -> Result<Vec<ImportRow>, String> {
+    /*
+        Parses and validates a CSV of QR codes to create.
+
+        Columns:
+            target_url: Required, where the code redirects to.
+            name: Optional name.
+            tags: Optional tags separated by `;` or `,`.
+            slug: Optional custom server URL, 3 to 64 letters, digits, `-` or `_`.
+
+        Returns:
+            Result<Vec<ImportRow>, String>: Every row with its validation errors, or why the file
+            as a whole can't be imported.
+    */
+
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|error| format!("Unreadable header: {}", error))?
+        .iter()
+        .map(|header| header.to_ascii_lowercase())
+        .collect();
+
+    if let Some(unknown) = headers.iter().find(|h| !COLUMNS.contains(&h.as_str())) {
+        return Err(format!(
+            "Unknown column `{}`, expected {}",
+            unknown,
+            COLUMNS.join(", ")
+        ));
+    }
+
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let target_url = column("target_url").ok_or("Missing the target_url column")?;
+    let (name, tags, slug) = (column("name"), column("tags"), column("slug"));
+
+    let mut rows = Vec::new();
+    let mut slugs = HashSet::new();
+
+    for record in reader.records() {
+        if rows.len() == MAX_IMPORT_ROWS {
+            return Err(format!("More than {} rows", MAX_IMPORT_ROWS));
+        }
+
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                let row = error.position().map_or(0, |position| position.line());
+                rows.push(invalid(row, vec![error.to_string()]));
+                continue;
+            }
+        };
+
+        let row = record.position().map_or(0, |position| position.line());
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|index| record.get(index))
+                .filter(|value| !value.is_empty())
+        };
+
+        let mut errors = Vec::new();
+
+        let target = cell(Some(target_url)).unwrap_or_default().to_string();
+        if let Err(error) = validate_target_url(&target) {
+            errors.push(error.to_string());
+        }
+
+        let mut qrcode = DynamicQr {
+            target_url: target,
+            name: cell(name).map(str::to_string),
+            tags: cell(tags)
+                .map(|tags| tags.split([';', ',']).map(str::to_string).collect())
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        if !qrcode.normalize() {
+            errors.push("Name or tags are over the length limits".to_string());
+        }
+
+        let slug = cell(slug).map(str::to_string);
+        if let Some(slug) = &slug {
+            if !valid_slug(slug) {
+                errors.push("Slug must be 3 to 64 letters, digits, `-` or `_`".to_string());
+            } else if !slugs.insert(slug.clone()) {
+                errors.push("Slug is used by another row".to_string());
+            }
+        }
+
+        if errors.is_empty() {
+            rows.push(ImportRow {
+                report: report(row, RowStatus::Valid, Vec::new()),
+                code: Some(NewQrCode::new(qrcode, slug)),
+            });
+        } else {
+            rows.push(invalid(row, errors));
+        }
+    }
+
+    if rows.is_empty() {
+        return Err("The file has no rows".to_string());
+    }
+
+    Ok(rows)
+}
+
+pub fn validate_target_url(target_url: &str) -> Result<(), &'static str> {
+    // Targets without a scheme are redirected to over http, see `redirect_location`.
+    let lowered = target_url.to_ascii_lowercase();
+
+    if target_url.is_empty() {
+        Err("Missing target_url")
+    } else if target_url.len() > MAX_TARGET_URL_LENGTH {
+        Err("target_url is too long")
+    } else if target_url.chars().any(char::is_whitespace) {
+        Err("target_url can't contain spaces")
+    } else if lowered.contains("://")
+        && !lowered.starts_with("http://")
+        && !lowered.starts_with("https://")
+    {
+        Err("target_url must be an http or https URL")
+    } else {
+        Ok(())
+    }
+}
+
+fn report(row: u64, status: RowStatus, errors: Vec<String>) -> RowReport {
+    RowReport {
+        row,
+        status,
+        id: None,
+        server_url: None,
+        errors,
+    }
+}
+
+fn invalid(row: u64, errors: Vec<String>) -> ImportRow {
+    ImportRow {
+        report: report(row, RowStatus::Invalid, errors),
+        code: None,
+    }
+}
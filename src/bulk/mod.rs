@@ -0,0 +1,4 @@
+pub mod import;
+pub mod models;
+pub mod routes;
+pub mod update;
@@ -0,0 +1,140 @@
+use crate::database::models::{DynamicQr, QrCodeId, QrColor, QrStatus, QrTarget};
+
+use rocket::form::FromForm;
+use rocket::fs::TempFile;
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+
+pub const MAX_IMPORT_ROWS: usize = 500;
+
+pub const MAX_TARGET_URL_LENGTH: usize = 2048;
+
+#[derive(FromForm)]
+pub struct CsvUpload<'r> {
+    pub file: TempFile<'r>,
+}
+
+// A validated row, in the shape the import query expects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewQrCode {
+    pub target_url: String,
+    pub redirect_code: u16,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub color: Option<QrColor>,
+    // A custom server URL, otherwise one is generated.
+    pub slug: Option<String>,
+}
+
+impl NewQrCode {
+    pub fn new(qrcode: DynamicQr, slug: Option<String>) -> Self {
+        NewQrCode {
+            target_url: qrcode.target_url,
+            redirect_code: u16::from(qrcode.redirect_code),
+            name: qrcode.name,
+            description: qrcode.description,
+            tags: qrcode.tags,
+            color: qrcode.color,
+            slug,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowStatus {
+    // Passed validation but wasn't created because another row failed.
+    Valid,
+    Invalid,
+    Created,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RowReport {
+    // The line of the row in the file, the header being line 1.
+    pub row: u64,
+    pub status: RowStatus,
+    pub id: Option<QrCodeId>,
+    pub server_url: Option<String>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ImportRow {
+    pub report: RowReport,
+    pub code: Option<NewQrCode>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub created: usize,
+    pub rows: Vec<RowReport>,
+}
+
+pub const MAX_BULK_IDS: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkOperation {
+    // Points every code at the same target URL.
+    Retarget {
+        target_url: String,
+    },
+    // Swaps the host of targets on `find` for `replace`, other targets are left alone.
+    ReplaceDomain {
+        find: String,
+        replace: String,
+    },
+    // Adds and removes tags, keeping any others.
+    Retag {
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    Pause,
+    Resume,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkUpdate {
+    pub ids: Vec<String>,
+    pub operation: BulkOperation,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkDelete {
+    pub ids: Vec<String>,
+}
+
+// The fields of a code that bulk operations read.
+#[derive(Debug, Deserialize)]
+pub struct BulkTarget {
+    pub id: QrCodeId,
+    pub server_url: String,
+    pub target_url: String,
+    #[serde(default)]
+    pub target_type: QrTarget,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub status: QrStatus,
+}
+
+// New values for one code, fields left as none are kept.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BulkChange {
+    pub id: RecordId,
+    pub target_url: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub status: Option<QrStatus>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkSummary {
+    pub requested: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub deleted: usize,
+}
@@ -0,0 +1,244 @@
+use crate::apikey::models::ApiScope;
+use crate::bulk::import::parse_csv;
+use crate::bulk::models::{
+    BulkDelete, BulkOperation, BulkSummary, BulkUpdate, CsvUpload, ImportReport, RowStatus,
+};
+use crate::bulk::update::{normalize, parse_ids};
+use crate::database::database::Database;
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::organization::models::Role;
+use crate::routes::guard::Caller;
+use crate::routes::user::{available_usage, validate_and_get_subscription, workspace};
+
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{delete, patch, post};
+use serde_json::json;
+use tokio::io::AsyncReadExt;
+
+fn unprocessable(message: &str, data: serde_json::Value) -> status::Custom<Json<ApiResponse>> {
+    status::Custom(
+        Status::UnprocessableEntity,
+        Json(ApiResponse {
+            status: Status::UnprocessableEntity.code,
+            message: message.to_string(),
+            data,
+        }),
+    )
+}
+
+#[post("/user/<user_id>/qrcode/import?<org>", data = "<upload>")]
+pub async fn import_qrcodes(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    org: Option<&str>,
+    upload: Form<CsvUpload<'_>>,
+) -> Response<status::Custom<Json<ApiResponse>>> {
+    /*
+        Creates many dynamic URLs from an uploaded CSV file, all or nothing.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org: optional organization ID, the codes then belong to the organization.
+            upload: a multipart form with the CSV in `file`. Columns are `target_url`, and
+                    optionally `name`, `tags` (separated by `;` or `,`) and `slug`.
+
+        Returns:
+            Response<status::Custom<Json<ApiResponse>>>: 201 with a report of every created row, or 422
+            with every row's errors when any row is invalid, in which case nothing is created.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+
+    let org = caller.workspace(org)?;
+
+    let owner = workspace(db, user_id, org.as_deref(), Role::Editor).await?;
+
+    let mut text = String::new();
+    upload
+        .file
+        .open()
+        .await
+        .map_err(|_| ApiError::BadRequest)?
+        .read_to_string(&mut text)
+        .await
+        .map_err(|_| ApiError::BadRequest)?;
+
+    let mut rows = match parse_csv(&text) {
+        Ok(rows) => rows,
+        Err(error) => return Ok(unprocessable(&error, json!({}))),
+    };
+
+    // The whole file has to fit in the subscription before anything is checked against the database.
+    // The insert checks it again in its transaction, in case other codes were created meanwhile.
+    let (tier, available) = available_usage(db, &owner).await?;
+
+    if rows.len() as i32 > available {
+        return Err(ApiError::BadRequest);
+    }
+
+    let slugs = rows
+        .iter()
+        .filter_map(|row| row.code.as_ref().and_then(|code| code.slug.clone()))
+        .collect::<Vec<_>>();
+    let taken = db.slugs_taken(slugs).await?;
+
+    for row in rows.iter_mut() {
+        let slug_taken = row
+            .code
+            .as_ref()
+            .and_then(|code| code.slug.as_ref())
+            .is_some_and(|slug| taken.contains(slug));
+
+        if slug_taken {
+            row.code = None;
+            row.report.status = RowStatus::Invalid;
+            row.report.errors.push("Slug is already taken".to_string());
+        }
+    }
+
+    if rows.iter().any(|row| row.code.is_none()) {
+        let report = ImportReport {
+            created: 0,
+            rows: rows.into_iter().map(|row| row.report).collect(),
+        };
+
+        return Ok(unprocessable("Some rows are invalid", json!(report)));
+    }
+
+    let codes = rows.iter_mut().filter_map(|row| row.code.take()).collect();
+    let created = db
+        .insert_dynamic_urls(user_id, &owner, codes, &tier)
+        .await?;
+
+    let report = ImportReport {
+        created: created.len(),
+        rows: rows
+            .into_iter()
+            .zip(created)
+            .map(|(row, qrcode)| {
+                let mut report = row.report;
+                report.status = RowStatus::Created;
+                report.id = Some(qrcode.id().clone());
+                report.server_url = Some(qrcode.server_url().to_string());
+                report
+            })
+            .collect(),
+    };
+
+    Ok(status::Custom(
+        Status::Created,
+        Json(ApiResponse {
+            status: Status::Created.code,
+            message: "Dynamic URLs created".to_string(),
+            data: json!(report),
+        }),
+    ))
+}
+
+#[patch("/user/<user_id>/qrcode?<org>", format = "json", data = "<update>")]
+pub async fn bulk_update_qrcodes(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    org: Option<&str>,
+    update: Json<BulkUpdate>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Applies one operation to many dynamic URLs, all or nothing.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org: optional organization ID, when the codes belong to the organization.
+            update: the dynamic URL IDs and the operation, tagged by `type`: `retarget` with `target_url`,
+                    `replace_domain` with `find` and `replace`, `retag` with `add` and `remove`, `pause` or `resume`.
+
+        Returns:
+            Response<Json<ApiResponse>>: a summary of how many codes were updated and left unchanged.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+
+    let org = caller.workspace(org)?;
+
+    let owner = workspace(db, user_id, org.as_deref(), Role::Editor).await?;
+
+    let BulkUpdate { ids, mut operation } = update.into_inner();
+
+    let qrcode_ids = parse_ids(&ids).ok_or(ApiError::BadRequest)?;
+
+    if !normalize(&mut operation) {
+        return Err(ApiError::BadRequest);
+    }
+
+    // On tiers where paused codes are free, resumed codes take up room again.
+    if matches!(operation, BulkOperation::Resume) {
+        let (tier, available) = available_usage(db, &owner).await?;
+
+        if !tier.counts_paused() && qrcode_ids.len() as i32 > available {
+            return Err(ApiError::BadRequest);
+        }
+    }
+
+    let summary = db
+        .bulk_update_dynamic_urls(user_id, &owner, &qrcode_ids, &operation)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Dynamic URLs updated".to_string(),
+        data: json!(summary),
+    }))
+}
+
+#[delete("/user/<user_id>/qrcode?<org>", format = "json", data = "<delete>")]
+pub async fn bulk_delete_qrcodes(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    org: Option<&str>,
+    delete: Json<BulkDelete>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Moves many dynamic URLs to the trash and gives their usage back, all or nothing.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org: optional organization ID, when the codes belong to the organization.
+            delete: the dynamic URL IDs.
+
+        Returns:
+            Response<Json<ApiResponse>>: a summary of how many codes were trashed.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+
+    let org = caller.workspace(org)?;
+
+    let qrcode_ids = parse_ids(&delete.ids).ok_or(ApiError::BadRequest)?;
+
+    let owner = workspace(db, user_id, org.as_deref(), Role::Editor).await?;
+
+    validate_and_get_subscription(db, &owner).await?;
+
+    let deleted = db.delete_dynamic_urls(&owner, &qrcode_ids).await?;
+
+    let summary = BulkSummary {
+        requested: qrcode_ids.len(),
+        deleted,
+        ..Default::default()
+    };
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Dynamic URLs moved to trash".to_string(),
+        data: json!(summary),
+    }))
+}
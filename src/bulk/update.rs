@@ -0,0 +1,173 @@
+use crate::bulk::import::validate_target_url;
+use crate::bulk::models::{BulkChange, BulkOperation, BulkTarget, MAX_BULK_IDS};
+use crate::database::models::{QrCodeId, QrStatus, QrTarget, MAX_TAGS, MAX_TAG_LENGTH};
+
+pub fn parse_ids(ids: &[String]) -> Option<Vec<QrCodeId>> {
+    /*
+        Parses the IDs of a bulk request, dropping duplicates.
+
+        Returns:
+            Option<Vec<QrCodeId>>: The IDs, or None if there are none, too many or any is malformed.
+    */
+
+    if ids.is_empty() || ids.len() > MAX_BULK_IDS {
+        return None;
+    }
+
+    let mut parsed: Vec<QrCodeId> = Vec::with_capacity(ids.len());
+
+    for id in ids {
+        let id = QrCodeId::parse(id)?;
+        if !parsed.contains(&id) {
+            parsed.push(id);
+        }
+    }
+
+    Some(parsed)
+}
+
+fn valid_host(host: &str) -> bool {
+    !host.is_empty()
+        && host.len() <= 253
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+}
+
+fn normalize_tags(tags: &mut Vec<String>) -> bool {
+    let mut normalized: Vec<String> = Vec::new();
+
+    for tag in tags.drain(..) {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    *tags = normalized;
+
+    tags.iter().all(|tag| tag.chars().count() <= MAX_TAG_LENGTH)
+}
+
+pub fn normalize(operation: &mut BulkOperation) -> bool {
+    /*
+        Cleans up an operation's arguments the same way single updates are.
+
+        Returns:
+            bool: false if the arguments are invalid, e.g. a malformed target URL or domain.
+    */
+
+    match operation {
+        BulkOperation::Retarget { target_url } => {
+            *target_url = target_url.trim().to_string();
+            validate_target_url(target_url).is_ok()
+        }
+        BulkOperation::ReplaceDomain { find, replace } => {
+            *find = find.trim().to_ascii_lowercase();
+            *replace = replace.trim().to_ascii_lowercase();
+            valid_host(find) && valid_host(replace)
+        }
+        BulkOperation::Retag { add, remove } => {
+            normalize_tags(add) && normalize_tags(remove) && !(add.is_empty() && remove.is_empty())
+        }
+        BulkOperation::Pause | BulkOperation::Resume => true,
+    }
+}
+
+pub fn replace_domain(target_url: &str, find: &str, replace: &str) -> Option<String> {
+    /*
+        Swaps the host of a target URL, leaving its scheme, port, path and query as they were.
+
+        Returns:
+            Option<String>: The new target, or None if the host isn't `find`.
+    */
+
+    let start = target_url.find("://").map_or(0, |index| index + 3);
+    let rest = &target_url[start..];
+    let end = rest.find(['/', '?', '#', ':']).unwrap_or(rest.len());
+
+    if !rest[..end].eq_ignore_ascii_case(find) {
+        return None;
+    }
+
+    Some(format!(
+        "{}{}{}",
+        &target_url[..start],
+        replace,
+        &rest[end..]
+    ))
+}
+
+pub fn plan(
+    operation: &BulkOperation,
+    target: &BulkTarget,
+) -> Result<Option<BulkChange>, &'static str> {
+    /*
+        Works out what an operation changes on one code.
+
+        Returns:
+            Result<Option<BulkChange>, &str>: The change, None if the code is already as requested,
+            or why the operation can't apply to it.
+    */
+
+    let mut change = BulkChange {
+        id: target.id.record(),
+        target_url: None,
+        tags: None,
+        status: None,
+    };
+
+    let retargets = matches!(
+        operation,
+        BulkOperation::Retarget { .. } | BulkOperation::ReplaceDomain { .. }
+    );
+
+    if retargets && target.target_type != QrTarget::Url {
+        return Err("Only URL codes have a target URL");
+    }
+
+    match operation {
+        BulkOperation::Retarget { target_url } => {
+            if &target.target_url != target_url {
+                change.target_url = Some(target_url.clone());
+            }
+        }
+        BulkOperation::ReplaceDomain { find, replace } => {
+            change.target_url = replace_domain(&target.target_url, find, replace)
+                .filter(|target_url| target_url != &target.target_url);
+        }
+        BulkOperation::Retag { add, remove } => {
+            let mut tags: Vec<String> = target
+                .tags
+                .iter()
+                .filter(|tag| !remove.contains(tag))
+                .cloned()
+                .collect();
+            for tag in add {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+
+            if tags.len() > MAX_TAGS {
+                return Err("Too many tags");
+            }
+            if tags != target.tags {
+                change.tags = Some(tags);
+            }
+        }
+        BulkOperation::Pause => {
+            if target.status != QrStatus::Paused {
+                change.status = Some(QrStatus::Paused);
+            }
+        }
+        BulkOperation::Resume => {
+            if target.status != QrStatus::Active {
+                change.status = Some(QrStatus::Active);
+            }
+        }
+    }
+
+    let changed = change.target_url.is_some() || change.tags.is_some() || change.status.is_some();
+
+    Ok(changed.then_some(change))
+}
@@ -0,0 +1,4 @@
+pub mod models;
+pub mod render;
+pub mod routes;
+pub mod vcard;
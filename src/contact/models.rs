@@ -0,0 +1,139 @@
+use crate::bulk::import::validate_target_url;
+use crate::transfer::models::valid_email;
+
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+
+pub const MAX_NAME_LENGTH: usize = 100;
+pub const MAX_PHONE_LENGTH: usize = 32;
+pub const MAX_ADDRESS_LENGTH: usize = 300;
+pub const MAX_NOTE_LENGTH: usize = 500;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContactCard {
+    pub first_name: String,
+    #[serde(default)]
+    pub last_name: Option<String>,
+    #[serde(default)]
+    pub organization: Option<String>,
+    #[serde(default)]
+    pub job_title: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    // Work and mobile numbers, written as people read them, e.g. `+1 (555) 010-0199`.
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub mobile: Option<String>,
+    #[serde(default)]
+    pub website: Option<String>,
+    // A postal address on one or more lines.
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    // The URL of a portrait or logo, shown on the page and linked from the vCard.
+    #[serde(default)]
+    pub photo: Option<String>,
+    // Scans download the .vcf straight away instead of showing the contact page.
+    #[serde(default)]
+    pub download: bool,
+}
+
+fn valid_phone(phone: &str) -> bool {
+    phone.len() <= MAX_PHONE_LENGTH
+        && phone.chars().any(|c| c.is_ascii_digit())
+        && phone
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '+' | ' ' | '-' | '(' | ')' | '.'))
+}
+
+impl ContactCard {
+    pub fn normalize(&mut self) -> bool {
+        /*
+            Trims every field and drops blank optional ones.
+
+            Returns:
+                bool: false if the first name is blank, a field is too long, or the email, a phone
+                number, the website or the photo URL is malformed.
+        */
+
+        let trim = |field: &mut Option<String>| {
+            *field = field
+                .take()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty());
+        };
+
+        self.first_name = self.first_name.trim().to_string();
+        trim(&mut self.last_name);
+        trim(&mut self.organization);
+        trim(&mut self.job_title);
+        trim(&mut self.email);
+        trim(&mut self.phone);
+        trim(&mut self.mobile);
+        trim(&mut self.website);
+        trim(&mut self.address);
+        trim(&mut self.note);
+        trim(&mut self.photo);
+
+        let fits = |field: &Option<String>, limit: usize| {
+            field
+                .as_deref()
+                .is_none_or(|value| value.chars().count() <= limit)
+        };
+
+        !self.first_name.is_empty()
+            && self.first_name.chars().count() <= MAX_NAME_LENGTH
+            && [&self.last_name, &self.organization, &self.job_title]
+                .iter()
+                .all(|field| fits(field, MAX_NAME_LENGTH))
+            && fits(&self.address, MAX_ADDRESS_LENGTH)
+            && fits(&self.note, MAX_NOTE_LENGTH)
+            && self.email.as_deref().is_none_or(valid_email)
+            && [&self.phone, &self.mobile]
+                .iter()
+                .all(|phone| phone.as_deref().is_none_or(valid_phone))
+            && [&self.website, &self.photo].iter().all(|url| {
+                url.as_deref()
+                    .is_none_or(|url| validate_target_url(url).is_ok())
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContactCardResult {
+    pub first_name: String,
+    #[serde(default)]
+    pub last_name: Option<String>,
+    #[serde(default)]
+    pub organization: Option<String>,
+    #[serde(default)]
+    pub job_title: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub mobile: Option<String>,
+    #[serde(default)]
+    pub website: Option<String>,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub photo: Option<String>,
+    #[serde(default)]
+    pub download: bool,
+    pub updated_at: Datetime,
+}
+
+impl ContactCardResult {
+    pub fn full_name(&self) -> String {
+        match &self.last_name {
+            Some(last_name) => format!("{} {}", self.first_name, last_name),
+            None => self.first_name.clone(),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::contact::models::ContactCardResult;
+use crate::contact::vcard::tel_uri;
+use crate::database::models::QrColor;
+use crate::routes::qrcode::redirect_location;
+use crate::scan::page::escape_html;
+
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder};
+use std::io::Cursor;
+
+const DEFAULT_ACCENT: &str = "#222222";
+
+// The contact page of a contact code, answered to scans unless the card downloads straight away.
+pub struct ContactView {
+    server_url: String,
+    card: ContactCardResult,
+    accent: &'static str,
+}
+
+impl ContactView {
+    pub fn new(server_url: &str, card: ContactCardResult, color: Option<QrColor>) -> Self {
+        ContactView {
+            server_url: server_url.to_string(),
+            card,
+            accent: color.map_or(DEFAULT_ACCENT, |color| color.hex()),
+        }
+    }
+
+    pub fn html(&self) -> String {
+        let card = &self.card;
+
+        let photo = card.photo.as_deref().map_or(String::new(), |photo| {
+            format!(
+                "<img src=\"{}\" alt=\"\">",
+                escape_html(&redirect_location(photo))
+            )
+        });
+
+        let role = [card.job_title.as_deref(), card.organization.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(escape_html)
+            .collect::<Vec<String>>()
+            .join(" · ");
+
+        let mut rows: Vec<String> = Vec::new();
+
+        if let Some(email) = &card.email {
+            rows.push(format!(
+                "<li><a href=\"mailto:{}\">{}</a></li>",
+                escape_html(email),
+                escape_html(email)
+            ));
+        }
+        for phone in [&card.phone, &card.mobile].into_iter().flatten() {
+            rows.push(format!(
+                "<li><a href=\"{}\">{}</a></li>",
+                escape_html(&tel_uri(phone)),
+                escape_html(phone)
+            ));
+        }
+        if let Some(website) = &card.website {
+            rows.push(format!(
+                "<li><a href=\"{}\" rel=\"noopener\">{}</a></li>",
+                escape_html(&redirect_location(website)),
+                escape_html(website)
+            ));
+        }
+        if let Some(address) = &card.address {
+            rows.push(format!(
+                "<li>{}</li>",
+                escape_html(address).replace('\n', "<br>")
+            ));
+        }
+        if let Some(note) = &card.note {
+            rows.push(format!("<li>{}</li>", escape_html(note)));
+        }
+
+        // The button fetches the same card as a .vcf, which phones offer to save.
+        format!(
+            "<!DOCTYPE html>\
+            <html lang=\"en\">\
+            <head>\
+            <meta charset=\"utf-8\">\
+            <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+            <title>{name}</title>\
+            <style>body{{font-family:system-ui,sans-serif;margin:0;color:#222;border-top:6px solid {accent}}}main{{padding:32px 24px;max-width:480px;margin:0 auto;text-align:center}}img{{width:112px;height:112px;border-radius:50%;object-fit:cover}}ul{{list-style:none;padding:0}}li{{margin:10px 0}}li a{{color:{accent}}}.add{{display:block;padding:14px;margin-top:24px;border-radius:8px;background:{accent};color:#fff;text-decoration:none;font-weight:600}}</style>\
+            </head>\
+            <body><main>{photo}<h1>{name}</h1><p>{role}</p><ul>{rows}</ul>\
+            <a class=\"add\" href=\"/scan/{server_url}/contact.vcf\">Add to contacts</a>\
+            </main></body>\
+            </html>",
+            name = escape_html(&card.full_name()),
+            accent = self.accent,
+            photo = photo,
+            role = role,
+            rows = rows.concat(),
+            server_url = escape_html(&self.server_url),
+        )
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ContactView {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> response::Result<'static> {
+        let body = self.html();
+
+        rocket::Response::build()
+            .status(Status::Ok)
+            .header(ContentType::HTML)
+            .header(Header::new("Cache-Control", "no-store"))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
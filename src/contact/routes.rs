@@ -0,0 +1,126 @@
+use crate::apikey::models::ApiScope;
+use crate::contact::models::ContactCard;
+use crate::contact::vcard::VCardFile;
+use crate::database::database::Database;
+use crate::database::models::{QrCodeId, QrSlug, QrTarget};
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::routes::guard::Caller;
+use crate::routes::qrcode::{unavailable, ScanResponse};
+use crate::routes::user::reach_code;
+use crate::utils::Environments;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{get, put};
+use serde_json::json;
+
+#[get("/user/<user_id>/qrcode/<qrcode_id>/contact")]
+pub async fn read_contact(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Reads the contact card of a contact code.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            qrcode_id: the contact code ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the contact card object, or null before it is first set, as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let card = db.select_contact_card(user_id, &qrcode_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Contact card".to_string(),
+        data: json!({"contact": card}),
+    }))
+}
+
+#[put(
+    "/user/<user_id>/qrcode/<qrcode_id>/contact",
+    format = "json",
+    data = "<card>"
+)]
+pub async fn update_contact(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+    card: Json<ContactCard>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Replaces the contact card of a contact code. Printed codes show the new details on their
+        next scan.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            qrcode_id: the contact code ID.
+            card: the required `first_name` and optional `last_name`, `organization`, `job_title`,
+                  `email`, `phone`, `mobile`, `website`, `address`, `note` and `photo` URL, and
+                  `download` to have scans download the .vcf instead of showing the page.
+
+        Returns:
+            Response<Json<ApiResponse>>: the updated contact card object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let mut card = card.into_inner();
+
+    if !card.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    let updated = db.update_contact_card(user_id, &qrcode_id, &card).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Contact card updated".to_string(),
+        data: json!({"contact": updated}),
+    }))
+}
+
+#[get("/scan/<server_url>/contact.vcf")]
+pub async fn download_contact(
+    server_url: QrSlug,
+    db: &State<Database>,
+    env: &State<Environments>,
+) -> Response<ScanResponse> {
+    /*
+       Downloads the contact card of a contact code as a vCard, from its page's "add to contacts"
+       button. Not counted as a scan, the page already was.
+
+       Params:
+           server_url (QrSlug): The server URL of the contact code.
+
+       Returns:
+           Response<ScanResponse>: The .vcf file, or the page a paused or trashed code answers with.
+
+    */
+
+    let link = db.lookup_dynamic_url(&server_url).await?;
+
+    if let Some(page) = unavailable(&link, env) {
+        return Ok(page);
+    }
+
+    if link.target_type != QrTarget::Contact {
+        return Err(ApiError::NotFound);
+    }
+
+    let card = db.lookup_contact_card(&server_url).await?;
+
+    Ok(ScanResponse::VCard(VCardFile::new(&card)))
+}
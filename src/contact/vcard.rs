@@ -0,0 +1,164 @@
+use crate::contact::models::ContactCardResult;
+use crate::routes::qrcode::redirect_location;
+
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder};
+use std::io::Cursor;
+
+// Content lines longer than this many octets are folded, as RFC 6350 recommends.
+const MAX_LINE_OCTETS: usize = 75;
+
+fn escape_text(value: &str) -> String {
+    // Backslashes, commas, semicolons and line breaks are escaped in text values.
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ',' => escaped.push_str("\\,"),
+            ';' => escaped.push_str("\\;"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn fold(line: &str) -> String {
+    // Continuation lines start with a space, and a character is never split across lines.
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut octets = 0;
+
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
+
+pub fn tel_uri(phone: &str) -> String {
+    // Only the digits and a leading plus are dialled.
+    let digits: String = phone
+        .chars()
+        .enumerate()
+        .filter(|(index, c)| c.is_ascii_digit() || (*index == 0 && *c == '+'))
+        .map(|(_, c)| c)
+        .collect();
+
+    format!("tel:{}", digits)
+}
+
+pub fn vcard(card: &ContactCardResult) -> String {
+    /*
+        Writes a contact card as a vCard 4.0 file.
+
+        Returns:
+            String: The vCard, with CRLF line endings and long lines folded.
+    */
+
+    let mut lines = vec![
+        "BEGIN:VCARD".to_string(),
+        "VERSION:4.0".to_string(),
+        format!("FN:{}", escape_text(&card.full_name())),
+        format!(
+            "N:{};{};;;",
+            escape_text(card.last_name.as_deref().unwrap_or("")),
+            escape_text(&card.first_name)
+        ),
+    ];
+
+    if let Some(organization) = &card.organization {
+        lines.push(format!("ORG:{}", escape_text(organization)));
+    }
+    if let Some(job_title) = &card.job_title {
+        lines.push(format!("TITLE:{}", escape_text(job_title)));
+    }
+    if let Some(email) = &card.email {
+        lines.push(format!("EMAIL;TYPE=work:{}", escape_text(email)));
+    }
+    if let Some(phone) = &card.phone {
+        lines.push(format!(
+            "TEL;VALUE=uri;TYPE=\"work,voice\":{}",
+            tel_uri(phone)
+        ));
+    }
+    if let Some(mobile) = &card.mobile {
+        lines.push(format!(
+            "TEL;VALUE=uri;TYPE=\"cell,voice\":{}",
+            tel_uri(mobile)
+        ));
+    }
+    if let Some(website) = &card.website {
+        lines.push(format!("URL:{}", redirect_location(website)));
+    }
+    if let Some(address) = &card.address {
+        // The whole address goes in the street component, apps show it as written.
+        lines.push(format!("ADR;TYPE=work:;;{};;;;", escape_text(address)));
+    }
+    if let Some(note) = &card.note {
+        lines.push(format!("NOTE:{}", escape_text(note)));
+    }
+    if let Some(photo) = &card.photo {
+        lines.push(format!("PHOTO:{}", redirect_location(photo)));
+    }
+
+    lines.push(format!("REV:{}", card.updated_at.format("%Y%m%dT%H%M%SZ")));
+    lines.push("END:VCARD".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+pub fn file_name(card: &ContactCardResult) -> String {
+    // Only ASCII letters and digits are kept, so the name is safe in a header.
+    let name: String = card
+        .full_name()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let name: Vec<&str> = name.split('-').filter(|part| !part.is_empty()).collect();
+
+    if name.is_empty() {
+        "contact.vcf".to_string()
+    } else {
+        format!("{}.vcf", name.join("-"))
+    }
+}
+
+// A contact card served as a .vcf download.
+pub struct VCardFile {
+    file_name: String,
+    body: String,
+}
+
+impl VCardFile {
+    pub fn new(card: &ContactCardResult) -> Self {
+        VCardFile {
+            file_name: file_name(card),
+            body: vcard(card),
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for VCardFile {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> response::Result<'static> {
+        // Cards can be edited at any time, like a redirect target.
+        rocket::Response::build()
+            .status(Status::Ok)
+            .header(ContentType::new("text", "vcard").with_params(("charset", "utf-8")))
+            .header(Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", self.file_name),
+            ))
+            .header(Header::new("Cache-Control", "no-store"))
+            .sized_body(self.body.len(), Cursor::new(self.body))
+            .ok()
+    }
+}
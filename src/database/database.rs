@@ -1,15 +1,75 @@
-use crate::database::models::{self, format_user_id};
+use crate::apikey::models::{ApiKeyGrant, ApiKeyResult, NewApiKey};
+use crate::applink::models::{AppLink, AppLinkResult};
+use crate::bulk::models::{BulkOperation, BulkSummary, BulkTarget, NewQrCode};
+use crate::bulk::update::plan;
+use crate::contact::models::{ContactCard, ContactCardResult};
+use crate::database::models::{self, format_user_id, ApiKeyId, FolderId, InvitationId, OrgId};
 use crate::errors::{ApiError, Response};
+use crate::folder::models::{
+    descendants, rollup, Folder, FolderDeleteMode, FolderDeleted, FolderResult,
+};
+use crate::hosted::models::{HostedFile, HostedFileResult};
+use crate::invitation::models::{InvitationResult, InvitationStatus, INVITATION_EXPIRY_DAYS};
+use crate::landing::models::{LandingPage, LandingPageResult, LinkRecord};
+use crate::organization::models::{
+    CodeAccess, MemberResult, MembershipResult, NewMember, Organization, OrganizationResult, Role,
+};
+use crate::scan::batch::ScanBuffer;
+use crate::scan::bot::ClientKind;
+use crate::scan::cache::{CacheStats, ScanCache};
+use crate::transfer::models::{TransferList, TransferResult, TransferStatus, TRANSFER_EXPIRY_DAYS};
+use crate::trash::purge::TRASH_RETENTION_DAYS;
 use crate::utils::Environments;
+use crate::webhook::delivery::{backoff, DeliveryOutcome, MAX_ATTEMPTS};
+use crate::webhook::models::{
+    DeliveryStatus, DueDelivery, Webhook, WebhookDeliveryResult, WebhookResult, WebhookSecretResult,
+};
 
-use surrealdb::engine::remote::ws::{Client, Wss};
+use surrealdb::engine::any::{self, Any};
 use surrealdb::opt::auth::Root;
-use surrealdb::Surreal;
+use surrealdb::sql::Datetime;
+use surrealdb::{RecordId, Surreal};
 
-use super::models::UserResult;
+use std::sync::Arc;
 
+// Thrown by queries that would take an owner past their tier's code limit.
+const USAGE_LIMIT_REACHED: &str = "Usage limit reached";
+
+// Thrown by queries that would take an owner past their tier's storage limit.
+const STORAGE_LIMIT_REACHED: &str = "Storage limit reached";
+
+// Thrown by queries whose record was changed or removed by another request first.
+const NO_LONGER_AVAILABLE: &str = "No longer available";
+
+// Dynamic URL fields plus the folder the code is filed in, if any.
+const QR_FIELDS: &str = "*, (->filed_in->folder)[0] AS folder";
+
+// Revision fields plus the scans made while the revision was live.
+const REVISION_FIELDS: &str = "id, previous_target, target_url, record::id(author) AS author, restored_from, (SELECT count() FROM scan WHERE revision = $parent.id AND client = 'human' GROUP ALL)[0].count ?? 0 AS access_count, (SELECT count() FROM scan WHERE revision = $parent.id AND client = 'bot' GROUP ALL)[0].count ?? 0 AS bot_count, created_at";
+
+// Transfer fields with the sender as a plain user ID.
+const TRANSFER_FIELDS: &str = "id, record::id(sender) AS sender, recipient_email, qrcodes, status, created_at, expires_at, resolved_at";
+
+// Landing page fields with its links in page order.
+const PAGE_FIELDS: &str = "title, avatar, updated_at, (SELECT id, title, url, clicks, bot_clicks, position FROM landing_link WHERE page = $parent.id ORDER BY position) AS links";
+
+// Folder fields plus the counts of the codes filed directly in it, leaving out trashed codes.
+const FOLDER_FIELDS: &str = "id, name, parent, created_at, updated_at, count(<-filed_in<-(dynamic_url WHERE deleted_at = NONE)) AS qrcode_count, math::sum(<-filed_in<-(dynamic_url WHERE deleted_at = NONE).access_count) AS access_count, math::sum(<-filed_in<-(dynamic_url WHERE deleted_at = NONE).bot_count) AS bot_count";
+
+// Member fields with the member as a plain user ID.
+const MEMBER_FIELDS: &str = "record::id(in) AS user, in.username AS username, in.email AS email, role, created_at AS joined_at";
+
+// API key fields with the maker as a plain user ID, leaving out the key hash.
+const API_KEY_FIELDS: &str = "id, name, prefix, scopes, record::id(user) AS created_by, created_at, expires_at, last_used_at";
+
+// Invitation fields with the sender as a plain user ID, leaving out the token hash.
+const INVITATION_FIELDS: &str = "id, organization, email, role, record::id(invited_by) AS invited_by, status, created_at, expires_at, resolved_at";
+
+#[derive(Clone)]
 pub struct Database {
-    db: Surreal<Client>, //  Holds a private instance of the SurrealDB connection to restrict query access.
+    db: Surreal<Any>, //  Holds a private instance of the SurrealDB connection to restrict query access.
+    cache: Arc<ScanCache>, //  Resolved scan redirects, invalidated whenever a dynamic URL changes.
+    scans: Arc<ScanBuffer>, //  Scans waiting to be written by `flush_scans`.
 }
 
 impl Database {
@@ -24,22 +84,170 @@ impl Database {
         - `dynamic_url` table:
             - `id` (string): Unique identifier for the dynamic URL.
             - `server_url` (string): The server URL that will be shortened or dynamic.
-            - `target_url` (string): The original destination URL that the dynamic URL points to.
+            - `target_url` (string): The original destination URL that the dynamic URL points to, empty for page, file, app and contact codes.
+            - `target_type` (string): What scans resolve to, `url` by default, a hosted `page`, an uploaded `file`, an `app` deep link or a `contact` card.
+            - `redirect_code` (int): The HTTP redirect status used when scanned (301, 302, 307 or 308).
+            - `access_count` (int): Number of scans by people.
+            - `bot_count` (int): Number of scans by crawlers, link previews and scanners, kept out of `access_count`.
+            - `status` (string): The lifecycle state of the code, `active` by default or `paused`.
+            - `name` (string): Optional name to tell codes apart.
+            - `description` (string): Optional free-form notes.
+            - `tags` (array): Lowercase free-form tags.
+            - `color` (string): Optional color label.
+            - `deleted_at` (datetime): When the dynamic URL was moved to the trash, none while it is live.
+            - `last_accessed` (datetime): Timestamp of the last scan by a person.
             - `created_at` (datetime): Timestamp of when the dynamic URL was created.
             - `updated_at` (datetime): Timestamp of the last update to the dynamic URL.
+
+        - `created` relation, `user->created->dynamic_url` or `organization->created->dynamic_url`:
+          The owner of a code, whose subscription its usage counts against.
+
+        - `organization` table, a team that owns codes and a subscription together:
+            - `name` (string): The organization's name.
+            - `created_at` (datetime): Timestamp of when the organization was created.
+
+        - `member_of` relation, `user->member_of->organization`, at most one per user and organization:
+            - `role` (string): `owner`, `admin`, `editor` or `viewer`.
+            - `created_at` (datetime): Timestamp of when the user joined.
+
+        - `invitation` table, an invitation to join an organization sent by email:
+            - `organization` (record): The organization to join.
+            - `email` (string): Lowercase email of the account that may accept.
+            - `role` (string): The role the invitee gets on accepting.
+            - `invited_by` (record): The member who sent or last resent it.
+            - `token_hash` (string): SHA-256 of the mailed token, the token itself isn't stored.
+            - `recipient` (record): The user that accepted or declined, once they did.
+            - `status` (string): `pending`, `accepted`, `declined` or `revoked`.
+            - `created_at` (datetime): Timestamp of when the invitation was sent.
+            - `expires_at` (datetime): After this a pending invitation can't be accepted until resent.
+            - `resolved_at` (datetime): Timestamp of the answer or revocation.
+
+        - `api_key` table, a key scripts use instead of an Auth0 token:
+            - `owner` (record): The user or organization whose codes the key reaches.
+            - `user` (record): The user who made the key and whom it acts as.
+            - `name` (string): A label for the key.
+            - `prefix` (string): The start of the key, shown to tell keys apart.
+            - `key_hash` (string): SHA-256 of the key, the key itself isn't stored.
+            - `scopes` (array): `qrcode:read`, `qrcode:write` and/or `analytics:read`.
+            - `created_at` (datetime): Timestamp of when the key was made.
+            - `expires_at` (datetime): After this the key stops working, none for keys that don't expire.
+            - `last_used_at` (datetime): Timestamp of the last request made with the key.
+
+        - `folder` table, related to its owner through `user->owns->folder`:
+            - `name` (string): The folder name.
+            - `parent` (record): The folder it is nested in, if any.
+            - `created_at` (datetime): Timestamp of when the folder was created.
+            - `updated_at` (datetime): Timestamp of the last rename or move.
+
+        - `filed_in` relation, `dynamic_url->filed_in->folder`: The folder a code is filed in, at most one per code.
+
+        - `dynamic_url_revision` table, one record per target URL a code has had:
+            - `dynamic_url` (record): The dynamic URL that changed.
+            - `previous_target` (string): The target URL before the change, none for the first revision.
+            - `target_url` (string): The target URL from this revision on.
+            - `author` (record): The user that made the change.
+            - `restored_from` (record): The revision rolled back to, if the change was a rollback.
+            - `created_at` (datetime): Timestamp of the change.
+
+        - `landing_page` table, the hosted page of a page code, keyed like its dynamic URL:
+            - `dynamic_url` (record): The page code the page belongs to.
+            - `title` (string): The heading of the page.
+            - `avatar` (string): Optional URL of an image shown above the title.
+            - `updated_at` (datetime): Timestamp of the last edit.
+
+        - `landing_link` table, one record per link on a landing page:
+            - `page` (record): The landing page the link is on.
+            - `position` (int): The link's place on the page, from 0.
+            - `title` (string): The link text.
+            - `url` (string): Where the link goes.
+            - `clicks` (int): Number of clicks by people.
+            - `bot_clicks` (int): Number of clicks by crawlers and link previews.
+
+        - `hosted_file` table, the uploaded file of a file code, keyed like its dynamic URL:
+            - `dynamic_url` (record): The file code the file belongs to.
+            - `file_name` (string): The uploaded file's name, with the extension of its detected type.
+            - `content_type` (string): The detected content type.
+            - `size` (int): The size in bytes.
+            - `path` (string): Where the file is stored on disk.
+            - `updated_at` (datetime): Timestamp of the last upload.
+
+        - `app_link` table, the deep links of an app code, keyed like its dynamic URL:
+            - `dynamic_url` (record): The app code the links belong to.
+            - `ios_url` (string): Optional deep link opened on iOS.
+            - `android_url` (string): Optional deep link opened on Android.
+            - `ios_store_url` (string): Optional App Store listing, the fallback on iOS.
+            - `android_store_url` (string): Optional Play Store listing, the fallback on Android.
+            - `fallback_url` (string): Where other devices, and phones without a store link, go.
+            - `updated_at` (datetime): Timestamp of the last edit.
+
+        - `contact_card` table, the contact details of a contact code, keyed like its dynamic URL:
+            - `dynamic_url` (record): The contact code the card belongs to.
+            - `first_name` (string): The contact's first name.
+            - `last_name`, `organization`, `job_title` (string): Optional name and role.
+            - `email`, `phone`, `mobile`, `website`, `address`, `note` (string): Optional details.
+            - `photo` (string): Optional URL of a portrait or logo.
+            - `download` (bool): Whether scans download the vCard instead of showing the page.
+            - `updated_at` (datetime): Timestamp of the last edit.
+
+        - `transfer` table, a handover of codes from one user to another:
+            - `sender` (record): The user handing the codes over.
+            - `recipient_email` (string): Lowercase email of the account that may accept.
+            - `recipient` (record): The user that accepted or declined, once they did.
+            - `qrcodes` (array): The dynamic URLs being handed over.
+            - `status` (string): `pending`, `accepted`, `declined` or `cancelled`.
+            - `created_at` (datetime): Timestamp of when the transfer was started.
+            - `expires_at` (datetime): After this a pending transfer can't be accepted.
+            - `resolved_at` (datetime): Timestamp of the answer or cancellation.
+
+        - `scan` table:
+            - `dynamic_url` (record): The dynamic URL that was scanned.
+            - `revision` (record): The revision that was live when the code was scanned.
+            - `client` (string): `human` or `bot`.
+            - `scanned_at` (datetime): Timestamp of the scan.
+
+        - `webhook` table, related to its owner through `user->registered->webhook` or
+          `organization->registered->webhook`:
+            - `url` (string): The endpoint that receives scan events.
+            - `secret` (string): Key used to sign each delivery with HMAC-SHA256.
+            - `mode` (string): `scan` for one delivery per scan, `minute` for one per minute.
+            - `include_bots` (bool): Whether bot scans are sent as well.
+            - `active` (bool): Whether new events are queued for the webhook.
+            - `created_at` (datetime): Timestamp of when the webhook was registered.
+
+        - `webhook_delivery` table:
+            - `webhook` (record): The webhook the delivery is for.
+            - `event` (string): `scan` or `scan.batch`.
+            - `payload` (object): The JSON body sent to the endpoint.
+            - `status` (string): `pending`, `retrying`, `delivered` or `dead`.
+            - `attempts` (int): Number of delivery attempts so far.
+            - `next_attempt_at` (datetime): When the dispatcher should next send it.
+            - `last_status` (int): HTTP status of the last attempt, if any.
+            - `last_error` (string): Error of the last failed attempt, if any.
+            - `created_at` (datetime): Timestamp of when the delivery was queued.
+            - `delivered_at` (datetime): Timestamp of the successful attempt.
     */
 
     pub async fn new(secrets: &Environments) -> Response<Self> {
         // Establish a connection to the database using the provided URL.
-        let db = Surreal::new::<Wss>(&secrets.get("DATABASE_URL")).await?;
+        let db = any::connect(format!("wss://{}", secrets.get("DATABASE_URL"))).await?;
 
         // Sign in using the provided credentials.
         db.signin(Root {
-            username: &secrets.get("DATABASE_USERNAME").as_str(),
-            password: &secrets.get("DATABASE_PASSWORD").as_str(),
+            username: secrets.get("DATABASE_USERNAME").as_str(),
+            password: secrets.get("DATABASE_PASSWORD").as_str(),
         })
         .await?;
 
+        Self::define(db).await
+    }
+
+    #[cfg(test)]
+    pub async fn memory() -> Response<Self> {
+        // An empty in-memory database with the same schema, for tests.
+        Self::define(any::connect("mem://").await?).await
+    }
+
+    async fn define(db: Surreal<Any>) -> Response<Self> {
         // Set the namespace and database to use.
         db.use_ns("ns").use_db("db").await?;
 
@@ -69,42 +277,424 @@ impl Database {
         DEFINE FIELD id ON dynamic_url TYPE string ASSERT $value != NONE;
         DEFINE FIELD server_url ON dynamic_url TYPE string ASSERT $value != NONE;
         DEFINE FIELD target_url ON dynamic_url TYPE string ASSERT $value != NONE;
+        DEFINE FIELD target_type ON dynamic_url TYPE string DEFAULT 'url' ASSERT $value INSIDE ['url', 'page', 'file', 'app', 'contact'];
+        DEFINE FIELD redirect_code ON dynamic_url TYPE int DEFAULT 302 ASSERT $value INSIDE [301, 302, 307, 308];
         DEFINE FIELD access_count ON dynamic_url TYPE int ASSERT $value != NONE;
+        DEFINE FIELD bot_count ON dynamic_url TYPE int DEFAULT 0;
+        DEFINE FIELD status ON dynamic_url TYPE string DEFAULT 'active' ASSERT $value INSIDE ['active', 'paused'];
+        DEFINE FIELD name ON dynamic_url TYPE option<string> ASSERT $value = NONE OR string::len($value) <= 100;
+        DEFINE FIELD description ON dynamic_url TYPE option<string> ASSERT $value = NONE OR string::len($value) <= 500;
+        DEFINE FIELD tags ON dynamic_url TYPE array<string> DEFAULT [];
+        DEFINE FIELD color ON dynamic_url TYPE option<string> ASSERT $value = NONE OR $value INSIDE ['red', 'orange', 'yellow', 'green', 'blue', 'purple', 'pink', 'gray'];
+        DEFINE FIELD deleted_at ON dynamic_url TYPE option<datetime>;
         DEFINE FIELD last_accessed ON dynamic_url TYPE datetime ASSERT $value != NONE;
         DEFINE FIELD created_at ON dynamic_url TYPE datetime ASSERT $value != NONE;
         DEFINE FIELD updated_at ON dynamic_url TYPE datetime ASSERT $value != NONE; 
+        DEFINE INDEX dynamic_url_server_url ON dynamic_url FIELDS server_url UNIQUE;
+        DEFINE INDEX dynamic_url_deleted_at ON dynamic_url FIELDS deleted_at;
+
+        DEFINE ANALYZER qr_text TOKENIZERS blank, class, punct FILTERS lowercase, ascii, edgengram(2, 20);
+        DEFINE INDEX dynamic_url_name_search ON dynamic_url FIELDS name SEARCH ANALYZER qr_text BM25 HIGHLIGHTS;
+        DEFINE INDEX dynamic_url_description_search ON dynamic_url FIELDS description SEARCH ANALYZER qr_text BM25 HIGHLIGHTS;
+        DEFINE INDEX dynamic_url_tags_search ON dynamic_url FIELDS tags SEARCH ANALYZER qr_text BM25 HIGHLIGHTS;
+        DEFINE INDEX dynamic_url_target_url_search ON dynamic_url FIELDS target_url SEARCH ANALYZER qr_text BM25 HIGHLIGHTS;
+
+        DEFINE TABLE organization SCHEMAFULL;
+        DEFINE FIELD name ON organization TYPE string ASSERT string::len($value) > 0 AND string::len($value) <= 100;
+        DEFINE FIELD created_at ON organization TYPE datetime ASSERT $value != NONE;
+
+        DEFINE TABLE member_of SCHEMAFULL TYPE RELATION IN user OUT organization;
+        DEFINE FIELD role ON member_of TYPE string ASSERT $value INSIDE ['owner', 'admin', 'editor', 'viewer'];
+        DEFINE FIELD created_at ON member_of TYPE datetime ASSERT $value != NONE;
+        DEFINE INDEX member_of_pair ON member_of FIELDS in, out UNIQUE;
+        DEFINE INDEX member_of_organization ON member_of FIELDS out;
+
+        DEFINE TABLE invitation SCHEMAFULL;
+        DEFINE FIELD organization ON invitation TYPE record<organization> ASSERT $value != NONE;
+        DEFINE FIELD email ON invitation TYPE string ASSERT $value != NONE;
+        DEFINE FIELD role ON invitation TYPE string ASSERT $value INSIDE ['owner', 'admin', 'editor', 'viewer'];
+        DEFINE FIELD invited_by ON invitation TYPE record<user> ASSERT $value != NONE;
+        DEFINE FIELD token_hash ON invitation TYPE string ASSERT $value != NONE;
+        DEFINE FIELD recipient ON invitation TYPE option<record<user>>;
+        DEFINE FIELD status ON invitation TYPE string ASSERT $value INSIDE ['pending', 'accepted', 'declined', 'revoked'];
+        DEFINE FIELD created_at ON invitation TYPE datetime ASSERT $value != NONE;
+        DEFINE FIELD expires_at ON invitation TYPE datetime ASSERT $value != NONE;
+        DEFINE FIELD resolved_at ON invitation TYPE option<datetime>;
+        DEFINE INDEX invitation_token_hash ON invitation FIELDS token_hash UNIQUE;
+        DEFINE INDEX invitation_organization ON invitation FIELDS organization, status;
+
+        DEFINE TABLE api_key SCHEMAFULL;
+        DEFINE FIELD owner ON api_key TYPE record<user | organization> ASSERT $value != NONE;
+        DEFINE FIELD user ON api_key TYPE record<user> ASSERT $value != NONE;
+        DEFINE FIELD name ON api_key TYPE string ASSERT string::len($value) > 0 AND string::len($value) <= 100;
+        DEFINE FIELD prefix ON api_key TYPE string ASSERT $value != NONE;
+        DEFINE FIELD key_hash ON api_key TYPE string ASSERT $value != NONE;
+        DEFINE FIELD scopes ON api_key TYPE array<string> ASSERT array::len($value) > 0 AND $value ALLINSIDE ['qrcode:read', 'qrcode:write', 'analytics:read'];
+        DEFINE FIELD created_at ON api_key TYPE datetime ASSERT $value != NONE;
+        DEFINE FIELD expires_at ON api_key TYPE option<datetime>;
+        DEFINE FIELD last_used_at ON api_key TYPE option<datetime>;
+        DEFINE INDEX api_key_hash ON api_key FIELDS key_hash UNIQUE;
+        DEFINE INDEX api_key_owner ON api_key FIELDS owner;
+
+        DEFINE TABLE folder SCHEMAFULL;
+        DEFINE FIELD name ON folder TYPE string ASSERT string::len($value) > 0 AND string::len($value) <= 100;
+        DEFINE FIELD parent ON folder TYPE option<record<folder>>;
+        DEFINE FIELD created_at ON folder TYPE datetime ASSERT $value != NONE;
+        DEFINE FIELD updated_at ON folder TYPE datetime ASSERT $value != NONE;
+        DEFINE INDEX folder_parent ON folder FIELDS parent;
+
+        DEFINE TABLE filed_in SCHEMAFULL TYPE RELATION IN dynamic_url OUT folder;
+        DEFINE INDEX filed_in_dynamic_url ON filed_in FIELDS in UNIQUE;
+
+        DEFINE TABLE dynamic_url_revision SCHEMAFULL;
+        DEFINE FIELD dynamic_url ON dynamic_url_revision TYPE record<dynamic_url> ASSERT $value != NONE;
+        DEFINE FIELD previous_target ON dynamic_url_revision TYPE option<string>;
+        DEFINE FIELD target_url ON dynamic_url_revision TYPE string ASSERT $value != NONE;
+        DEFINE FIELD author ON dynamic_url_revision TYPE record<user> ASSERT $value != NONE;
+        DEFINE FIELD restored_from ON dynamic_url_revision TYPE option<record<dynamic_url_revision>>;
+        DEFINE FIELD created_at ON dynamic_url_revision TYPE datetime ASSERT $value != NONE;
+        DEFINE INDEX dynamic_url_revision_dynamic_url ON dynamic_url_revision FIELDS dynamic_url, created_at;
+
+        DEFINE TABLE landing_page SCHEMAFULL;
+        DEFINE FIELD dynamic_url ON landing_page TYPE record<dynamic_url> ASSERT $value != NONE;
+        DEFINE FIELD title ON landing_page TYPE string ASSERT string::len($value) > 0 AND string::len($value) <= 100;
+        DEFINE FIELD avatar ON landing_page TYPE option<string>;
+        DEFINE FIELD updated_at ON landing_page TYPE datetime ASSERT $value != NONE;
+        DEFINE INDEX landing_page_dynamic_url ON landing_page FIELDS dynamic_url UNIQUE;
+
+        DEFINE TABLE landing_link SCHEMAFULL;
+        DEFINE FIELD page ON landing_link TYPE record<landing_page> ASSERT $value != NONE;
+        DEFINE FIELD position ON landing_link TYPE int ASSERT $value != NONE;
+        DEFINE FIELD title ON landing_link TYPE string ASSERT string::len($value) > 0 AND string::len($value) <= 100;
+        DEFINE FIELD url ON landing_link TYPE string ASSERT $value != NONE;
+        DEFINE FIELD clicks ON landing_link TYPE int DEFAULT 0;
+        DEFINE FIELD bot_clicks ON landing_link TYPE int DEFAULT 0;
+        DEFINE INDEX landing_link_page ON landing_link FIELDS page, position;
+
+        DEFINE TABLE hosted_file SCHEMAFULL;
+        DEFINE FIELD dynamic_url ON hosted_file TYPE record<dynamic_url> ASSERT $value != NONE;
+        DEFINE FIELD file_name ON hosted_file TYPE string ASSERT $value != NONE;
+        DEFINE FIELD content_type ON hosted_file TYPE string ASSERT $value != NONE;
+        DEFINE FIELD size ON hosted_file TYPE int ASSERT $value >= 0;
+        DEFINE FIELD path ON hosted_file TYPE string ASSERT $value != NONE;
+        DEFINE FIELD updated_at ON hosted_file TYPE datetime ASSERT $value != NONE;
+        DEFINE INDEX hosted_file_dynamic_url ON hosted_file FIELDS dynamic_url UNIQUE;
+
+        DEFINE TABLE app_link SCHEMAFULL;
+        DEFINE FIELD dynamic_url ON app_link TYPE record<dynamic_url> ASSERT $value != NONE;
+        DEFINE FIELD ios_url ON app_link TYPE option<string>;
+        DEFINE FIELD android_url ON app_link TYPE option<string>;
+        DEFINE FIELD ios_store_url ON app_link TYPE option<string>;
+        DEFINE FIELD android_store_url ON app_link TYPE option<string>;
+        DEFINE FIELD fallback_url ON app_link TYPE string ASSERT $value != NONE;
+        DEFINE FIELD updated_at ON app_link TYPE datetime ASSERT $value != NONE;
+        DEFINE INDEX app_link_dynamic_url ON app_link FIELDS dynamic_url UNIQUE;
+
+        DEFINE TABLE contact_card SCHEMAFULL;
+        DEFINE FIELD dynamic_url ON contact_card TYPE record<dynamic_url> ASSERT $value != NONE;
+        DEFINE FIELD first_name ON contact_card TYPE string ASSERT string::len($value) > 0 AND string::len($value) <= 100;
+        DEFINE FIELD last_name ON contact_card TYPE option<string>;
+        DEFINE FIELD organization ON contact_card TYPE option<string>;
+        DEFINE FIELD job_title ON contact_card TYPE option<string>;
+        DEFINE FIELD email ON contact_card TYPE option<string>;
+        DEFINE FIELD phone ON contact_card TYPE option<string>;
+        DEFINE FIELD mobile ON contact_card TYPE option<string>;
+        DEFINE FIELD website ON contact_card TYPE option<string>;
+        DEFINE FIELD address ON contact_card TYPE option<string>;
+        DEFINE FIELD note ON contact_card TYPE option<string>;
+        DEFINE FIELD photo ON contact_card TYPE option<string>;
+        DEFINE FIELD download ON contact_card TYPE bool DEFAULT false;
+        DEFINE FIELD updated_at ON contact_card TYPE datetime ASSERT $value != NONE;
+        DEFINE INDEX contact_card_dynamic_url ON contact_card FIELDS dynamic_url UNIQUE;
+
+        DEFINE TABLE transfer SCHEMAFULL;
+        DEFINE FIELD sender ON transfer TYPE record<user> ASSERT $value != NONE;
+        DEFINE FIELD recipient_email ON transfer TYPE string ASSERT $value != NONE;
+        DEFINE FIELD recipient ON transfer TYPE option<record<user>>;
+        DEFINE FIELD qrcodes ON transfer TYPE array<record<dynamic_url>>;
+        DEFINE FIELD status ON transfer TYPE string ASSERT $value INSIDE ['pending', 'accepted', 'declined', 'cancelled'];
+        DEFINE FIELD created_at ON transfer TYPE datetime ASSERT $value != NONE;
+        DEFINE FIELD expires_at ON transfer TYPE datetime ASSERT $value != NONE;
+        DEFINE FIELD resolved_at ON transfer TYPE option<datetime>;
+        DEFINE INDEX transfer_sender ON transfer FIELDS sender;
+        DEFINE INDEX transfer_recipient_email ON transfer FIELDS recipient_email, status;
+
+        DEFINE TABLE scan SCHEMAFULL;
+        DEFINE FIELD dynamic_url ON scan TYPE record<dynamic_url> ASSERT $value != NONE;
+        DEFINE FIELD revision ON scan TYPE option<record<dynamic_url_revision>>;
+        DEFINE FIELD client ON scan TYPE string ASSERT $value INSIDE ['human', 'bot'];
+        DEFINE FIELD scanned_at ON scan TYPE datetime ASSERT $value != NONE;
+        DEFINE INDEX scan_dynamic_url ON scan FIELDS dynamic_url, scanned_at;
+        DEFINE INDEX scan_revision ON scan FIELDS revision;
+
+        DEFINE TABLE webhook SCHEMAFULL;
+        DEFINE FIELD url ON webhook TYPE string ASSERT string::is::url($value);
+        DEFINE FIELD secret ON webhook TYPE string ASSERT $value != NONE;
+        DEFINE FIELD mode ON webhook TYPE string ASSERT $value INSIDE ['scan', 'minute'];
+        DEFINE FIELD include_bots ON webhook TYPE bool DEFAULT false;
+        DEFINE FIELD active ON webhook TYPE bool DEFAULT true;
+        DEFINE FIELD created_at ON webhook TYPE datetime ASSERT $value != NONE;
+
+        DEFINE TABLE webhook_delivery SCHEMAFULL;
+        DEFINE FIELD webhook ON webhook_delivery TYPE record<webhook> ASSERT $value != NONE;
+        DEFINE FIELD event ON webhook_delivery TYPE string ASSERT $value != NONE;
+        DEFINE FIELD payload ON webhook_delivery FLEXIBLE TYPE object;
+        DEFINE FIELD status ON webhook_delivery TYPE string ASSERT $value INSIDE ['pending', 'retrying', 'delivered', 'dead'];
+        DEFINE FIELD attempts ON webhook_delivery TYPE int DEFAULT 0;
+        DEFINE FIELD next_attempt_at ON webhook_delivery TYPE datetime ASSERT $value != NONE;
+        DEFINE FIELD last_status ON webhook_delivery TYPE option<int>;
+        DEFINE FIELD last_error ON webhook_delivery TYPE option<string>;
+        DEFINE FIELD created_at ON webhook_delivery TYPE datetime ASSERT $value != NONE;
+        DEFINE FIELD delivered_at ON webhook_delivery TYPE option<datetime>;
+        DEFINE INDEX webhook_delivery_due ON webhook_delivery FIELDS status, next_attempt_at;
+        DEFINE INDEX webhook_delivery_webhook ON webhook_delivery FIELDS webhook, created_at;
         ",
         )
         .await?;
 
         // Return a new instance of the Database struct with the established connection.
-        Ok(Database { db })
+        Ok(Database {
+            db,
+            cache: Arc::new(ScanCache::default()),
+            scans: Arc::new(ScanBuffer::default()),
+        })
     }
 
-    pub async fn list_user_urls(&self, user_id: &str) -> Response<Vec<models::DynamicQrResult>> {
+    pub async fn list_user_urls(
+        &self,
+        owner: &models::Owner,
+        query: &models::QrListQuery,
+    ) -> Response<models::DynamicQrPage> {
         /*
-           Lists all dynamic URLs created by a user.
+           Lists a page of the dynamic URLs created by a user or an organization.
+
+           Pages are keyset paginated on the sort field and record ID, so codes created
+           or deleted between requests don't shift later pages.
 
            Params:
-               user_id (string): The user's Auth0 ID.
+               owner (models::Owner): The user or organization the codes belong to.
+               query (models::QrListQuery): Contains:
+                   - `cursor`: The `next_cursor` of the previous page, if any.
+                   - `limit`: The page size (1 to 100).
+                   - `sort`, `order`: The field and direction to sort by.
+                   - `domain`, `status`, `name`, `tags`, `color`, `folder`, `created_*`, `updated_*`: Optional filters.
+
+           Returns:
+               Response<models::DynamicQrPage>: The page, the total matching the filters and the cursor of the next page.
+
+        */
+
+        let parse_date = |date: &Option<String>| match date {
+            Some(date) => Datetime::try_from(date.as_str())
+                .map(Some)
+                .map_err(|_| ApiError::BadRequest),
+            None => Ok(None),
+        };
+
+        let created_after = parse_date(&query.created_after)?;
+        let created_before = parse_date(&query.created_before)?;
+        let updated_after = parse_date(&query.updated_after)?;
+        let updated_before = parse_date(&query.updated_before)?;
+
+        let cursor = match &query.cursor {
+            Some(cursor) => Some(models::QrCursor::decode(cursor).ok_or(ApiError::BadRequest)?),
+            None => None,
+        };
+
+        let mut filters = vec!["deleted_at = NONE".to_string()];
+
+        if query.domain.is_some() {
+            filters.push("parse::url::domain(IF string::contains(target_url, '://') THEN target_url ELSE string::concat('http://', target_url) END) = $domain".to_string());
+        }
+        if query.status.is_some() {
+            filters.push("(status ?? 'active') = $status".to_string());
+        }
+        if query.name.is_some() {
+            filters.push("string::contains(string::lowercase(name ?? ''), $name)".to_string());
+        }
+        if !query.tags.is_empty() {
+            filters.push("(tags ?? []) CONTAINSALL $tags".to_string());
+        }
+        if query.color.is_some() {
+            filters.push("color = $color".to_string());
+        }
+        if query.folder.is_some() {
+            filters.push("->filed_in->folder CONTAINS $folder".to_string());
+        }
+        if created_after.is_some() {
+            filters.push("created_at >= $created_after".to_string());
+        }
+        if created_before.is_some() {
+            filters.push("created_at < $created_before".to_string());
+        }
+        if updated_after.is_some() {
+            filters.push("updated_at >= $updated_after".to_string());
+        }
+        if updated_before.is_some() {
+            filters.push("updated_at < $updated_before".to_string());
+        }
+
+        let field = query.sort.field();
+        let (direction, comparison) = match query.order {
+            models::SortOrder::Asc => ("ASC", ">"),
+            models::SortOrder::Desc => ("DESC", "<"),
+        };
+
+        let mut page_filters = filters.clone();
+
+        if cursor.is_some() {
+            let value = match query.sort.is_datetime() {
+                true => "<datetime> $cursor_value",
+                false => "$cursor_value",
+            };
+
+            page_filters.push(format!(
+                "({field} {comparison} {value} OR ({field} = {value} AND id {comparison} type::thing('dynamic_url', $cursor_id)))"
+            ));
+        }
+
+        let where_clause = |filters: &[String]| match filters.is_empty() {
+            true => String::new(),
+            false => format!(" WHERE {}", filters.join(" AND ")),
+        };
+
+        let mut request = self
+            .db
+            .query(format!(
+                "
+                SELECT {QR_FIELDS} FROM $owner->created->dynamic_url{} ORDER BY {field} {direction}, id {direction} LIMIT $limit;
+
+                SELECT count() AS total FROM $owner->created->dynamic_url{} GROUP ALL;",
+                where_clause(&page_filters),
+                where_clause(&filters),
+            ))
+            .bind(("owner", owner.record()))
+            // One extra row tells us whether there is a next page.
+            .bind(("limit", query.limit + 1));
+
+        if let Some(domain) = &query.domain {
+            request = request.bind(("domain", domain.trim().to_ascii_lowercase()));
+        }
+        if let Some(status) = query.status {
+            request = request.bind(("status", status.as_str()));
+        }
+        if let Some(name) = &query.name {
+            request = request.bind(("name", name.trim().to_lowercase()));
+        }
+        if !query.tags.is_empty() {
+            let tags: Vec<String> = query
+                .tags
+                .iter()
+                .map(|tag| tag.trim().to_lowercase())
+                .collect();
+            request = request.bind(("tags", tags));
+        }
+        if let Some(color) = query.color {
+            request = request.bind(("color", color.as_str()));
+        }
+        if let Some(folder) = &query.folder {
+            let folder = FolderId::parse(folder).ok_or(ApiError::BadRequest)?;
+            request = request.bind(("folder", folder.record()));
+        }
+        if let Some(cursor) = cursor {
+            request = request
+                .bind(("cursor_value", cursor.value))
+                .bind(("cursor_id", cursor.id));
+        }
+        for (name, date) in [
+            ("created_after", created_after),
+            ("created_before", created_before),
+            ("updated_after", updated_after),
+            ("updated_before", updated_before),
+        ] {
+            if let Some(date) = date {
+                request = request.bind((name, date));
+            }
+        }
+
+        let mut result = request.await?;
+
+        let mut urls = result.take::<Vec<models::DynamicQrResult>>(0)?;
+        let total = result
+            .take::<Option<models::CountResult>>(1)?
+            .map_or(0, |count| count.total);
+
+        let next_cursor = match urls.len() > query.limit {
+            true => {
+                urls.truncate(query.limit);
+                urls.last().map(|url| url.cursor(query.sort).encode())
+            }
+            false => None,
+        };
+
+        Ok(models::DynamicQrPage {
+            urls,
+            total,
+            next_cursor,
+        })
+    }
+
+    pub async fn search_user_urls(
+        &self,
+        owner: &models::Owner,
+        query: &models::QrSearchQuery,
+    ) -> Response<Vec<models::QrSearchHit>> {
+        /*
+           Searches the name, description, tags and target URL of a user's or an organization's dynamic URLs.
+
+           Words match on their prefix, so `camp` finds "Spring Campaign" and `shop exa`
+           finds `https://shop.example.com`. Every word has to match in the same field.
+
+           Params:
+               owner (models::Owner): The user or organization the codes belong to.
+               query (models::QrSearchQuery): The search text and the maximum number of results.
 
            Returns:
-               Response<Vec<models::DynamicUrlResult>>: A list of dynamic URLs created by the user.
+               Response<Vec<models::QrSearchHit>>: The matching codes, best match first, with the
+               matched fields highlighted. BadRequest if nothing searchable is left of the text.
 
         */
 
+        let terms = models::search_terms(&query.q).ok_or(ApiError::BadRequest)?;
+
         let mut result = self
             .db
-            .query("RETURN SELECT * FROM type::thing('user', $user)->created->dynamic_url")
-            .bind(("user", user_id.to_string()))
+            .query(format!(
+                "
+                LET $owned = $owner->created->(dynamic_url WHERE deleted_at = NONE);
+
+                LET $hits = (
+                    SELECT id,
+                        (search::score(0) ?? 0) + (search::score(1) ?? 0) + (search::score(2) ?? 0) + (search::score(3) ?? 0) AS score,
+                        {{
+                            name: search::highlight('<mark>', '</mark>', 0),
+                            description: search::highlight('<mark>', '</mark>', 1),
+                            tags: array::flatten([search::highlight('<mark>', '</mark>', 2) ?? []]),
+                            target_url: search::highlight('<mark>', '</mark>', 3)
+                        }} AS highlights
+                    FROM dynamic_url
+                    WHERE (name @0@ $terms OR description @1@ $terms OR tags @2@ $terms OR target_url @3@ $terms)
+                        AND id INSIDE $owned
+                    ORDER BY score DESC
+                    LIMIT $limit
+                );
+
+                SELECT score, highlights, (SELECT {QR_FIELDS} FROM ONLY $parent.id) AS qrcode FROM $hits;"
+            ))
+            .bind(("owner", owner.record()))
+            .bind(("terms", terms))
+            .bind(("limit", query.limit))
             .await?;
 
-        let created = result.take::<Vec<models::DynamicQrResult>>(0)?;
+        let hits = result.take::<Vec<models::QrSearchHit>>(2)?;
 
-        match created.is_empty() {
-            true => Err(ApiError::InternalServerError("No URLs found.".to_string())),
-            false => Ok(created),
-        }
+        Ok(hits
+            .into_iter()
+            .map(|hit| models::QrSearchHit {
+                highlights: hit.highlights.matched_only(),
+                ..hit
+            })
+            .collect())
     }
 
     pub async fn insert_user(&self, user: models::User) -> Response<models::UserResult> {
@@ -162,46 +752,92 @@ impl Database {
     pub async fn insert_dynamic_url(
         &self,
         user_id: &str,
+        owner: &models::Owner,
         dynamic_url: models::DynamicQr,
+        tier: &models::SubscriptionTier,
     ) -> Response<Vec<models::DynamicQrResult>> {
         /*
-           Inserts a new dynamic URL into the database.
+           Inserts a new dynamic URL for a user or an organization, along with its first revision,
+           and adds it to the owner's usage in the same transaction, after checking there is room
+           for it in the tier.
 
            Params:
-               user_id (string): The user's Auth0 ID.
+               user_id (string): The Auth0 ID of the user creating it, the author of the first revision.
+               owner (models::Owner): The user or organization the code belongs to.
                dynamic_url (models::DynamicUrl): Contains:
                    - `server_url`: The server URL that will be shortened.
                    - `target_url`: The original destination URL that the dynamic URL points to.
+                   - `target_type`: Whether scans redirect, render a landing page, serve a file or open an app.
+                     Page codes start with an empty page titled after the code; file and app codes start
+                     without a file or links. None of them has revisions.
+                   - `redirect_code`: The redirect status to answer scans with.
+                   - `name`, `description`, `tags`, `color`: Optional labels.
+               tier (models::SubscriptionTier): The owner's tier, whose limit the code has to fit in.
 
            Returns:
-               Response<models::DynamicUrlResult>: The inserted dynamic URL object, including any generated fields like `created_at`.
+               Response<Vec<models::DynamicUrlResult>>: The owner's dynamic URLs, including the inserted one.
+               BadRequest if the owner has no room left.
 
         */
 
-        let mut result = self
+        let usage_check = usage_check("1");
+
+        let result = self
             .db
-            .query(
+            .query(format!(
                 "
+                BEGIN TRANSACTION;
+
                 LET $user = type::thing('user', $user_id);
                 LET $url = type::thing('dynamic_url', rand::ulid());
-                
-        RELATE $user->created->CREATE $url 
+                {usage_check}
+
+        RELATE $owner->created->CREATE $url 
         SET server_url = rand::ulid(), 
         access_count = 0,
         last_accessed = time::now(),
         target_url = $target_url, 
+        target_type = $target_type,
+        redirect_code = $redirect_code,
         access_count = 0,
+        bot_count = 0,
+        status = 'active',
+        name = $name ?? NONE,
+        description = $description ?? NONE,
+        tags = $tags,
+        color = $color ?? NONE,
         last_accessed = time::now(),
         created_at = time::now(), 
         updated_at = time::now();
-        
-        SELECT * FROM $user->created->dynamic_url;",
-            )
+
+        IF $target_type = 'page' {{
+            CREATE type::thing('landing_page', record::id($url)) SET dynamic_url = $url, title = $name ?? 'Links', updated_at = time::now();
+        }} ELSE IF $target_type = 'url' {{
+            CREATE dynamic_url_revision SET dynamic_url = $url, target_url = $target_url, author = $user, created_at = time::now();
+        }};
+
+        UPDATE $owner->subscribed->subscription SET usage += 1;
+
+        SELECT {QR_FIELDS} FROM $owner->created->dynamic_url WHERE deleted_at = NONE;
+
+        COMMIT TRANSACTION;"
+            ))
             .bind(("user_id", user_id.to_string()))
+            .bind(("owner", owner.record()))
             .bind(("target_url", dynamic_url.target_url))
+            .bind(("target_type", dynamic_url.target_type))
+            .bind(("redirect_code", u16::from(dynamic_url.redirect_code)))
+            .bind(("name", dynamic_url.name))
+            .bind(("description", dynamic_url.description))
+            .bind(("tags", dynamic_url.tags))
+            .bind(("color", dynamic_url.color))
+            .bind(("max_usage", tier.max_usage()))
+            .bind(("counts_paused", tier.counts_paused()))
             .await?;
 
-        let created = result.take::<Vec<models::DynamicQrResult>>(3)?;
+        let mut result = check_thrown(result)?;
+
+        let created = result.take::<Vec<models::DynamicQrResult>>(8)?;
 
         if created.is_empty() {
             Err(ApiError::InternalServerError(
@@ -212,447 +848,3884 @@ impl Database {
         }
     }
 
-    pub async fn lookup_dynamic_url(&self, server_url: &str) -> Response<String> {
+    pub async fn clone_dynamic_url(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        tier: &models::SubscriptionTier,
+    ) -> Response<models::DynamicQrResult> {
         /*
-           Looks up a dynamic URL in the database and returns the target URL.
+            Copies a dynamic URL into a new one with a fresh server URL for the same owner, and adds
+            it to the owner's usage in the same transaction, after checking there is room for it in
+            the tier. The target, redirect code, labels, folder, landing page, app links and contact
+            card are copied; counts and history start over and the copy is active. A hosted file is
+            copied on disk once the transaction commits.
 
-           Params:
-               server_url (string): The server URL to look up.
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the dynamic URL to copy.
+                tier (models::SubscriptionTier): The owner's tier, whose limit the copy has to fit in.
 
-           Returns:
-               Response<String>: The target URL that the server URL points to.
+            Returns:
+                Response<models::DynamicQrResult>: The new dynamic URL. NotFound if the user can't see
+                the original, Forbidden if they can't edit it, BadRequest if the owner has no room left.
 
         */
 
-        let mut result = self
-            .db
-            .query("SELECT target_url FROM dynamic_url WHERE server_url = $server_url;
-                    UPDATE dynamic_url SET access_count = access_count + 1, last_accessed = time::now() WHERE server_url = $server_url;")
-            .bind(("server_url", server_url.to_string()))
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Editor)
             .await?;
 
-        match result.take::<Option<models::LinkResult>>(0)? {
-            Some(created) => Ok(created.target_url),
-            None => Err(ApiError::InternalServerError(
-                "Url doesn't exist.".to_string(),
-            )),
-        }
-    }
+        let usage_check = usage_check("1");
 
-    pub async fn update_dynamic_url(
-        &self,
-        server_url: &str,
-        new_target_url: &str,
-    ) -> Response<models::DynamicQrResult> {
-        /*
-             Updates the target URL of a dynamic URL in the database.
+        let result = self
+            .db
+            .query(format!(
+                "
+                BEGIN TRANSACTION;
 
-             Params:
-               server_url (string): The server URL to update.
-               new_target_url (string): The new target URL to set.
+                LET $user = type::thing('user', $user_id);
+                LET $owner = ($qrcode<-created<-?)[0];
+                LET $source = (SELECT * FROM ONLY $qrcode);
+                LET $url = type::thing('dynamic_url', rand::ulid());
+                {usage_check}
 
-             Returns:
-               Response<models::DynamicUrlResult>: The updated dynamic URL object, including any generated fields like `updated_at`.
+                RELATE $owner->created->CREATE $url
+                SET server_url = rand::ulid(),
+                target_url = $source.target_url,
+                target_type = $source.target_type ?? 'url',
+                redirect_code = $source.redirect_code,
+                access_count = 0,
+                bot_count = 0,
+                status = 'active',
+                name = $source.name,
+                description = $source.description,
+                tags = $source.tags ?? [],
+                color = $source.color,
+                last_accessed = time::now(),
+                created_at = time::now(),
+                updated_at = time::now();
 
-        */
+                FOR $folder IN $qrcode->filed_in->folder {{
+                    RELATE $url->filed_in->$folder;
+                }};
 
-        let mut result = self
-            .db
-            .query("UPDATE dynamic_url SET target_url = $target_url, updated_at = time::now() WHERE server_url = $server_url")
-            .bind(("server_url", server_url.to_string()))
-            .bind(("target_url", new_target_url.to_string()))
-            .await?;
+                IF $source.target_type = 'page' {{
+                    LET $page = type::thing('landing_page', record::id($url));
+                    LET $original = type::thing('landing_page', record::id($qrcode));
+                    CREATE $page SET dynamic_url = $url, title = $original.title, avatar = $original.avatar, updated_at = time::now();
+                    INSERT INTO landing_link (SELECT $page AS page, position, title, url FROM landing_link WHERE page = $original);
+                }} ELSE IF $source.target_type = 'app' {{
+                    LET $links = (SELECT ios_url, android_url, ios_store_url, android_store_url, fallback_url, $url AS dynamic_url, time::now() AS updated_at FROM ONLY type::thing('app_link', record::id($qrcode)));
+                    IF $links {{
+                        CREATE type::thing('app_link', record::id($url)) CONTENT $links;
+                    }};
+                }} ELSE IF $source.target_type = 'contact' {{
+                    LET $card = (SELECT *, $url AS dynamic_url, time::now() AS updated_at OMIT id FROM ONLY type::thing('contact_card', record::id($qrcode)));
+                    IF $card {{
+                        CREATE type::thing('contact_card', record::id($url)) CONTENT $card;
+                    }};
+                }} ELSE IF ($source.target_type ?? 'url') = 'url' {{
+                    CREATE dynamic_url_revision SET dynamic_url = $url, target_url = $source.target_url, author = $user, created_at = time::now();
+                }};
 
-        match result.take::<Option<models::DynamicQrResult>>(0)? {
-            Some(updated) => Ok(updated),
-            None => Err(ApiError::InternalServerError(
-                "No matching URL found.".to_string(),
-            )),
-        }
-    }
+                UPDATE $owner->subscribed->subscription SET usage += 1;
 
-    pub async fn delete_dynamic_url(&self, server_url: &str) -> Response<bool> {
-        /*
-            Deletes a dynamic URL from the database.
+                SELECT {QR_FIELDS} FROM $url;
 
-            Params:
-                id (string): The ID of the dynamic URL to delete.
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("qrcode", qrcode))
+            .bind(("max_usage", tier.max_usage()))
+            .bind(("counts_paused", tier.counts_paused()))
+            .await?;
 
-        */
+        let mut result = check_thrown(result)?;
 
-        let _ = self
-            .db
-            .query("DELETE dynamic_url WHERE server_url = $server_url")
-            .bind(("server_url", server_url.to_string()))
-            .await?;
+        let cloned = match result.take::<Option<models::DynamicQrResult>>(11)? {
+            Some(cloned) => cloned,
+            None => {
+                return Err(ApiError::InternalServerError(
+                    "Failed to clone dynamic URL.".to_string(),
+                ))
+            }
+        };
 
-        Ok(true)
+        self.copy_hosted_file(qrcode_id, cloned.id()).await?;
+
+        Ok(cloned)
     }
 
-    pub async fn get_subscription_id(&self, user_id: &str) -> Response<Option<String>> {
+    pub async fn slugs_taken(&self, slugs: Vec<String>) -> Response<Vec<String>> {
         /*
-            Looks up a user's subscription in the database.
+            Finds which of the given server URLs are already used by a dynamic URL.
 
             Params:
-                user_id (string): The user's Auth0 ID.
+                slugs (Vec<string>): The server URLs to check.
 
             Returns:
-                Response<Option<models::UserSubscription>>: The user's subscription object, or None if no subscription was found.
+                Response<Vec<string>>: The server URLs that are taken.
 
         */
 
         let mut result = self
             .db
-            .query(
-                "SELECT subscription_id FROM type::thing('user', $user)->subscribed->subscription",
-            )
-            .bind(("user", user_id.to_string()))
+            .query("SELECT VALUE server_url FROM dynamic_url WHERE server_url INSIDE $slugs;")
+            .bind(("slugs", slugs))
             .await?;
 
-        match result.take::<Option<models::SubscriptionId>>(0)? {
-            Some(id) => Ok(Some(id.subscription_id)),
-            None => Ok(None),
-        }
+        Ok(result.take::<Vec<String>>(0)?)
     }
 
-    pub async fn delete_user_data(&self, user_id: &str) -> Response<bool> {
+    pub async fn insert_dynamic_urls(
+        &self,
+        user_id: &str,
+        owner: &models::Owner,
+        codes: Vec<NewQrCode>,
+        tier: &models::SubscriptionTier,
+    ) -> Response<Vec<models::DynamicQrResult>> {
         /*
-            Deletes a user's data from the database.
+            Creates many dynamic URLs and adds them to the owner's usage in one transaction,
+            so either all of them are created or none are, after checking they all fit in the tier.
 
             Params:
-                user_id (string): The user's Auth0 ID.
+                user_id (string): The Auth0 ID of the user creating them, the author of their first revisions.
+                owner (models::Owner): The user or organization the codes belong to.
+                codes (Vec<NewQrCode>): The validated codes, each with an optional custom slug.
+                tier (models::SubscriptionTier): The owner's tier, whose limit the codes have to fit in.
+
+            Returns:
+                Response<Vec<models::DynamicQrResult>>: The created dynamic URLs, in the order given.
+                BadRequest if the owner doesn't have room for all of them.
 
         */
 
-        let _ = self
+        let usage_check = usage_check("array::len($codes)");
+
+        let result = self
             .db
-            .query(
+            .query(format!(
                 "
-                    LET $user = type::thing('user', $user_id);
-            
-                    DELETE $user->subscribed->subscription;
-                    DELETE $user->created->dynamic_url;",
-            )
+                BEGIN TRANSACTION;
+
+                LET $user = type::thing('user', $user_id);
+                {usage_check}
+
+                LET $records = (
+                    SELECT type::thing('dynamic_url', rand::ulid()) AS id,
+                        slug ?? rand::ulid() AS server_url,
+                        target_url,
+                        redirect_code,
+                        0 AS access_count,
+                        0 AS bot_count,
+                        'active' AS status,
+                        name ?? NONE AS name,
+                        description ?? NONE AS description,
+                        tags,
+                        color ?? NONE AS color,
+                        time::now() AS last_accessed,
+                        time::now() AS created_at,
+                        time::now() AS updated_at
+                    FROM $codes
+                );
+
+                LET $created = (INSERT INTO dynamic_url $records).id;
+
+                RELATE $owner->created->$created;
+
+                INSERT INTO dynamic_url_revision (SELECT id AS dynamic_url, target_url, $user AS author, created_at FROM $records);
+
+                UPDATE $owner->subscribed->subscription SET usage += array::len($created);
+
+                SELECT {QR_FIELDS} FROM $created;
+
+                COMMIT TRANSACTION;"
+            ))
             .bind(("user_id", user_id.to_string()))
+            .bind(("owner", owner.record()))
+            .bind(("codes", codes))
+            .bind(("max_usage", tier.max_usage()))
+            .bind(("counts_paused", tier.counts_paused()))
             .await?;
 
-        Ok(true)
+        let mut result = check_thrown(result)?;
+
+        Ok(result.take::<Vec<models::DynamicQrResult>>(9)?)
     }
 
-    pub async fn get_user_from_subscription(&self, subscription_id: &str) -> Response<UserResult> {
+    pub async fn lookup_dynamic_url(
+        &self,
+        server_url: &models::QrSlug,
+    ) -> Response<models::LinkResult> {
         /*
-            Looks up a user's Auth0 ID from a subscription ID in the database.
+           Looks up a dynamic URL and returns where it redirects to, served from the scan cache
+           when possible. Paused and trashed codes are returned too, so scans can explain why
+           they don't redirect.
 
-            Params:
-                subscription_id (str): The subscription ID to look up.
+           Params:
+               server_url (models::QrSlug): The server URL to look up.
 
-            Returns:
-                Response<UserResult>: The user's data
+           Returns:
+               Response<models::LinkResult>: The target URL, redirect code and state of the code the server URL points to.
 
         */
 
+        let server_url = server_url.as_str();
+
+        if let Some(link) = self.cache.get(server_url) {
+            return Ok(link);
+        }
+
         let mut result = self
             .db
             .query(
-                "
-                LET $subscription = type::thing('subscription', $subscription_id);
-                LET $user = SELECT in FROM subscribed WHERE out = $subscription;
-                SELECT * FROM $user.in;",
+                "SELECT target_url, target_type ?? 'url' AS target_type, redirect_code, status ?? 'active' AS status, name, color, deleted_at != NONE AS trashed FROM dynamic_url WHERE server_url = $server_url;",
             )
-            .bind(("subscription_id", subscription_id.to_string()))
+            .bind(("server_url", server_url.to_string()))
             .await?;
 
-        match result.take::<Option<models::UserResult>>(2)? {
-            Some(subscription) => Ok(subscription),
-            None => Err(ApiError::InternalServerError("No user found.".to_string())),
+        match result.take::<Option<models::LinkResult>>(0)? {
+            Some(link) => {
+                self.cache.insert(server_url, link.clone());
+                Ok(link)
+            }
+            None => Err(ApiError::InternalServerError(
+                "Url doesn't exist.".to_string(),
+            )),
         }
     }
 
-    pub async fn get_user_from_session(&self, session_id: &str) -> Response<UserResult> {
+    pub fn record_scan(&self, server_url: &models::QrSlug, client: ClientKind) {
         /*
-            Looks up a user's Auth0 ID from a session ID in the database.
-
-            Params:
-                session_id (string): The session ID to look up.
+           Buffers a scan of a dynamic URL. Counters are written later by `flush_scans`.
+           Bot scans are counted separately so they don't inflate `access_count`.
 
-            Returns:
-                Response<Option<String>>: The user's Auth0 ID, or None if no user was found.
+           Params:
+               server_url (models::QrSlug): The server URL that was scanned.
+               client (ClientKind): Whether the scan came from a person or a bot.
 
         */
 
-        let mut result = self
-            .db
-            .query(
-                "
-                LET $payment = type::thing('session', $session_id);
-                LET $user = SELECT in FROM payment WHERE out = $payment;
-                SELECT * FROM $user.in;",
-            )
-            .bind(("session_id", session_id.to_string()))
-            .await?;
-
-        match result.take::<Option<models::UserResult>>(2)? {
-            Some(subscription) => Ok(subscription),
-            None => Err(ApiError::InternalServerError("No user found.".to_string())),
-        }
+        self.scans.push(server_url.as_str(), client);
     }
 
-    pub async fn insert_session(
-        &self,
-        user_id: &str,
-        session: models::PaymentSession,
-    ) -> Response<models::PaymentSessionResult> {
+    pub async fn flush_scans(&self) -> Response<usize> {
         /*
-            Inserts a new session into the database.
+           Writes all buffered scans to the database in a single transaction, queuing
+           a webhook delivery for every webhook registered by the code's owner. If the
+           write fails the scans are put back into the buffer for the next flush.
 
-            Params:
-                session_id (string): The session ID.
-                tier (string): The session's tier.
+           Returns:
+               Response<usize>: The number of scans written.
 
         */
 
-        let mut result = self.db
-            .query("
+        let batch = self.scans.drain();
 
-            LET $user = type::thing('user', $user_id);
+        if batch.events.is_empty() {
+            return Ok(0);
+        }
+
+        let written = batch.events.len();
+
+        let response = self
+            .db
+            .query(
+                "
+            BEGIN TRANSACTION;
+
+            FOR $count IN $counts {
+                UPDATE dynamic_url SET
+                    access_count += $count.humans,
+                    bot_count = (bot_count ?? 0) + $count.bots,
+                    last_accessed = $count.last_accessed ?? last_accessed
+                WHERE server_url = $count.server_url;
+            };
+
+            FOR $event IN $events {
+                LET $url = (SELECT VALUE id FROM dynamic_url WHERE server_url = $event.server_url)[0];
+
+                IF $url != NONE {
+                    LET $revision = (SELECT id, created_at FROM dynamic_url_revision WHERE dynamic_url = $url AND created_at <= $event.scanned_at ORDER BY created_at DESC LIMIT 1)[0].id;
+
+                    CREATE scan SET dynamic_url = $url, revision = $revision, client = $event.client, scanned_at = $event.scanned_at;
+
+                    LET $owner = (SELECT VALUE in FROM created WHERE out = $url)[0];
+                    LET $payload = {
+                        qrcode: <string> $url,
+                        server_url: $event.server_url,
+                        target_url: $url.target_url,
+                        client: $event.client,
+                        scanned_at: <string> $event.scanned_at,
+                    };
+
+                    FOR $hook IN (SELECT id, mode FROM $owner->registered->webhook WHERE active = true AND (include_bots = true OR $event.client = 'human')) {
+                        IF $hook.mode = 'scan' {
+                            CREATE webhook_delivery SET
+                                webhook = $hook.id,
+                                event = 'scan',
+                                payload = $payload,
+                                status = 'pending',
+                                attempts = 0,
+                                next_attempt_at = time::now(),
+                                created_at = time::now();
+                        } ELSE {
+                            LET $window = time::floor(time::now(), 1m);
+
+                            UPSERT type::thing('webhook_delivery', string::concat(record::id($hook.id), time::format($window, '%Y%m%d%H%M'))) SET
+                                webhook = $hook.id,
+                                event = 'scan.batch',
+                                payload = { window: <string> $window, scans: array::append(payload.scans ?? [], $payload) },
+                                status = 'pending',
+                                attempts = 0,
+                                next_attempt_at = $window + 1m,
+                                created_at = created_at ?? time::now();
+                        };
+                    };
+                };
+            };
+
+            COMMIT TRANSACTION;",
+            )
+            .bind(("counts", batch.counts.clone()))
+            .bind(("events", batch.events.clone()))
+            .await;
+
+        let result = match response {
+            Ok(response) => response.check().map(|_| ()),
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Ok(_) => Ok(written),
+            Err(error) => {
+                self.scans.restore(batch);
+                Err(error.into())
+            }
+        }
+    }
+
+    pub async fn scans_filled(&self) {
+        /*
+           Resolves once enough scans are buffered to flush before the next interval.
+        */
+
+        self.scans.filled().await
+    }
+
+    pub async fn dynamic_url_owner(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        role: Role,
+    ) -> Response<models::Owner> {
+        /*
+            Resolves who owns a dynamic URL the user can work on, for checking the owner's limits.
+
+            Every read and write of a single dynamic URL goes through here. Users have every role
+            on the codes they created, and their role in the organization on the organization's
+            codes. Codes of anyone else, and codes in the trash, look exactly like codes that
+            don't exist.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the dynamic URL.
+                role (Role): The least role the action needs.
+
+            Returns:
+                Response<models::Owner>: The user or organization that owns the code. NotFound if
+                the user can't see it, Forbidden if their role is below `role`.
+
+        */
+
+        let mut result = self
+            .db
+            .query(
+                "
+                LET $user = type::thing('user', $user_id);
+                LET $owner = IF $qrcode.deleted_at = NONE { ($qrcode<-created<-?)[0] };
+
+                RETURN {
+                    owner: $owner,
+                    role: IF $owner = $user { 'owner' } ELSE { (SELECT VALUE role FROM member_of WHERE in = $user AND out = $owner)[0] }
+                };",
+            )
+            .bind(("user_id", user_id.to_string()))
+            .bind(("qrcode", qrcode_id.record()))
+            .await?;
+
+        let access = result.take::<Option<CodeAccess>>(2)?;
+
+        match access.and_then(|access| access.owner.zip(access.role)) {
+            Some((owner, granted)) if granted >= role => Ok(owner),
+            Some(_) => Err(ApiError::Forbidden),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    async fn find_dynamic_url(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        role: Role,
+    ) -> Response<RecordId> {
+        /*
+            Resolves a dynamic URL ID to its record, as long as the user has at least `role` on it,
+            see `dynamic_url_owner`.
+
+            Returns:
+                Response<RecordId>: The dynamic URL record, NotFound or Forbidden.
+
+        */
+
+        self.dynamic_url_owner(user_id, qrcode_id, role).await?;
+
+        Ok(qrcode_id.record())
+    }
+
+    pub async fn select_dynamic_url(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+    ) -> Response<models::DynamicQrResult> {
+        /*
+             Selects a single dynamic URL of a user.
+
+             Params:
+               user_id (string): The user's Auth0 ID.
+               qrcode_id (models::QrCodeId): The ID of the dynamic URL.
+
+             Returns:
+               Response<models::DynamicQrResult>: The dynamic URL, or NotFound if the user didn't create it.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Viewer)
+            .await?;
+
+        let mut result = self
+            .db
+            .query(format!("SELECT {QR_FIELDS} FROM $qrcode;"))
+            .bind(("qrcode", qrcode))
+            .await?;
+
+        match result.take::<Option<models::DynamicQrResult>>(0)? {
+            Some(dynamic_url) => Ok(dynamic_url),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn update_dynamic_url(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        dynamic_url: &models::DynamicQr,
+    ) -> Response<models::DynamicQrResult> {
+        /*
+             Updates the target URL, redirect code and labels of a user's dynamic URL, recording a
+             revision when the target URL changes. Page, file and app codes keep their empty target URL.
+
+             Params:
+               user_id (string): The user's Auth0 ID.
+               qrcode_id (models::QrCodeId): The ID of the dynamic URL to update.
+               dynamic_url (models::DynamicQr): The new target URL, redirect code, name, description, tags and color to set.
+
+             Returns:
+               Response<models::DynamicUrlResult>: The updated dynamic URL object, or NotFound if the user didn't create it.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Editor)
+            .await?;
+
+        let mut result = self
+            .db
+            .query(format!(
+                "
+                BEGIN TRANSACTION;
+
+                LET $previous = $qrcode.target_url;
+
+                IF ($qrcode.target_type ?? 'url') = 'url' AND $previous != $target_url {{
+                    CREATE dynamic_url_revision SET dynamic_url = $qrcode, previous_target = $previous, target_url = $target_url, author = type::thing('user', $user_id), created_at = time::now();
+                }};
+
+                UPDATE $qrcode SET target_url = IF (target_type ?? 'url') = 'url' THEN $target_url ELSE target_url END, redirect_code = $redirect_code, name = $name ?? NONE, description = $description ?? NONE, tags = $tags, color = $color ?? NONE, updated_at = time::now() RETURN {QR_FIELDS};
+
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("qrcode", qrcode))
+            .bind(("target_url", dynamic_url.target_url.clone()))
+            .bind(("redirect_code", u16::from(dynamic_url.redirect_code)))
+            .bind(("name", dynamic_url.name.clone()))
+            .bind(("description", dynamic_url.description.clone()))
+            .bind(("tags", dynamic_url.tags.clone()))
+            .bind(("color", dynamic_url.color))
+            .await?;
+
+        match result.take::<Option<models::DynamicQrResult>>(2)? {
+            Some(updated) => {
+                self.cache.invalidate(updated.server_url());
+                Ok(updated)
+            }
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn delete_dynamic_url(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+    ) -> Response<bool> {
+        /*
+            Moves a dynamic URL to the trash, giving back its usage to its owner. It can be restored
+            until `purge_trash` deletes it for good.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the dynamic URL to delete.
+
+            Returns:
+                Response<bool>: true once trashed. NotFound if the user can't see it, Forbidden if
+                they can't edit it.
+
+        */
+
+        let owner = self
+            .dynamic_url_owner(user_id, qrcode_id, Role::Editor)
+            .await?;
+
+        let deleted = self
+            .delete_dynamic_urls(&owner, std::slice::from_ref(qrcode_id))
+            .await?;
+
+        Ok(deleted == 1)
+    }
+
+    async fn find_dynamic_urls(
+        &self,
+        owner: &models::Owner,
+        qrcode_ids: &[models::QrCodeId],
+    ) -> Response<Vec<RecordId>> {
+        /*
+            Resolves many dynamic URL IDs to their records, as long as the owner created all of them
+            and none of them is in the trash. Callers check the user's role on the owner first.
+
+            Params:
+                owner (models::Owner): The user or organization the codes belong to.
+                qrcode_ids (&[models::QrCodeId]): The IDs of the dynamic URLs, without duplicates.
+
+            Returns:
+                Response<Vec<RecordId>>: The dynamic URL records, or NotFound if any of them isn't the owner's.
+
+        */
+
+        let qrcodes = qrcode_ids
+            .iter()
+            .map(models::QrCodeId::record)
+            .collect::<Vec<_>>();
+
+        let mut result = self
+            .db
+            .query("SELECT VALUE id FROM $owner->created->dynamic_url WHERE id INSIDE $qrcodes AND deleted_at = NONE;")
+            .bind(("owner", owner.record()))
+            .bind(("qrcodes", qrcodes))
+            .await?;
+
+        let found = result.take::<Vec<RecordId>>(0)?;
+
+        if found.len() != qrcode_ids.len() {
+            return Err(ApiError::NotFound);
+        }
+
+        Ok(found)
+    }
+
+    pub async fn bulk_update_dynamic_urls(
+        &self,
+        user_id: &str,
+        owner: &models::Owner,
+        qrcode_ids: &[models::QrCodeId],
+        operation: &BulkOperation,
+    ) -> Response<BulkSummary> {
+        /*
+            Applies one operation to many of an owner's dynamic URLs in a single transaction, recording
+            a revision by the user for every changed target URL.
+
+            Params:
+                user_id (string): The Auth0 ID of the user making the change.
+                owner (models::Owner): The user or organization the codes belong to.
+                qrcode_ids (&[models::QrCodeId]): The IDs of the dynamic URLs, without duplicates.
+                operation (BulkOperation): The normalized operation to apply.
+
+            Returns:
+                Response<BulkSummary>: How many codes changed and how many were already as requested.
+                NotFound if any code isn't the owner's, BadRequest if the operation can't apply to one of them,
+                in both cases nothing is changed.
+
+        */
+
+        let qrcodes = self.find_dynamic_urls(owner, qrcode_ids).await?;
+
+        let mut result = self
+            .db
+            .query("SELECT id, server_url, target_url, target_type ?? 'url' AS target_type, tags, status FROM $qrcodes;")
+            .bind(("qrcodes", qrcodes))
+            .await?;
+
+        let targets = result.take::<Vec<BulkTarget>>(0)?;
+
+        let mut changes = Vec::new();
+        let mut server_urls = Vec::new();
+        for target in &targets {
+            if let Some(change) = plan(operation, target).map_err(|_| ApiError::BadRequest)? {
+                changes.push(change);
+                server_urls.push(target.server_url.as_str());
+            }
+        }
+
+        let summary = BulkSummary {
+            requested: qrcode_ids.len(),
+            updated: changes.len(),
+            unchanged: qrcode_ids.len() - changes.len(),
+            deleted: 0,
+        };
+
+        if changes.is_empty() {
+            return Ok(summary);
+        }
+
+        self.db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                FOR $change IN $changes {
+                    IF $change.target_url {
+                        CREATE dynamic_url_revision SET dynamic_url = $change.id, previous_target = $change.id.target_url, target_url = $change.target_url, author = type::thing('user', $user_id), created_at = time::now();
+                    };
+
+                    UPDATE $change.id SET
+                        target_url = $change.target_url ?? target_url,
+                        tags = $change.tags ?? tags,
+                        status = $change.status ?? status,
+                        updated_at = time::now();
+                };
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("user_id", user_id.to_string()))
+            .bind(("changes", changes))
+            .await?
+            .check()?;
+
+        for server_url in server_urls {
+            self.cache.invalidate(server_url);
+        }
+
+        Ok(summary)
+    }
+
+    pub async fn delete_dynamic_urls(
+        &self,
+        owner: &models::Owner,
+        qrcode_ids: &[models::QrCodeId],
+    ) -> Response<usize> {
+        /*
+            Moves many of an owner's dynamic URLs to the trash and gives their usage back in one transaction.
+
+            Params:
+                owner (models::Owner): The user or organization the codes belong to.
+                qrcode_ids (&[models::QrCodeId]): The IDs of the dynamic URLs, without duplicates.
+
+            Returns:
+                Response<usize>: How many dynamic URLs were trashed, or NotFound if any of them isn't the owner's,
+                in which case nothing is trashed.
+
+        */
+
+        let qrcodes = self.find_dynamic_urls(owner, qrcode_ids).await?;
+
+        let mut result = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                LET $deleted = (UPDATE $qrcodes SET deleted_at = time::now() WHERE deleted_at = NONE RETURN BEFORE);
+
+                UPDATE $owner->subscribed->subscription SET usage -= array::len($deleted);
+
+                SELECT server_url FROM $deleted;
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("owner", owner.record()))
+            .bind(("qrcodes", qrcodes))
+            .await?
+            .check()?;
+
+        let deleted = result.take::<Vec<models::ServerUrl>>(2)?;
+
+        for code in &deleted {
+            self.cache.invalidate(&code.server_url);
+        }
+
+        Ok(deleted.len())
+    }
+
+    pub async fn set_dynamic_url_status(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        status: models::QrStatus,
+        tier: Option<&models::SubscriptionTier>,
+    ) -> Response<models::DynamicQrResult> {
+        /*
+            Pauses or resumes a user's dynamic URL. Paused codes keep their target and counts,
+            but scans are answered with an unavailable page instead of a redirect. On tiers that
+            leave paused codes out of the usage, resuming one checks it fits again in the same
+            transaction as the update.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the dynamic URL.
+                status (models::QrStatus): The new status.
+                tier (Option<models::SubscriptionTier>): The owner's tier when resuming, none when pausing.
+
+            Returns:
+                Response<models::DynamicQrResult>: The updated dynamic URL, NotFound if the user didn't
+                create it, or BadRequest if resuming it would go past the tier's limit.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Editor)
+            .await?;
+
+        let usage_check = usage_check(
+            "(IF $status = 'active' AND $qrcode.status = 'paused' AND !$counts_paused THEN 1 ELSE 0 END)",
+        );
+
+        let result = self
+            .db
+            .query(format!(
+                "
+                BEGIN TRANSACTION;
+
+                LET $owner = ($qrcode<-created<-?)[0];
+                {usage_check}
+
+                UPDATE $qrcode SET status = $status, updated_at = time::now() RETURN {QR_FIELDS};
+
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("qrcode", qrcode))
+            .bind(("status", status))
+            .bind(("max_usage", tier.map_or(0, |tier| tier.max_usage())))
+            .bind((
+                "counts_paused",
+                tier.is_none_or(|tier| tier.counts_paused()),
+            ))
+            .await?;
+
+        let mut result = check_thrown(result)?;
+
+        match result.take::<Option<models::DynamicQrResult>>(4)? {
+            Some(updated) => {
+                self.cache.invalidate(updated.server_url());
+                Ok(updated)
+            }
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn count_paused_urls(&self, owner: &models::Owner) -> Response<i32> {
+        /*
+            Counts an owner's paused dynamic URLs, which some tiers leave out of the usage limit.
+
+            Params:
+                owner (models::Owner): The user or organization the codes belong to.
+
+            Returns:
+                Response<i32>: The number of paused dynamic URLs outside the trash.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT count() AS total FROM $owner->created->dynamic_url WHERE status = 'paused' AND deleted_at = NONE GROUP ALL;")
+            .bind(("owner", owner.record()))
+            .await?;
+
+        match result.take::<Option<models::CountResult>>(0)? {
+            Some(count) => Ok(count.total as i32),
+            None => Ok(0),
+        }
+    }
+
+    pub async fn list_trash(
+        &self,
+        owner: &models::Owner,
+    ) -> Response<Vec<models::DynamicQrResult>> {
+        /*
+            Lists a user's or an organization's trashed dynamic URLs, most recently trashed first.
+
+            Params:
+                owner (models::Owner): The user or organization the codes belong to.
+
+            Returns:
+                Response<Vec<models::DynamicQrResult>>: The trashed dynamic URLs, with their `deleted_at`.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!("SELECT {QR_FIELDS} FROM $owner->created->dynamic_url WHERE deleted_at != NONE ORDER BY deleted_at DESC;"))
+            .bind(("owner", owner.record()))
+            .await?;
+
+        Ok(result.take::<Vec<models::DynamicQrResult>>(0)?)
+    }
+
+    pub async fn restore_dynamic_url(
+        &self,
+        owner: &models::Owner,
+        qrcode_id: &models::QrCodeId,
+        tier: &models::SubscriptionTier,
+    ) -> Response<models::DynamicQrResult> {
+        /*
+            Takes an owner's dynamic URL out of the trash and counts it towards their usage again,
+            as long as it was trashed less than `TRASH_RETENTION_DAYS` ago and still fits in the tier.
+
+            Params:
+                owner (models::Owner): The user or organization the code belongs to.
+                qrcode_id (models::QrCodeId): The ID of the trashed dynamic URL.
+                tier (models::SubscriptionTier): The owner's tier, whose limit the code has to fit in.
+
+            Returns:
+                Response<models::DynamicQrResult>: The restored dynamic URL, NotFound if the owner
+                didn't create it, it isn't in the trash or its retention window has passed, or
+                BadRequest if the owner has no room left.
+
+        */
+
+        let usage_check = usage_check("array::len($restored)");
+
+        let result = self
+            .db
+            .query(format!(
+                "
+                BEGIN TRANSACTION;
+
+                LET $restored = (UPDATE $owner->created->dynamic_url SET deleted_at = NONE, updated_at = time::now() WHERE id = $qrcode AND deleted_at > time::now() - duration::from::days($retention) RETURN AFTER);
+                {usage_check}
+
+                UPDATE $owner->subscribed->subscription SET usage += array::len($restored);
+
+                SELECT {QR_FIELDS} FROM $restored.id;
+
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("owner", owner.record()))
+            .bind(("qrcode", qrcode_id.record()))
+            .bind(("retention", TRASH_RETENTION_DAYS))
+            .bind(("max_usage", tier.max_usage()))
+            .bind(("counts_paused", tier.counts_paused()))
+            .await?;
+
+        let mut result = check_thrown(result)?;
+
+        match result.take::<Option<models::DynamicQrResult>>(5)? {
+            Some(restored) => {
+                self.cache.invalidate(restored.server_url());
+                Ok(restored)
+            }
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn purge_trash(&self, retention_days: i64) -> Response<usize> {
+        /*
+            Permanently deletes every dynamic URL that was trashed more than `retention_days` ago,
+            along with its revisions, scans, landing page, hosted file, app links and contact card.
+
+            Params:
+                retention_days (i64): How long trashed codes are kept.
+
+            Returns:
+                Response<usize>: The number of dynamic URLs deleted.
+
+        */
+
+        let mut result = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                LET $expired = (SELECT VALUE id FROM dynamic_url WHERE deleted_at != NONE AND deleted_at <= time::now() - duration::from::days($retention));
+
+                DELETE dynamic_url_revision WHERE dynamic_url INSIDE $expired;
+
+                DELETE scan WHERE dynamic_url INSIDE $expired;
+
+                DELETE landing_link WHERE page.dynamic_url INSIDE $expired;
+                DELETE landing_page WHERE dynamic_url INSIDE $expired;
+                DELETE app_link WHERE dynamic_url INSIDE $expired;
+                DELETE contact_card WHERE dynamic_url INSIDE $expired;
+                DELETE hosted_file WHERE dynamic_url INSIDE $expired RETURN BEFORE;
+
+                DELETE $expired RETURN BEFORE;
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("retention", retention_days))
+            .await?
+            .check()?;
+
+        let files = result.take::<Vec<HostedFileResult>>(7)?;
+        let purged = result.take::<Vec<models::ServerUrl>>(8)?;
+
+        remove_files(&files).await;
+
+        for code in &purged {
+            self.cache.invalidate(&code.server_url);
+        }
+
+        Ok(purged.len())
+    }
+
+    pub async fn list_revisions(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+    ) -> Response<Vec<models::RevisionResult>> {
+        /*
+            Lists the target URL history of a user's dynamic URL, newest first, with the scans
+            made while each revision was live.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the dynamic URL.
+
+            Returns:
+                Response<Vec<models::RevisionResult>>: The revisions, or NotFound if the user didn't create the code.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Viewer)
+            .await?;
+
+        let mut result = self
+            .db
+            .query(format!(
+                "SELECT {REVISION_FIELDS} FROM dynamic_url_revision WHERE dynamic_url = $qrcode ORDER BY created_at DESC;"
+            ))
+            .bind(("qrcode", qrcode))
+            .await?;
+
+        Ok(result.take::<Vec<models::RevisionResult>>(0)?)
+    }
+
+    pub async fn rollback_dynamic_url(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        revision_id: &models::RevisionId,
+    ) -> Response<models::DynamicQrResult> {
+        /*
+            Points a user's dynamic URL back at the target URL of one of its revisions. The rollback
+            is itself recorded as a new revision, so it can be undone the same way.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the dynamic URL.
+                revision_id (models::RevisionId): The revision to restore.
+
+            Returns:
+                Response<models::DynamicQrResult>: The updated dynamic URL, or NotFound if the user didn't
+                create the code or the revision belongs to another code.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Editor)
+            .await?;
+
+        let mut result = self
+            .db
+            .query("SELECT VALUE target_url FROM dynamic_url_revision WHERE id = $revision AND dynamic_url = $qrcode;")
+            .bind(("revision", revision_id.record()))
+            .bind(("qrcode", qrcode.clone()))
+            .await?;
+
+        let target_url = match result.take::<Option<String>>(0)? {
+            Some(target_url) => target_url,
+            None => return Err(ApiError::NotFound),
+        };
+
+        let mut result = self
+            .db
+            .query(format!(
+                "
+                BEGIN TRANSACTION;
+
+                LET $previous = $qrcode.target_url;
+
+                IF $previous != $target_url {{
+                    CREATE dynamic_url_revision SET dynamic_url = $qrcode, previous_target = $previous, target_url = $target_url, author = type::thing('user', $user_id), restored_from = $revision, created_at = time::now();
+                    UPDATE $qrcode SET target_url = $target_url, updated_at = time::now();
+                }};
+
+                SELECT {QR_FIELDS} FROM $qrcode;
+
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("qrcode", qrcode))
+            .bind(("revision", revision_id.record()))
+            .bind(("target_url", target_url))
+            .await?;
+
+        match result.take::<Option<models::DynamicQrResult>>(2)? {
+            Some(updated) => {
+                self.cache.invalidate(updated.server_url());
+                Ok(updated)
+            }
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn get_subscription_id(&self, owner: &models::Owner) -> Response<Option<String>> {
+        /*
+            Looks up a user's or an organization's subscription in the database.
+
+            Params:
+                owner (models::Owner): The user or organization.
+
+            Returns:
+                Response<Option<models::UserSubscription>>: The owner's subscription object, or None if no subscription was found.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT subscription_id FROM $owner->subscribed->subscription")
+            .bind(("owner", owner.record()))
+            .await?;
+
+        match result.take::<Option<models::SubscriptionId>>(0)? {
+            Some(id) => Ok(Some(id.subscription_id)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn delete_user_data(&self, user_id: &str) -> Response<bool> {
+        /*
+            Deletes a user's personal data from the database in one transaction: their subscription,
+            codes with their content, history and scans, webhooks, folders, pending transfers and
+            personal API keys. Organization memberships, organization codes and the keys the user
+            made for an organization are left alone.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+
+        */
+
+        let mut result = self
+            .db
+            .query(
+                "
+                    BEGIN TRANSACTION;
+
+                    LET $user = type::thing('user', $user_id);
+
+                    DELETE $user->subscribed->subscription;
+                    DELETE landing_link WHERE page.dynamic_url INSIDE $user->created->dynamic_url;
+                    DELETE landing_page WHERE dynamic_url INSIDE $user->created->dynamic_url;
+                    DELETE app_link WHERE dynamic_url INSIDE $user->created->dynamic_url;
+                    DELETE contact_card WHERE dynamic_url INSIDE $user->created->dynamic_url;
+                    DELETE dynamic_url_revision WHERE dynamic_url INSIDE $user->created->dynamic_url;
+                    DELETE scan WHERE dynamic_url INSIDE $user->created->dynamic_url;
+                    DELETE hosted_file WHERE dynamic_url INSIDE $user->created->dynamic_url RETURN BEFORE;
+                    DELETE $user->created->dynamic_url RETURN BEFORE;
+                    DELETE webhook_delivery WHERE webhook INSIDE $user->registered->webhook;
+                    DELETE $user->registered->webhook;
+                    DELETE $user->owns->folder;
+                    DELETE transfer WHERE sender = $user AND status = 'pending';
+                    DELETE api_key WHERE owner = $user;
+
+                    COMMIT TRANSACTION;",
+            )
+            .bind(("user_id", user_id.to_string()))
+            .await?
+            .check()?;
+
+        remove_files(&result.take::<Vec<HostedFileResult>>(8)?).await;
+
+        for deleted in result.take::<Vec<models::ServerUrl>>(9)? {
+            self.cache.invalidate(&deleted.server_url);
+        }
+
+        Ok(true)
+    }
+
+    pub async fn get_owner_from_subscription(
+        &self,
+        subscription_id: &str,
+    ) -> Response<models::Owner> {
+        /*
+            Looks up the user or organization a subscription ID belongs to.
+
+            Params:
+                subscription_id (str): The subscription ID to look up.
+
+            Returns:
+                Response<models::Owner>: The subscription's owner.
+
+        */
+
+        let mut result = self
+            .db
+            .query("RETURN (SELECT VALUE in FROM subscribed WHERE out = type::thing('subscription', $subscription_id))[0];")
+            .bind(("subscription_id", subscription_id.to_string()))
+            .await?;
+
+        match result.take::<Option<models::Owner>>(0)? {
+            Some(owner) => Ok(owner),
+            None => Err(ApiError::InternalServerError("No user found.".to_string())),
+        }
+    }
+
+    pub async fn get_owner_from_session(&self, session_id: &str) -> Response<models::Owner> {
+        /*
+            Looks up the user or organization a checkout session was started for.
+
+            Params:
+                session_id (string): The session ID to look up.
+
+            Returns:
+                Response<models::Owner>: The session's owner.
+
+        */
+
+        let mut result = self
+            .db
+            .query("RETURN (SELECT VALUE in FROM payment WHERE out = type::thing('session', $session_id))[0];")
+            .bind(("session_id", session_id.to_string()))
+            .await?;
+
+        match result.take::<Option<models::Owner>>(0)? {
+            Some(owner) => Ok(owner),
+            None => Err(ApiError::InternalServerError("No user found.".to_string())),
+        }
+    }
+
+    pub async fn insert_session(
+        &self,
+        owner: &models::Owner,
+        session: models::PaymentSession,
+    ) -> Response<models::PaymentSessionResult> {
+        /*
+            Inserts a new session into the database, for the user or organization that will own the subscription.
+
+            Params:
+                owner (models::Owner): The user or organization paying.
+                session_id (string): The session ID.
+                tier (string): The session's tier.
+
+        */
+
+        let mut result = self.db
+            .query("
+            RELATE $owner->payment->CREATE type::thing('session', $session_id) SET session_id = $session_id, tier = $tier, created_at = time::now();
+            
+            SELECT * FROM $owner->payment->session ORDER BY created_at DESC LIMIT 1;")
+            .bind(("owner", owner.record()))
+            .bind(("session_id", session.session_id))
+            .bind(("tier", session.tier))
+            .await?;
+
+        match result.take::<Option<models::PaymentSessionResult>>(1)? {
+            Some(created) => Ok(created),
+            None => Err(ApiError::InternalServerError(
+                "Failed to create session.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn insert_subscription(
+        &self,
+        owner: &models::Owner,
+        subscription: models::UserSubscription,
+    ) -> Response<models::UserSubscriptionResult> {
+        /*
+            Inserts a new subscription into the database.
+
+            Params:
+                owner (models::Owner): The user or organization subscribing.
+                subscription_id (string): The subscription ID.
+                tier (string): The subscription's tier.
+                start_date (datetime): The subscription's start date.
+                end_date (datetime): The subscription's end date.
+                usage (int): The subscription's usage.
+                subscription_status (string): The subscription's status.
+
+        */
+
+        let mut result = self
+            .db
+            .query("
+            RELATE $owner->subscribed->CREATE type::thing('subscription', $subscription_id) 
+            SET subscription_id = $subscription_id, tier = $tier, start_date = time::now(), end_date = time::now(), usage = 0, subscription_status = $subscription_status;
             
-            RELATE $user->payment->CREATE type::thing('session', $session_id) SET session_id = $session_id, tier = $tier, created_at = time::now();
+            SELECT * FROM $owner->subscribed->subscription LIMIT 1;")
+            .bind(("owner", owner.record()))
+            .bind(("subscription_id", subscription.sub_id))
+            .bind(("tier", subscription.tier))
+            .bind(("subscription_status", subscription.status))
+            .await?;
+
+        match result.take::<Option<models::UserSubscriptionResult>>(1)? {
+            Some(created) => Ok(created),
+            None => Err(ApiError::InternalServerError(
+                "Failed to create subscription.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn get_subscription(
+        &self,
+        owner: &models::Owner,
+    ) -> Response<models::UserSubscriptionResult> {
+        /*
+            Gets a user's or an organization's subscription from the database.
+
+            Params:
+                owner (models::Owner): The user or organization.
+
+            Returns:
+                Response<Option<models::UserSubscriptionResult>>: The owner's subscription object, or None if no subscription was found.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT * FROM $owner->subscribed->subscription;")
+            .bind(("owner", owner.record()))
+            .await?;
+
+        match result.take::<Option<models::UserSubscriptionResult>>(0)? {
+            Some(subscription) => Ok(subscription),
+            None => Err(ApiError::InternalServerError(
+                "No subscription found.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn override_subscription(
+        &self,
+        owner: &models::Owner,
+        subscription_id: &str,
+        new_tier: &str,
+    ) -> Response<models::UserSubscriptionResult> {
+        /*
+            Overrides a user's or an organization's subscription in the database.
+
+            Params:
+                owner (models::Owner): The user or organization the subscription belongs to.
+                subscription (models::UserSubscription): The new subscription object.
+
+            Returns:
+                Response<models::UserSubscriptionResult>: The updated subscription object.
+
+        */
+
+        let mut result = self
+            .db
+            .query("UPDATE $owner->subscribed->subscription SET tier = $tier, start_date = time::now(), end_date = time::now() WHERE subscription_id = $subscription_id;
             
-            SELECT * FROM $user->payment->session ORDER BY created_at DESC LIMIT 1;")
+            SELECT * FROM subscription WHERE subscription_id = $subscription_id LIMIT 1;")
+            .bind(("owner", owner.record()))
+            .bind(("tier", new_tier.to_string()))
+            .bind(("subscription_id", subscription_id.to_string() ))
+            .await?;
+
+        match result.take::<Option<models::UserSubscriptionResult>>(1)? {
+            Some(updated) => Ok(updated),
+            None => Err(ApiError::InternalServerError(
+                "Failed to update subscription.".to_string(),
+            )),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub async fn set_subscription_status(
+        &self,
+        user_id: &str,
+        status: &str,
+    ) -> Response<models::UserSubscriptionResult> {
+        /*
+            Sets a user's subscription status to inactive.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+
+            Returns:
+                Response<models::UserSubscriptionResult>: The updated subscription object.
+
+        */
+
+        let mut result = self
+            .db
+            .query("UPDATE type::thing('user', $user_id)->subscribed->subscription SET subscription_status = $status;")
+            .bind(("user_id", user_id.to_string()))
+            .bind(("status", status.to_string()))
+            .await?;
+
+        match result.take::<Option<models::UserSubscriptionResult>>(0)? {
+            Some(updated) => Ok(updated),
+            None => Err(ApiError::InternalServerError(
+                "Failed to update subscription.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn validate_subscription_status(&self, owner: &models::Owner) -> Response<bool> {
+        /*
+            Checks the status of a user's or an organization's subscription.
+
+            Params:
+                owner (models::Owner): The user or organization.
+
+            Returns:
+                Response<String>: The owner's subscription status.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT subscription_status FROM $owner->subscribed->subscription;")
+            .bind(("owner", owner.record()))
+            .await?;
+
+        match result.take::<Option<models::SubscriptionStatus>>(0)? {
+            Some(status) => {
+                if status.subscription_status == "complete" {
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            None => Ok(false),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub async fn increment_usage(
+        &self,
+        owner: &models::Owner,
+    ) -> Response<models::UserSubscriptionResult> {
+        /*
+            Updates the usage of a user's or an organization's subscription.
+
+            Params:
+                owner (models::Owner): The user or organization.
+
+            Returns:
+                Response<models::UserSubscriptionResult>: The updated subscription object.
+
+        */
+
+        let mut result = self
+            .db
+            .query("UPDATE $owner->subscribed->subscription SET usage = usage + 1;")
+            .bind(("owner", owner.record()))
+            .await?;
+
+        match result.take::<Option<models::UserSubscriptionResult>>(0)? {
+            Some(updated) => Ok(updated),
+            None => Err(ApiError::InternalServerError(
+                "Failed to update usage.".to_string(),
+            )),
+        }
+    }
+
+    pub fn scan_cache_stats(&self) -> CacheStats {
+        /*
+            Returns the hit and miss counters of the scan cache.
+
+            Returns:
+                CacheStats: Hits, misses and the number of cached redirects.
+
+        */
+
+        self.cache.stats()
+    }
+
+    pub async fn insert_webhook(
+        &self,
+        owner: &models::Owner,
+        webhook: Webhook,
+        secret: &str,
+    ) -> Response<WebhookSecretResult> {
+        /*
+            Registers a webhook that receives scan events for all of a user's or an organization's
+            dynamic URLs.
+
+            Params:
+                owner (models::Owner): The user or organization the webhook is for.
+                webhook (Webhook): The endpoint URL, delivery mode and whether to include bots.
+                secret (string): The secret used to sign deliveries.
+
+            Returns:
+                Response<WebhookSecretResult>: The created webhook, including its secret.
+
+        */
+
+        let mut result = self
+            .db
+            .query(
+                "
+                LET $hook = type::thing('webhook', rand::ulid());
+
+                RELATE $owner->registered->CREATE $hook
+                SET url = $url,
+                secret = $secret,
+                mode = $mode,
+                include_bots = $include_bots,
+                active = true,
+                created_at = time::now();
+
+                SELECT * FROM $hook;",
+            )
+            .bind(("owner", owner.record()))
+            .bind(("url", webhook.url))
+            .bind(("secret", secret.to_string()))
+            .bind(("mode", webhook.mode))
+            .bind(("include_bots", webhook.include_bots))
+            .await?;
+
+        match result.take::<Option<WebhookSecretResult>>(2)? {
+            Some(created) => Ok(created),
+            None => Err(ApiError::InternalServerError(
+                "Failed to create webhook.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn list_webhooks(&self, owner: &models::Owner) -> Response<Vec<WebhookResult>> {
+        /*
+            Lists the webhooks registered by a user or an organization, without their secrets.
+
+            Params:
+                owner (models::Owner): The user or organization the webhooks are for.
+
+            Returns:
+                Response<Vec<WebhookResult>>: The owner's webhooks.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT id, url, mode, include_bots, active, created_at FROM $owner->registered->webhook ORDER BY created_at;")
+            .bind(("owner", owner.record()))
+            .await?;
+
+        Ok(result.take::<Vec<WebhookResult>>(0)?)
+    }
+
+    async fn find_webhook(
+        &self,
+        owner: &models::Owner,
+        webhook_id: &models::WebhookId,
+    ) -> Response<RecordId> {
+        /*
+            Resolves a webhook ID to its record, as long as it belongs to the owner.
+
+            Params:
+                owner (models::Owner): The user or organization the webhook is for.
+                webhook_id (models::WebhookId): The ID of the webhook.
+
+            Returns:
+                Response<RecordId>: The webhook record, or NotFound.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT VALUE id FROM $owner->registered->webhook WHERE id = $webhook;")
+            .bind(("owner", owner.record()))
+            .bind(("webhook", webhook_id.record()))
+            .await?;
+
+        match result.take::<Option<RecordId>>(0)? {
+            Some(webhook) => Ok(webhook),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn delete_webhook(
+        &self,
+        owner: &models::Owner,
+        webhook_id: &models::WebhookId,
+    ) -> Response<bool> {
+        /*
+            Deletes a user's or an organization's webhook along with its delivery log.
+
+            Params:
+                owner (models::Owner): The user or organization the webhook is for.
+                webhook_id (models::WebhookId): The ID of the webhook.
+
+        */
+
+        let webhook = self.find_webhook(owner, webhook_id).await?;
+
+        let _ = self
+            .db
+            .query(
+                "
+                DELETE webhook_delivery WHERE webhook = $webhook;
+                DELETE $webhook;",
+            )
+            .bind(("webhook", webhook))
+            .await?;
+
+        Ok(true)
+    }
+
+    pub async fn list_webhook_deliveries(
+        &self,
+        owner: &models::Owner,
+        webhook_id: &models::WebhookId,
+    ) -> Response<Vec<WebhookDeliveryResult>> {
+        /*
+            Lists the 100 most recent deliveries of a user's or an organization's webhook.
+
+            Params:
+                owner (models::Owner): The user or organization the webhook is for.
+                webhook_id (models::WebhookId): The ID of the webhook.
+
+            Returns:
+                Response<Vec<WebhookDeliveryResult>>: The delivery log, newest first.
+
+        */
+
+        let webhook = self.find_webhook(owner, webhook_id).await?;
+
+        let mut result = self
+            .db
+            .query("SELECT * FROM webhook_delivery WHERE webhook = $webhook ORDER BY created_at DESC LIMIT 100;")
+            .bind(("webhook", webhook))
+            .await?;
+
+        Ok(result.take::<Vec<WebhookDeliveryResult>>(0)?)
+    }
+
+    pub async fn redeliver_webhook_delivery(
+        &self,
+        owner: &models::Owner,
+        webhook_id: &models::WebhookId,
+        delivery_id: &models::DeliveryId,
+    ) -> Response<WebhookDeliveryResult> {
+        /*
+            Queues a delivery to be sent again, including dead ones, with a fresh set of attempts.
+
+            Params:
+                owner (models::Owner): The user or organization the webhook is for.
+                webhook_id (models::WebhookId): The ID of the webhook.
+                delivery_id (models::DeliveryId): The ID of the delivery.
+
+            Returns:
+                Response<WebhookDeliveryResult>: The requeued delivery.
+
+        */
+
+        let webhook = self.find_webhook(owner, webhook_id).await?;
+
+        let mut result = self
+            .db
+            .query("UPDATE $delivery SET status = 'pending', attempts = 0, next_attempt_at = time::now() WHERE webhook = $webhook;")
+            .bind(("delivery", delivery_id.record()))
+            .bind(("webhook", webhook))
+            .await?;
+
+        match result.take::<Option<WebhookDeliveryResult>>(0)? {
+            Some(delivery) => Ok(delivery),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn claim_webhook_deliveries(&self, limit: usize) -> Response<Vec<DueDelivery>> {
+        /*
+            Picks deliveries that are due and pushes their next attempt back a minute, so
+            another dispatcher doesn't send them while this one is. The claim is one transaction
+            that only takes rows still due when they are updated, so two dispatchers never claim
+            the same delivery.
+
+            Params:
+                limit (usize): The maximum number of deliveries to claim.
+
+            Returns:
+                Response<Vec<DueDelivery>>: The claimed deliveries with their endpoint and secret.
+
+        */
+
+        let mut result = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                LET $due = (SELECT id, next_attempt_at FROM webhook_delivery WHERE (status = 'pending' OR status = 'retrying') AND next_attempt_at <= time::now() ORDER BY next_attempt_at LIMIT $limit).id;
+
+                LET $claimed = (UPDATE $due SET next_attempt_at = time::now() + 1m WHERE (status = 'pending' OR status = 'retrying') AND next_attempt_at <= time::now() RETURN VALUE id);
+
+                SELECT id, event, payload, attempts, webhook.url AS url, webhook.secret AS secret FROM $claimed;
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("limit", limit))
+            .await?
+            .check()?;
+
+        Ok(result.take::<Vec<DueDelivery>>(2)?)
+    }
+
+    pub async fn record_webhook_attempt(
+        &self,
+        delivery: &DueDelivery,
+        outcome: DeliveryOutcome,
+    ) -> Response<bool> {
+        /*
+            Records the outcome of a delivery attempt. Failed deliveries are retried with
+            exponential backoff until `MAX_ATTEMPTS`, after which they are marked dead.
+
+            Params:
+                delivery (DueDelivery): The delivery that was attempted.
+                outcome (DeliveryOutcome): The result of the attempt.
+
+        */
+
+        let attempts = delivery.attempts + 1;
+
+        let query = match outcome {
+            DeliveryOutcome::Delivered(status) => self
+                .db
+                .query("UPDATE $delivery SET status = $status, attempts = $attempts, last_status = $last_status, last_error = NONE, delivered_at = time::now();")
+                .bind(("status", DeliveryStatus::Delivered))
+                .bind(("last_status", status)),
+            DeliveryOutcome::Failed { status, error } => {
+                let next = if attempts >= MAX_ATTEMPTS {
+                    DeliveryStatus::Dead
+                } else {
+                    DeliveryStatus::Retrying
+                };
+
+                self.db
+                    .query("UPDATE $delivery SET status = $status, attempts = $attempts, last_status = $last_status ?? NONE, last_error = $error, next_attempt_at = time::now() + duration::from::secs($delay);")
+                    .bind(("status", next))
+                    .bind(("last_status", status))
+                    .bind(("error", error))
+                    .bind(("delay", backoff(attempts).as_secs()))
+            }
+        };
+
+        let _ = query
+            .bind(("delivery", delivery.id.clone()))
+            .bind(("attempts", attempts))
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn find_folder(&self, user_id: &str, folder_id: &FolderId) -> Response<RecordId> {
+        /*
+            Resolves a folder ID to its record, as long as it belongs to the user.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                folder_id (FolderId): The ID of the folder.
+
+            Returns:
+                Response<RecordId>: The folder record, or NotFound.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT VALUE id FROM type::thing('user', $user_id)->owns->folder WHERE id = $folder;")
+            .bind(("user_id", user_id.to_string()))
+            .bind(("folder", folder_id.record()))
+            .await?;
+
+        match result.take::<Option<RecordId>>(0)? {
+            Some(folder) => Ok(folder),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    async fn find_parent_folder(
+        &self,
+        user_id: &str,
+        parent: &Option<String>,
+    ) -> Response<Option<RecordId>> {
+        // An optional parent from a request body, which must be one of the user's folders.
+        match parent {
+            Some(parent) => {
+                let parent = FolderId::parse(parent).ok_or(ApiError::BadRequest)?;
+                Ok(Some(self.find_folder(user_id, &parent).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn insert_folder(&self, user_id: &str, folder: Folder) -> Response<FolderResult> {
+        /*
+            Creates a folder for organizing a user's dynamic URLs.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                folder (Folder): The folder name and optional parent folder.
+
+            Returns:
+                Response<FolderResult>: The created folder.
+
+        */
+
+        let parent = self.find_parent_folder(user_id, &folder.parent).await?;
+
+        let mut result = self
+            .db
+            .query(format!(
+                "
+                LET $user = type::thing('user', $user_id);
+                LET $folder = type::thing('folder', rand::ulid());
+
+                RELATE $user->owns->CREATE $folder
+                SET name = $name,
+                parent = $parent ?? NONE,
+                created_at = time::now(),
+                updated_at = time::now();
+
+                SELECT {FOLDER_FIELDS} FROM $folder;"
+            ))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("name", folder.name))
+            .bind(("parent", parent))
+            .await?;
+
+        match result.take::<Option<FolderResult>>(3)? {
+            Some(created) => Ok(created),
+            None => Err(ApiError::InternalServerError(
+                "Failed to create folder.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn list_folders(&self, user_id: &str) -> Response<Vec<FolderResult>> {
+        /*
+            Lists all of a user's folders with the scan counts of the codes in them.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+
+            Returns:
+                Response<Vec<FolderResult>>: The folders sorted by name, each with the counts of its
+                own codes and, in `total`, of its subfolders' codes as well.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!(
+                "SELECT {FOLDER_FIELDS} FROM type::thing('user', $user_id)->owns->folder ORDER BY name;"
+            ))
+            .bind(("user_id", user_id.to_string()))
+            .await?;
+
+        let mut folders = result.take::<Vec<FolderResult>>(0)?;
+        rollup(&mut folders);
+
+        Ok(folders)
+    }
+
+    pub async fn select_folder(
+        &self,
+        user_id: &str,
+        folder_id: &FolderId,
+    ) -> Response<FolderResult> {
+        /*
+            Selects one of a user's folders.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                folder_id (FolderId): The ID of the folder.
+
+            Returns:
+                Response<FolderResult>: The folder with its counts, or NotFound.
+
+        */
+
+        self.list_folders(user_id)
+            .await?
+            .into_iter()
+            .find(|folder| &folder.id == folder_id)
+            .ok_or(ApiError::NotFound)
+    }
+
+    pub async fn update_folder(
+        &self,
+        user_id: &str,
+        folder_id: &FolderId,
+        folder: Folder,
+    ) -> Response<FolderResult> {
+        /*
+            Renames a folder or moves it under another parent.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                folder_id (FolderId): The ID of the folder.
+                folder (Folder): The new name and parent folder.
+
+            Returns:
+                Response<FolderResult>: The updated folder, NotFound, or BadRequest if the folder
+                would end up inside itself.
+
+        */
+
+        let record = self.find_folder(user_id, folder_id).await?;
+        let parent = self.find_parent_folder(user_id, &folder.parent).await?;
+
+        if let Some(parent) = &parent {
+            let folders = self.list_folders(user_id).await?;
+
+            if descendants(&folders, folder_id)
+                .iter()
+                .any(|descendant| &descendant.record() == parent)
+            {
+                return Err(ApiError::BadRequest);
+            }
+        }
+
+        let _ = self
+            .db
+            .query("UPDATE $folder SET name = $name, parent = $parent ?? NONE, updated_at = time::now();")
+            .bind(("folder", record))
+            .bind(("name", folder.name))
+            .bind(("parent", parent))
+            .await?
+            .check()?;
+
+        self.select_folder(user_id, folder_id).await
+    }
+
+    pub async fn delete_folder(
+        &self,
+        user_id: &str,
+        folder_id: &FolderId,
+        mode: FolderDeleteMode,
+    ) -> Response<FolderDeleted> {
+        /*
+            Deletes a folder.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                folder_id (FolderId): The ID of the folder.
+                mode (FolderDeleteMode):
+                    - `MoveToParent`: Its codes and subfolders move to its parent, or to the top level.
+                    - `Cascade`: Its subfolders are deleted too and all of their codes are moved to
+                      the trash, where they no longer count towards the subscription's usage.
+
+            Returns:
+                Response<FolderDeleted>: How many folders were deleted and codes trashed, or NotFound.
+
+        */
+
+        let record = self.find_folder(user_id, folder_id).await?;
+
+        match mode {
+            FolderDeleteMode::MoveToParent => {
+                let _ = self
+                    .db
+                    .query(
+                        "
+                        BEGIN TRANSACTION;
+
+                        LET $parent = $folder.parent;
+                        LET $codes = (SELECT VALUE in FROM filed_in WHERE out = $folder);
+
+                        DELETE $folder;
+
+                        IF $parent {
+                            FOR $code IN $codes {
+                                RELATE $code->filed_in->$parent;
+                            };
+                        };
+
+                        UPDATE folder SET parent = $parent, updated_at = time::now() WHERE parent = $folder;
+
+                        COMMIT TRANSACTION;",
+                    )
+                    .bind(("folder", record))
+                    .await?
+                    .check()?;
+
+                Ok(FolderDeleted {
+                    folders: 1,
+                    qrcodes: 0,
+                })
+            }
+            FolderDeleteMode::Cascade => {
+                let folders: Vec<RecordId> =
+                    descendants(&self.list_folders(user_id).await?, folder_id)
+                        .iter()
+                        .map(FolderId::record)
+                        .collect();
+
+                let mut result = self
+                    .db
+                    .query(
+                        "
+                        BEGIN TRANSACTION;
+
+                        LET $codes = (SELECT VALUE in FROM filed_in WHERE out INSIDE $folders);
+
+                        LET $trashed = (UPDATE $codes SET deleted_at = time::now() WHERE deleted_at = NONE RETURN BEFORE);
+
+                        UPDATE type::thing('user', $user_id)->subscribed->subscription SET usage = math::max([usage - array::len($trashed), 0]);
+
+                        DELETE $folders;
+
+                        SELECT server_url FROM $trashed;
+
+                        COMMIT TRANSACTION;",
+                    )
+                    .bind(("user_id", user_id.to_string()))
+                    .bind(("folders", folders.clone()))
+                    .await?
+                    .check()?;
+
+                let deleted = result.take::<Vec<models::ServerUrl>>(4)?;
+
+                for code in &deleted {
+                    self.cache.invalidate(&code.server_url);
+                }
+
+                Ok(FolderDeleted {
+                    folders: folders.len(),
+                    qrcodes: deleted.len(),
+                })
+            }
+        }
+    }
+
+    pub async fn move_dynamic_url(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        folder: &Option<String>,
+    ) -> Response<models::DynamicQrResult> {
+        /*
+            Files a dynamic URL in one of the user's folders, replacing its current folder.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the dynamic URL.
+                folder (Option<string>): The ID of the folder, or None to take the code out of its folder.
+
+            Returns:
+                Response<models::DynamicQrResult>: The moved dynamic URL, or NotFound if the user
+                doesn't own the code or the folder.
+
+        */
+
+        // Folders are personal, so only the user's own codes can be filed in them.
+        let owner = self
+            .dynamic_url_owner(user_id, qrcode_id, Role::Editor)
+            .await?;
+
+        if owner != models::Owner::User(user_id.to_string()) {
+            return Err(ApiError::NotFound);
+        }
+
+        let qrcode = qrcode_id.record();
+        let folder = self.find_parent_folder(user_id, folder).await?;
+
+        let _ = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                DELETE $qrcode->filed_in;
+
+                IF $folder {
+                    RELATE $qrcode->filed_in->$folder;
+                };
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("qrcode", qrcode))
+            .bind(("folder", folder))
+            .await?
+            .check()?;
+
+        self.select_dynamic_url(user_id, qrcode_id).await
+    }
+
+    pub async fn insert_transfer(
+        &self,
+        user_id: &str,
+        recipient_email: &str,
+        qrcode_ids: &[models::QrCodeId],
+    ) -> Response<TransferResult> {
+        /*
+            Starts handing some of a user's dynamic URLs over to the account with the given email.
+            Nothing moves until the recipient accepts.
+
+            Params:
+                user_id (string): The sender's Auth0 ID.
+                recipient_email (string): The normalized email of the recipient.
+                qrcode_ids (&[models::QrCodeId]): The IDs of the dynamic URLs, without duplicates.
+
+            Returns:
+                Response<TransferResult>: The pending transfer. NotFound if any code isn't the user's,
+                BadRequest if the email is the sender's own or a code is already in a pending transfer.
+
+        */
+
+        let qrcodes = self
+            .find_dynamic_urls(&models::Owner::User(user_id.to_string()), qrcode_ids)
+            .await?;
+
+        let mut result = self
+            .db
+            .query(
+                "
+                LET $user = type::thing('user', $user_id);
+
+                RETURN string::lowercase($user.email) = $email;
+
+                SELECT count() AS total FROM transfer WHERE status = 'pending' AND expires_at > time::now() AND qrcodes CONTAINSANY $qrcodes GROUP ALL;",
+            )
+            .bind(("user_id", user_id.to_string()))
+            .bind(("email", recipient_email.to_string()))
+            .bind(("qrcodes", qrcodes.clone()))
+            .await?;
+
+        let own_email = result.take::<Option<bool>>(1)?.unwrap_or(false);
+        let pending = result.take::<Option<models::CountResult>>(2)?;
+
+        if own_email || pending.is_some_and(|pending| pending.total > 0) {
+            return Err(ApiError::BadRequest);
+        }
+
+        let mut result = self
+            .db
+            .query(format!(
+                "CREATE transfer SET sender = type::thing('user', $user_id), recipient_email = $email, qrcodes = $qrcodes, status = 'pending', created_at = time::now(), expires_at = time::now() + duration::from::days($expiry) RETURN {TRANSFER_FIELDS};"
+            ))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("email", recipient_email.to_string()))
+            .bind(("qrcodes", qrcodes))
+            .bind(("expiry", TRANSFER_EXPIRY_DAYS))
+            .await?;
+
+        match result.take::<Option<TransferResult>>(0)? {
+            Some(transfer) => Ok(transfer),
+            None => Err(ApiError::InternalServerError(
+                "Failed to create transfer.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn list_transfers(&self, user_id: &str) -> Response<TransferList> {
+        /*
+            Lists the transfers a user started and the ones sent to their email, newest first.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+
+            Returns:
+                Response<TransferList>: The outgoing and incoming transfers.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!(
+                "
+                LET $user = type::thing('user', $user_id);
+
+                SELECT {TRANSFER_FIELDS} FROM transfer WHERE sender = $user ORDER BY created_at DESC;
+
+                SELECT {TRANSFER_FIELDS} FROM transfer WHERE recipient_email = string::lowercase($user.email) ORDER BY created_at DESC;"
+            ))
+            .bind(("user_id", user_id.to_string()))
+            .await?;
+
+        Ok(TransferList {
+            outgoing: result.take::<Vec<TransferResult>>(1)?,
+            incoming: result.take::<Vec<TransferResult>>(2)?,
+        })
+    }
+
+    pub async fn select_incoming_transfer(
+        &self,
+        user_id: &str,
+        transfer_id: &models::TransferId,
+    ) -> Response<TransferResult> {
+        /*
+            Selects a pending, unexpired transfer sent to a user's email.
+
+            Params:
+                user_id (string): The recipient's Auth0 ID.
+                transfer_id (models::TransferId): The ID of the transfer.
+
+            Returns:
+                Response<TransferResult>: The transfer, or NotFound if it isn't pending for this user.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!(
+                "SELECT {TRANSFER_FIELDS} FROM ONLY $transfer WHERE status = 'pending' AND expires_at > time::now() AND recipient_email = string::lowercase(type::thing('user', $user_id).email);"
+            ))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("transfer", transfer_id.record()))
+            .await?;
+
+        match result.take::<Option<TransferResult>>(0)? {
+            Some(transfer) => Ok(transfer),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn accept_transfer(
+        &self,
+        user_id: &str,
+        transfer_id: &models::TransferId,
+        tier: &models::SubscriptionTier,
+    ) -> Response<TransferResult> {
+        /*
+            Moves the codes of a transfer to the recipient in one transaction: the `created` edges
+            are moved, the codes leave the sender's folders and both subscriptions' usage is adjusted.
+            Codes the sender deleted in the meantime are left out. The transaction checks the transfer
+            is still pending and that its codes and their files fit in the recipient's tier, so
+            racing accepts or cancels can't move the codes twice or past the limits.
+
+            Params:
+                user_id (string): The recipient's Auth0 ID.
+                transfer_id (models::TransferId): The ID of the transfer.
+                tier (models::SubscriptionTier): The recipient's tier, whose limits the codes have to fit in.
+
+            Returns:
+                Response<TransferResult>: The accepted transfer with the codes that moved, NotFound
+                if it isn't pending for this user, or BadRequest if the recipient has no room left.
+
+        */
+
+        let transfer = self.select_incoming_transfer(user_id, transfer_id).await?;
+
+        let usage_check = usage_check("array::len($codes)");
+
+        let result = self
+            .db
+            .query(format!(
+                "
+                BEGIN TRANSACTION;
+
+                LET $user = type::thing('user', $user_id);
+                LET $owner = $user;
+                LET $sender = type::thing('user', $sender_id);
+
+                IF $transfer.status != 'pending' OR $transfer.expires_at <= time::now() {{
+                    THROW '{NO_LONGER_AVAILABLE}';
+                }};
+
+                LET $codes = (SELECT VALUE id FROM $sender->created->dynamic_url WHERE id INSIDE $transfer.qrcodes AND deleted_at = NONE);
+                {usage_check}
+
+                LET $size = math::sum((SELECT VALUE size FROM hosted_file WHERE dynamic_url INSIDE $codes));
+                LET $stored = math::sum((SELECT VALUE size FROM hosted_file WHERE dynamic_url INSIDE $owner->created->dynamic_url));
+
+                IF $size > 0 AND $stored + $size > $max_storage {{
+                    THROW '{STORAGE_LIMIT_REACHED}';
+                }};
+
+                DELETE created WHERE in = $sender AND out INSIDE $codes;
+                DELETE filed_in WHERE in INSIDE $codes;
+
+                RELATE $user->created->$codes;
+
+                UPDATE $sender->subscribed->subscription SET usage = math::max([usage - array::len($codes), 0]);
+                UPDATE $user->subscribed->subscription SET usage += array::len($codes);
+
+                UPDATE $transfer SET status = 'accepted', recipient = $user, qrcodes = $codes, resolved_at = time::now() RETURN {TRANSFER_FIELDS};
+
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("user_id", user_id.to_string()))
+            .bind(("sender_id", transfer.sender))
+            .bind(("transfer", transfer_id.record()))
+            .bind(("max_usage", tier.max_usage()))
+            .bind(("counts_paused", tier.counts_paused()))
+            .bind(("max_storage", tier.max_storage()))
+            .await?;
+
+        let mut result = check_thrown(result)?;
+
+        match result.take::<Option<TransferResult>>(16)? {
+            Some(accepted) => Ok(accepted),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn decline_transfer(
+        &self,
+        user_id: &str,
+        transfer_id: &models::TransferId,
+    ) -> Response<TransferResult> {
+        /*
+            Declines a pending transfer sent to a user's email. The codes stay with the sender.
+
+            Params:
+                user_id (string): The recipient's Auth0 ID.
+                transfer_id (models::TransferId): The ID of the transfer.
+
+            Returns:
+                Response<TransferResult>: The declined transfer, or NotFound if it isn't pending for this user.
+
+        */
+
+        self.select_incoming_transfer(user_id, transfer_id).await?;
+
+        self.resolve_transfer(transfer_id, Some(user_id), TransferStatus::Declined)
+            .await
+    }
+
+    pub async fn cancel_transfer(
+        &self,
+        user_id: &str,
+        transfer_id: &models::TransferId,
+    ) -> Response<TransferResult> {
+        /*
+            Withdraws a pending transfer a user started.
+
+            Params:
+                user_id (string): The sender's Auth0 ID.
+                transfer_id (models::TransferId): The ID of the transfer.
+
+            Returns:
+                Response<TransferResult>: The cancelled transfer, or NotFound if the user didn't start it or it isn't pending.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT VALUE id FROM ONLY $transfer WHERE sender = type::thing('user', $user_id) AND status = 'pending';")
+            .bind(("user_id", user_id.to_string()))
+            .bind(("transfer", transfer_id.record()))
+            .await?;
+
+        if result.take::<Option<RecordId>>(0)?.is_none() {
+            return Err(ApiError::NotFound);
+        }
+
+        self.resolve_transfer(transfer_id, None, TransferStatus::Cancelled)
+            .await
+    }
+
+    async fn resolve_transfer(
+        &self,
+        transfer_id: &models::TransferId,
+        recipient_id: Option<&str>,
+        status: TransferStatus,
+    ) -> Response<TransferResult> {
+        /*
+            Closes a pending transfer without moving any codes.
+
+            Params:
+                transfer_id (models::TransferId): The ID of the transfer.
+                recipient_id (Option<string>): The recipient's Auth0 ID when they answered it.
+                status (TransferStatus): `Declined` or `Cancelled`.
+
+            Returns:
+                Response<TransferResult>: The closed transfer, or NotFound if it was no longer pending.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!(
+                "UPDATE $transfer SET status = $status, recipient = IF $recipient_id THEN type::thing('user', $recipient_id) ELSE NONE END, resolved_at = time::now() WHERE status = 'pending' RETURN {TRANSFER_FIELDS};"
+            ))
+            .bind(("transfer", transfer_id.record()))
+            .bind(("recipient_id", recipient_id.map(str::to_string)))
+            .bind(("status", status))
+            .await?;
+
+        match result.take::<Option<TransferResult>>(0)? {
+            Some(transfer) => Ok(transfer),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn select_landing_page(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+    ) -> Response<LandingPageResult> {
+        /*
+            Selects the landing page of a user's page code, with the clicks on each link.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the page code.
+
+            Returns:
+                Response<LandingPageResult>: The landing page, or NotFound if the user didn't create
+                the code or it isn't a page code.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Viewer)
+            .await?;
+
+        let mut result = self
+            .db
+            .query(format!(
+                "SELECT {PAGE_FIELDS} FROM landing_page WHERE dynamic_url = $qrcode;"
+            ))
+            .bind(("qrcode", qrcode))
+            .await?;
+
+        match result.take::<Option<LandingPageResult>>(0)? {
+            Some(page) => Ok(page),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn update_landing_page(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        page: &LandingPage,
+    ) -> Response<LandingPageResult> {
+        /*
+            Replaces the title, avatar and links of a user's landing page in one transaction.
+            Links sent with an existing ID keep their clicks, links left out are deleted.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the page code.
+                page (LandingPage): The normalized page content.
+
+            Returns:
+                Response<LandingPageResult>: The updated landing page. NotFound if the user didn't create
+                the code or it isn't a page code, BadRequest if a link ID isn't on this page.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Editor)
+            .await?;
+
+        let mut result = self
+            .db
+            .query(
+                "
+                LET $page = (SELECT VALUE id FROM landing_page WHERE dynamic_url = $qrcode)[0];
+
+                RETURN $page;
+
+                SELECT VALUE id FROM landing_link WHERE page = $page;",
+            )
+            .bind(("qrcode", qrcode.clone()))
+            .await?;
+
+        let page_id = match result.take::<Option<RecordId>>(1)? {
+            Some(page_id) => page_id,
+            None => return Err(ApiError::NotFound),
+        };
+        let existing = result.take::<Vec<RecordId>>(2)?;
+
+        let mut links = Vec::with_capacity(page.links.len());
+        for (position, link) in page.links.iter().enumerate() {
+            let id = link
+                .id
+                .as_deref()
+                .and_then(models::LinkId::parse)
+                .map(|id| id.record());
+
+            if id.as_ref().is_some_and(|id| !existing.contains(id)) {
+                return Err(ApiError::BadRequest);
+            }
+
+            links.push(LinkRecord {
+                id,
+                title: link.title.clone(),
+                url: link.url.clone(),
+                position,
+            });
+        }
+
+        let mut result = self
+            .db
+            .query(format!(
+                "
+                BEGIN TRANSACTION;
+
+                UPDATE $page SET title = $title, avatar = $avatar ?? NONE, updated_at = time::now();
+
+                DELETE landing_link WHERE page = $page AND id NOTINSIDE $links.id;
+
+                FOR $link IN $links {{
+                    IF $link.id {{
+                        UPDATE $link.id SET title = $link.title, url = $link.url, position = $link.position;
+                    }} ELSE {{
+                        CREATE landing_link SET page = $page, title = $link.title, url = $link.url, position = $link.position;
+                    }};
+                }};
+
+                UPDATE $qrcode SET updated_at = time::now();
+
+                SELECT {PAGE_FIELDS} FROM ONLY $page;
+
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("page", page_id))
+            .bind(("qrcode", qrcode))
+            .bind(("title", page.title.clone()))
+            .bind(("avatar", page.avatar.clone()))
+            .bind(("links", links))
+            .await?
+            .check()?;
+
+        match result.take::<Option<LandingPageResult>>(4)? {
+            Some(updated) => Ok(updated),
+            None => Err(ApiError::InternalServerError(
+                "Failed to update landing page.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn lookup_landing_page(
+        &self,
+        server_url: &models::QrSlug,
+    ) -> Response<LandingPageResult> {
+        /*
+            Looks up the landing page a page code's server URL renders.
+
+            Params:
+                server_url (models::QrSlug): The server URL of the page code.
+
+            Returns:
+                Response<LandingPageResult>: The landing page, or NotFound if the code has none.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!(
+                "SELECT {PAGE_FIELDS} FROM landing_page WHERE dynamic_url = (SELECT VALUE id FROM dynamic_url WHERE server_url = $server_url)[0];"
+            ))
+            .bind(("server_url", server_url.as_str().to_string()))
+            .await?;
+
+        match result.take::<Option<LandingPageResult>>(0)? {
+            Some(page) => Ok(page),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn record_link_click(
+        &self,
+        server_url: &models::QrSlug,
+        link_id: &models::LinkId,
+        client: ClientKind,
+    ) -> Response<String> {
+        /*
+            Counts a click on a landing page link. Bot clicks are counted separately, like scans.
+
+            Params:
+                server_url (models::QrSlug): The server URL of the page code the link is on.
+                link_id (models::LinkId): The ID of the link.
+                client (ClientKind): Whether the click came from a person or a bot.
+
+            Returns:
+                Response<String>: The URL the link goes to, or NotFound if it isn't on that page.
+
+        */
+
+        let counter = match client {
+            ClientKind::Human => "clicks",
+            ClientKind::Bot => "bot_clicks",
+        };
+
+        let mut result = self
+            .db
+            .query(format!(
+                "UPDATE $link SET {counter} += 1 WHERE page.dynamic_url.server_url = $server_url RETURN VALUE url;"
+            ))
+            .bind(("link", link_id.record()))
+            .bind(("server_url", server_url.as_str().to_string()))
+            .await?;
+
+        match result.take::<Option<String>>(0)? {
+            Some(url) => Ok(url),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn select_file_code(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+    ) -> Response<Option<HostedFileResult>> {
+        /*
+            Selects the hosted file of a user's file code.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the file code.
+
+            Returns:
+                Response<Option<HostedFileResult>>: The file, or none if nothing was uploaded yet. NotFound
+                if the user didn't create the code, BadRequest if it isn't a file code.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Viewer)
+            .await?;
+
+        let mut result = self
+            .db
+            .query(
+                "
+                RETURN $qrcode.target_type = 'file';
+
+                SELECT * FROM hosted_file WHERE dynamic_url = $qrcode;",
+            )
+            .bind(("qrcode", qrcode))
+            .await?;
+
+        if !result.take::<Option<bool>>(0)?.unwrap_or(false) {
+            return Err(ApiError::BadRequest);
+        }
+
+        Ok(result.take::<Option<HostedFileResult>>(1)?)
+    }
+
+    pub async fn store_hosted_file(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        file: HostedFile,
+    ) -> Response<HostedFileResult> {
+        /*
+            Records the file stored for a user's file code, replacing the previous one. The server
+            URL doesn't change, so printed codes serve the new file.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the file code.
+                file (HostedFile): The file as stored on disk.
+
+            Returns:
+                Response<HostedFileResult>: The hosted file, or NotFound if the user didn't create the code.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Editor)
+            .await?;
+
+        let mut result = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                UPSERT type::thing('hosted_file', record::id($qrcode)) SET dynamic_url = $qrcode, file_name = $file.file_name, content_type = $file.content_type, size = $file.size, path = $file.path, updated_at = time::now();
+
+                UPDATE $qrcode SET updated_at = time::now();
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("qrcode", qrcode))
+            .bind(("file", file))
+            .await?
+            .check()?;
+
+        match result.take::<Option<HostedFileResult>>(0)? {
+            Some(stored) => Ok(stored),
+            None => Err(ApiError::InternalServerError(
+                "Failed to store file.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn lookup_hosted_file(
+        &self,
+        server_url: &models::QrSlug,
+    ) -> Response<HostedFileResult> {
+        /*
+            Looks up the file a scan of a file code serves.
+
+            Params:
+                server_url (models::QrSlug): The server URL of the file code.
+
+            Returns:
+                Response<HostedFileResult>: The hosted file, or NotFound if nothing was uploaded yet.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT * FROM hosted_file WHERE dynamic_url = (SELECT VALUE id FROM dynamic_url WHERE server_url = $server_url)[0];")
+            .bind(("server_url", server_url.as_str().to_string()))
+            .await?;
+
+        match result.take::<Option<HostedFileResult>>(0)? {
+            Some(file) => Ok(file),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn storage_usage(&self, owner: &models::Owner) -> Response<i64> {
+        /*
+            Adds up the size of a user's or an organization's hosted files. Files of trashed codes
+            count until they are purged, since they are still on disk.
+
+            Params:
+                owner (models::Owner): The user or organization.
+
+            Returns:
+                Response<i64>: The total size in bytes.
+
+        */
+
+        let mut result = self
+            .db
+            .query("RETURN math::sum((SELECT VALUE size FROM hosted_file WHERE dynamic_url INSIDE $owner->created->dynamic_url));")
+            .bind(("owner", owner.record()))
+            .await?;
+
+        Ok(result.take::<Option<i64>>(0)?.unwrap_or(0))
+    }
+
+    pub async fn hosted_files_size(&self, qrcode_ids: &[models::QrCodeId]) -> Response<i64> {
+        /*
+            Adds up the size of the hosted files of some dynamic URLs, for checking storage limits
+            before codes are copied or handed over.
+
+            Params:
+                qrcode_ids (&[models::QrCodeId]): The IDs of the dynamic URLs.
+
+            Returns:
+                Response<i64>: The total size in bytes.
+
+        */
+
+        let records: Vec<RecordId> = qrcode_ids.iter().map(|id| id.record()).collect();
+
+        let mut result = self
+            .db
+            .query("RETURN math::sum((SELECT VALUE size FROM hosted_file WHERE dynamic_url INSIDE $qrcodes));")
+            .bind(("qrcodes", records))
+            .await?;
+
+        Ok(result.take::<Option<i64>>(0)?.unwrap_or(0))
+    }
+
+    async fn copy_hosted_file(
+        &self,
+        source: &models::QrCodeId,
+        target: &models::QrCodeId,
+    ) -> Response<()> {
+        /*
+            Copies the hosted file of a code, if it has one, to another code's place on disk.
+
+            Params:
+                source (models::QrCodeId): The ID of the code to copy from.
+                target (models::QrCodeId): The ID of the code to copy to.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT * FROM hosted_file WHERE dynamic_url = $source;")
+            .bind(("source", source.record()))
+            .await?;
+
+        let file = match result.take::<Option<HostedFileResult>>(0)? {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+
+        let path = std::path::Path::new(&file.path).with_file_name(target.to_string());
+
+        tokio::fs::copy(&file.path, &path)
+            .await
+            .map_err(|error| ApiError::InternalServerError(error.to_string()))?;
+
+        self.db
+            .query("CREATE type::thing('hosted_file', record::id($target)) SET dynamic_url = $target, file_name = $file.file_name, content_type = $file.content_type, size = $file.size, path = $path, updated_at = time::now();")
+            .bind(("target", target.record()))
+            .bind(("file", file))
+            .bind(("path", path.to_string_lossy().into_owned()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    pub async fn select_app_link(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+    ) -> Response<Option<AppLinkResult>> {
+        /*
+            Selects the deep links of a user's app code.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the app code.
+
+            Returns:
+                Response<Option<AppLinkResult>>: The links, or none before they are first set. NotFound
+                if the user didn't create the code, BadRequest if it isn't an app code.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Viewer)
+            .await?;
+
+        let mut result = self
+            .db
+            .query(
+                "
+                RETURN $qrcode.target_type = 'app';
+
+                SELECT * FROM app_link WHERE dynamic_url = $qrcode;",
+            )
+            .bind(("qrcode", qrcode))
+            .await?;
+
+        if !result.take::<Option<bool>>(0)?.unwrap_or(false) {
+            return Err(ApiError::BadRequest);
+        }
+
+        Ok(result.take::<Option<AppLinkResult>>(1)?)
+    }
+
+    pub async fn update_app_link(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        app_link: &AppLink,
+    ) -> Response<AppLinkResult> {
+        /*
+            Sets the deep links, store links and web fallback of a user's app code.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the app code.
+                app_link (AppLink): The normalized links.
+
+            Returns:
+                Response<AppLinkResult>: The updated links. NotFound if the user didn't create the code,
+                BadRequest if it isn't an app code.
+
+        */
+
+        self.find_dynamic_url(user_id, qrcode_id, Role::Editor)
+            .await?;
+        self.select_app_link(user_id, qrcode_id).await?;
+
+        let mut result = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                UPSERT type::thing('app_link', record::id($qrcode)) SET dynamic_url = $qrcode, ios_url = $link.ios_url ?? NONE, android_url = $link.android_url ?? NONE, ios_store_url = $link.ios_store_url ?? NONE, android_store_url = $link.android_store_url ?? NONE, fallback_url = $link.fallback_url, updated_at = time::now();
+
+                UPDATE $qrcode SET updated_at = time::now();
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("qrcode", qrcode_id.record()))
+            .bind(("link", app_link.clone()))
+            .await?
+            .check()?;
+
+        match result.take::<Option<AppLinkResult>>(0)? {
+            Some(updated) => Ok(updated),
+            None => Err(ApiError::InternalServerError(
+                "Failed to update app links.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn lookup_app_link(&self, server_url: &models::QrSlug) -> Response<AppLinkResult> {
+        /*
+            Looks up the deep links a scan of an app code picks from.
+
+            Params:
+                server_url (models::QrSlug): The server URL of the app code.
+
+            Returns:
+                Response<AppLinkResult>: The links, or NotFound before they are first set.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT * FROM app_link WHERE dynamic_url = (SELECT VALUE id FROM dynamic_url WHERE server_url = $server_url)[0];")
+            .bind(("server_url", server_url.as_str().to_string()))
+            .await?;
+
+        match result.take::<Option<AppLinkResult>>(0)? {
+            Some(app_link) => Ok(app_link),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn select_contact_card(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+    ) -> Response<Option<ContactCardResult>> {
+        /*
+            Selects the contact card of a user's contact code.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the contact code.
+
+            Returns:
+                Response<Option<ContactCardResult>>: The card, or none before it is first set. NotFound
+                if the user didn't create the code, BadRequest if it isn't a contact code.
+
+        */
+
+        let qrcode = self
+            .find_dynamic_url(user_id, qrcode_id, Role::Viewer)
+            .await?;
+
+        let mut result = self
+            .db
+            .query(
+                "
+                RETURN $qrcode.target_type = 'contact';
+
+                SELECT * FROM contact_card WHERE dynamic_url = $qrcode;",
+            )
+            .bind(("qrcode", qrcode))
+            .await?;
+
+        if !result.take::<Option<bool>>(0)?.unwrap_or(false) {
+            return Err(ApiError::BadRequest);
+        }
+
+        Ok(result.take::<Option<ContactCardResult>>(1)?)
+    }
+
+    pub async fn update_contact_card(
+        &self,
+        user_id: &str,
+        qrcode_id: &models::QrCodeId,
+        card: &ContactCard,
+    ) -> Response<ContactCardResult> {
+        /*
+            Replaces the contact card of a user's contact code, keeping its server URL.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                qrcode_id (models::QrCodeId): The ID of the contact code.
+                card (ContactCard): The normalized contact details.
+
+            Returns:
+                Response<ContactCardResult>: The updated card. NotFound if the user didn't create the
+                code, BadRequest if it isn't a contact code.
+
+        */
+
+        self.find_dynamic_url(user_id, qrcode_id, Role::Editor)
+            .await?;
+        self.select_contact_card(user_id, qrcode_id).await?;
+
+        let mut result = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                UPSERT type::thing('contact_card', record::id($qrcode)) SET dynamic_url = $qrcode, first_name = $card.first_name, last_name = $card.last_name ?? NONE, organization = $card.organization ?? NONE, job_title = $card.job_title ?? NONE, email = $card.email ?? NONE, phone = $card.phone ?? NONE, mobile = $card.mobile ?? NONE, website = $card.website ?? NONE, address = $card.address ?? NONE, note = $card.note ?? NONE, photo = $card.photo ?? NONE, download = $card.download, updated_at = time::now();
+
+                UPDATE $qrcode SET updated_at = time::now();
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("qrcode", qrcode_id.record()))
+            .bind(("card", card.clone()))
+            .await?
+            .check()?;
+
+        match result.take::<Option<ContactCardResult>>(0)? {
+            Some(updated) => Ok(updated),
+            None => Err(ApiError::InternalServerError(
+                "Failed to update contact card.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn lookup_contact_card(
+        &self,
+        server_url: &models::QrSlug,
+    ) -> Response<ContactCardResult> {
+        /*
+            Looks up the contact card a scan of a contact code shows.
+
+            Params:
+                server_url (models::QrSlug): The server URL of the contact code.
+
+            Returns:
+                Response<ContactCardResult>: The card, or NotFound before it is first set.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT * FROM contact_card WHERE dynamic_url = (SELECT VALUE id FROM dynamic_url WHERE server_url = $server_url)[0];")
+            .bind(("server_url", server_url.as_str().to_string()))
+            .await?;
+
+        match result.take::<Option<ContactCardResult>>(0)? {
+            Some(card) => Ok(card),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn insert_organization(
+        &self,
+        user_id: &str,
+        organization: Organization,
+    ) -> Response<OrganizationResult> {
+        /*
+            Creates an organization with the user as its first owner.
+
+            Params:
+                user_id (string): The Auth0 ID of the user creating it.
+                organization (Organization): The normalized name.
+
+            Returns:
+                Response<OrganizationResult>: The created organization.
+
+        */
+
+        let mut result = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                LET $user = type::thing('user', $user_id);
+                LET $org = type::thing('organization', rand::ulid());
+
+                CREATE $org SET name = $name, created_at = time::now();
+
+                RELATE $user->member_of->$org SET role = 'owner', created_at = time::now();
+
+                SELECT * FROM ONLY $org;
+
+                COMMIT TRANSACTION;",
+            )
             .bind(("user_id", user_id.to_string()))
-            .bind(("session_id", session.session_id))
-            .bind(("tier", session.tier))
+            .bind(("name", organization.name))
+            .await?
+            .check()?;
+
+        match result.take::<Option<OrganizationResult>>(4)? {
+            Some(created) => Ok(created),
+            None => Err(ApiError::InternalServerError(
+                "Failed to create organization.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn list_organizations(&self, user_id: &str) -> Response<Vec<MembershipResult>> {
+        /*
+            Lists the organizations a user is a member of.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+
+            Returns:
+                Response<Vec<MembershipResult>>: The organizations by name, with the user's role in each.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT out AS id, out.name AS name, role FROM type::thing('user', $user_id)->member_of ORDER BY name;")
+            .bind(("user_id", user_id.to_string()))
+            .await?;
+
+        Ok(result.take::<Vec<MembershipResult>>(0)?)
+    }
+
+    pub async fn member_role(&self, user_id: &str, org_id: &OrgId) -> Response<Option<Role>> {
+        /*
+            Looks up a user's role in an organization.
+
+            Params:
+                user_id (string): The user's Auth0 ID.
+                org_id (OrgId): The ID of the organization.
+
+            Returns:
+                Response<Option<Role>>: The role, or None if the user isn't a member.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT VALUE role FROM member_of WHERE in = type::thing('user', $user_id) AND out = $org;")
+            .bind(("user_id", user_id.to_string()))
+            .bind(("org", org_id.record()))
+            .await?;
+
+        Ok(result.take::<Option<Role>>(0)?)
+    }
+
+    pub async fn select_organization(&self, org_id: &OrgId) -> Response<OrganizationResult> {
+        /*
+            Selects an organization. Callers check the user's role in it first.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+
+            Returns:
+                Response<OrganizationResult>: The organization, or NotFound.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT * FROM ONLY $org;")
+            .bind(("org", org_id.record()))
+            .await?;
+
+        result
+            .take::<Option<OrganizationResult>>(0)?
+            .ok_or(ApiError::NotFound)
+    }
+
+    pub async fn update_organization(
+        &self,
+        org_id: &OrgId,
+        organization: Organization,
+    ) -> Response<OrganizationResult> {
+        /*
+            Renames an organization.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+                organization (Organization): The normalized new name.
+
+            Returns:
+                Response<OrganizationResult>: The renamed organization, or NotFound.
+
+        */
+
+        let mut result = self
+            .db
+            .query("UPDATE ONLY $org SET name = $name RETURN AFTER;")
+            .bind(("org", org_id.record()))
+            .bind(("name", organization.name))
+            .await?;
+
+        result
+            .take::<Option<OrganizationResult>>(0)?
+            .ok_or(ApiError::NotFound)
+    }
+
+    pub async fn list_members(&self, org_id: &OrgId) -> Response<Vec<MemberResult>> {
+        /*
+            Lists the members of an organization.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+
+            Returns:
+                Response<Vec<MemberResult>>: The members in the order they joined.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!(
+                "SELECT {MEMBER_FIELDS} FROM member_of WHERE out = $org ORDER BY joined_at;"
+            ))
+            .bind(("org", org_id.record()))
+            .await?;
+
+        Ok(result.take::<Vec<MemberResult>>(0)?)
+    }
+
+    pub async fn add_member(&self, org_id: &OrgId, member: NewMember) -> Response<MemberResult> {
+        /*
+            Adds an existing account to an organization.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+                member (NewMember): The normalized email of the account and the role to give it.
+
+            Returns:
+                Response<MemberResult>: The new member. NotFound if no account has the email,
+                BadRequest if it is already a member.
+
+        */
+
+        let mut result = self
+            .db
+            .query("SELECT VALUE id FROM user WHERE string::lowercase(email) = $email LIMIT 1;")
+            .bind(("email", member.email))
             .await?;
 
-        match result.take::<Option<models::PaymentSessionResult>>(2)? {
-            Some(created) => Ok(created),
-            None => Err(ApiError::InternalServerError(
-                "Failed to create session.".to_string(),
-            )),
+        let user = result
+            .take::<Option<RecordId>>(0)?
+            .ok_or(ApiError::NotFound)?;
+
+        let mut result = self
+            .db
+            .query(format!(
+                "
+                BEGIN TRANSACTION;
+
+                LET $joined = (SELECT VALUE id FROM member_of WHERE in = $user AND out = $org)[0] = NONE;
+
+                IF $joined {{
+                    RELATE $user->member_of->$org SET role = $role, created_at = time::now();
+                }};
+
+                $joined;
+
+                SELECT {MEMBER_FIELDS} FROM member_of WHERE in = $user AND out = $org;
+
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("user", user))
+            .bind(("org", org_id.record()))
+            .bind(("role", member.role))
+            .await?
+            .check()?;
+
+        if !result.take::<Option<bool>>(2)?.unwrap_or(false) {
+            return Err(ApiError::BadRequest);
+        }
+
+        match result.take::<Option<MemberResult>>(3)? {
+            Some(added) => Ok(added),
+            None => Err(ApiError::InternalServerError(
+                "Failed to add member.".to_string(),
+            )),
+        }
+    }
+
+    pub async fn set_member_role(
+        &self,
+        org_id: &OrgId,
+        member_id: &str,
+        role: Role,
+    ) -> Response<MemberResult> {
+        /*
+            Changes a member's role, as long as the organization keeps at least one owner.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+                member_id (string): The member's user ID.
+                role (Role): The new role.
+
+            Returns:
+                Response<MemberResult>: The member with the new role. NotFound if the user isn't a
+                member, BadRequest if they are the last owner and `role` isn't owner.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!(
+                "
+                BEGIN TRANSACTION;
+
+                LET $owners = (SELECT count() AS total FROM member_of WHERE out = $org AND role = 'owner' GROUP ALL)[0].total ?? 0;
+
+                LET $updated = (UPDATE member_of SET role = $role WHERE in = $user AND out = $org AND (role != 'owner' OR $role = 'owner' OR $owners > 1) RETURN AFTER);
+
+                array::len($updated) > 0;
+
+                SELECT {MEMBER_FIELDS} FROM member_of WHERE in = $user AND out = $org;
+
+                COMMIT TRANSACTION;"
+            ))
+            .bind(("org", org_id.record()))
+            .bind(("user", RecordId::from(("user", member_id))))
+            .bind(("role", role))
+            .await?
+            .check()?;
+
+        let updated = result.take::<Option<bool>>(2)?.unwrap_or(false);
+
+        match result.take::<Option<MemberResult>>(3)? {
+            Some(member) if updated => Ok(member),
+            Some(_) => Err(ApiError::BadRequest),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn remove_member(&self, org_id: &OrgId, member_id: &str) -> Response<bool> {
+        /*
+            Removes a member from an organization, as long as it keeps at least one owner. The
+            codes they made stay with the organization, the API keys they made for it are deleted.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+                member_id (string): The member's user ID.
+
+            Returns:
+                Response<bool>: true once removed. NotFound if the user isn't a member, BadRequest
+                if they are the last owner.
+
+        */
+
+        let mut result = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                LET $owners = (SELECT count() AS total FROM member_of WHERE out = $org AND role = 'owner' GROUP ALL)[0].total ?? 0;
+
+                LET $removed = (DELETE member_of WHERE in = $user AND out = $org AND (role != 'owner' OR $owners > 1) RETURN BEFORE);
+
+                array::len($removed) > 0;
+
+                (SELECT VALUE id FROM member_of WHERE in = $user AND out = $org)[0] != NONE;
+
+                IF array::len($removed) > 0 {
+                    DELETE api_key WHERE owner = $org AND user = $user;
+                };
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("org", org_id.record()))
+            .bind(("user", RecordId::from(("user", member_id))))
+            .await?
+            .check()?;
+
+        let removed = result.take::<Option<bool>>(2)?.unwrap_or(false);
+        let remaining = result.take::<Option<bool>>(3)?.unwrap_or(false);
+
+        match (removed, remaining) {
+            (true, _) => Ok(true),
+            (false, true) => Err(ApiError::BadRequest),
+            (false, false) => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn delete_organization(&self, org_id: &OrgId) -> Response<bool> {
+        /*
+            Deletes an organization with its codes, their content, history and scans, its subscription,
+            its memberships, invitations, API keys and webhooks.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+
+            Returns:
+                Response<bool>: true once deleted.
+
+        */
+
+        let mut result = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                DELETE $org->subscribed->subscription;
+                DELETE landing_link WHERE page.dynamic_url INSIDE $org->created->dynamic_url;
+                DELETE landing_page WHERE dynamic_url INSIDE $org->created->dynamic_url;
+                DELETE app_link WHERE dynamic_url INSIDE $org->created->dynamic_url;
+                DELETE contact_card WHERE dynamic_url INSIDE $org->created->dynamic_url;
+                DELETE dynamic_url_revision WHERE dynamic_url INSIDE $org->created->dynamic_url;
+                DELETE scan WHERE dynamic_url INSIDE $org->created->dynamic_url;
+                DELETE hosted_file WHERE dynamic_url INSIDE $org->created->dynamic_url RETURN BEFORE;
+                DELETE $org->created->dynamic_url RETURN BEFORE;
+                DELETE member_of WHERE out = $org;
+                DELETE invitation WHERE organization = $org;
+                DELETE api_key WHERE owner = $org;
+                DELETE webhook_delivery WHERE webhook INSIDE $org->registered->webhook;
+                DELETE $org->registered->webhook;
+                DELETE $org;
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("org", org_id.record()))
+            .await?
+            .check()?;
+
+        remove_files(&result.take::<Vec<HostedFileResult>>(7)?).await;
+
+        for deleted in result.take::<Vec<models::ServerUrl>>(8)? {
+            self.cache.invalidate(&deleted.server_url);
         }
+
+        Ok(true)
     }
 
-    pub async fn insert_subscription(
+    pub async fn delete_organization_data(&self, org_id: &OrgId) -> Response<bool> {
+        /*
+            Deletes an organization's subscription and codes, with their content, history and scans,
+            once its subscription ends. The organization, its memberships, invitations, API keys and
+            webhooks are kept so it can subscribe again.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+
+            Returns:
+                Response<bool>: true once deleted.
+
+        */
+
+        let mut result = self
+            .db
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                LET $codes = $org->created->dynamic_url;
+
+                DELETE $org->subscribed->subscription;
+                DELETE landing_link WHERE page.dynamic_url INSIDE $codes;
+                DELETE landing_page WHERE dynamic_url INSIDE $codes;
+                DELETE app_link WHERE dynamic_url INSIDE $codes;
+                DELETE contact_card WHERE dynamic_url INSIDE $codes;
+                DELETE dynamic_url_revision WHERE dynamic_url INSIDE $codes;
+                DELETE scan WHERE dynamic_url INSIDE $codes;
+                DELETE hosted_file WHERE dynamic_url INSIDE $codes RETURN BEFORE;
+                DELETE $codes RETURN BEFORE;
+
+                COMMIT TRANSACTION;",
+            )
+            .bind(("org", org_id.record()))
+            .await?
+            .check()?;
+
+        remove_files(&result.take::<Vec<HostedFileResult>>(8)?).await;
+
+        for deleted in result.take::<Vec<models::ServerUrl>>(9)? {
+            self.cache.invalidate(&deleted.server_url);
+        }
+
+        Ok(true)
+    }
+
+    pub async fn insert_invitation(
         &self,
         user_id: &str,
-        subscription: models::UserSubscription,
-    ) -> Response<models::UserSubscriptionResult> {
+        org_id: &OrgId,
+        email: &str,
+        role: Role,
+        token_hash: &str,
+    ) -> Response<InvitationResult> {
         /*
-            Inserts a new subscription into the database.
+            Stores a pending invitation to an organization for an email address.
 
             Params:
-                subscription_id (string): The subscription ID.
-                tier (string): The subscription's tier.
-                start_date (datetime): The subscription's start date.
-                end_date (datetime): The subscription's end date.
-                usage (int): The subscription's usage.
-                subscription_status (string): The subscription's status.
+                user_id (string): The Auth0 ID of the member sending it.
+                org_id (OrgId): The ID of the organization.
+                email (string): The normalized email of the invitee.
+                role (Role): The role the invitee gets on accepting.
+                token_hash (string): The hash of the token mailed to the invitee.
+
+            Returns:
+                Response<InvitationResult>: The pending invitation. BadRequest if the email already
+                belongs to a member or has a pending invitation, which can be resent instead.
 
         */
 
         let mut result = self
             .db
-            .query("
+            .query(
+                "
+                (SELECT VALUE id FROM member_of WHERE out = $org AND string::lowercase(in.email) = $email)[0] != NONE;
 
-            LET $user = type::thing('user', $user_id);
-            
-            RELATE $user->subscribed->CREATE type::thing('subscription', $subscription_id) 
-            SET subscription_id = $subscription_id, tier = $tier, start_date = time::now(), end_date = time::now(), usage = 0, subscription_status = $subscription_status;
-            
-            SELECT * FROM $user->subscribed->subscription LIMIT 1;")
+                (SELECT VALUE id FROM invitation WHERE organization = $org AND email = $email AND status = 'pending')[0] != NONE;",
+            )
+            .bind(("org", org_id.record()))
+            .bind(("email", email.to_string()))
+            .await?;
+
+        let member = result.take::<Option<bool>>(0)?.unwrap_or(false);
+        let pending = result.take::<Option<bool>>(1)?.unwrap_or(false);
+
+        if member || pending {
+            return Err(ApiError::BadRequest);
+        }
+
+        let mut result = self
+            .db
+            .query(format!(
+                "CREATE invitation SET organization = $org, email = $email, role = $role, invited_by = type::thing('user', $user_id), token_hash = $token_hash, status = 'pending', created_at = time::now(), expires_at = time::now() + duration::from::days($expiry) RETURN {INVITATION_FIELDS};"
+            ))
+            .bind(("org", org_id.record()))
+            .bind(("email", email.to_string()))
+            .bind(("role", role))
             .bind(("user_id", user_id.to_string()))
-            .bind(("subscription_id", subscription.sub_id))
-            .bind(("tier", subscription.tier))
-            .bind(("subscription_status", subscription.status))
+            .bind(("token_hash", token_hash.to_string()))
+            .bind(("expiry", INVITATION_EXPIRY_DAYS))
             .await?;
 
-        match result.take::<Option<models::UserSubscriptionResult>>(2)? {
-            Some(created) => Ok(created),
+        match result.take::<Option<InvitationResult>>(0)? {
+            Some(invitation) => Ok(invitation),
             None => Err(ApiError::InternalServerError(
-                "Failed to create subscription.".to_string(),
+                "Failed to create invitation.".to_string(),
             )),
         }
     }
 
-    pub async fn get_subscription(
+    pub async fn list_invitations(&self, org_id: &OrgId) -> Response<Vec<InvitationResult>> {
+        /*
+            Lists the invitations of an organization, newest first.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+
+            Returns:
+                Response<Vec<InvitationResult>>: Every invitation, answered or not.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!(
+                "SELECT {INVITATION_FIELDS} FROM invitation WHERE organization = $org ORDER BY created_at DESC;"
+            ))
+            .bind(("org", org_id.record()))
+            .await?;
+
+        Ok(result.take::<Vec<InvitationResult>>(0)?)
+    }
+
+    pub async fn select_invitation(
+        &self,
+        org_id: &OrgId,
+        invitation_id: &InvitationId,
+    ) -> Response<InvitationResult> {
+        /*
+            Selects a pending invitation of an organization, expired or not.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+                invitation_id (InvitationId): The ID of the invitation.
+
+            Returns:
+                Response<InvitationResult>: The invitation, or NotFound if it isn't a pending one of the organization.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!(
+                "SELECT {INVITATION_FIELDS} FROM ONLY $invitation WHERE organization = $org AND status = 'pending';"
+            ))
+            .bind(("org", org_id.record()))
+            .bind(("invitation", invitation_id.record()))
+            .await?;
+
+        match result.take::<Option<InvitationResult>>(0)? {
+            Some(invitation) => Ok(invitation),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn renew_invitation(
         &self,
         user_id: &str,
-    ) -> Response<models::UserSubscriptionResult> {
+        org_id: &OrgId,
+        invitation_id: &InvitationId,
+        token_hash: &str,
+    ) -> Response<InvitationResult> {
         /*
-            Gets a user's subscription from the database.
+            Replaces the token of a pending invitation and restarts its expiry, for sending it again.
+            The previous token stops working.
 
             Params:
-                user_id (string): The user's Auth0 ID.
+                user_id (string): The Auth0 ID of the member resending it.
+                org_id (OrgId): The ID of the organization.
+                invitation_id (InvitationId): The ID of the invitation.
+                token_hash (string): The hash of the new token.
 
             Returns:
-                Response<Option<models::UserSubscriptionResult>>: The user's subscription object, or None if no subscription was found.
+                Response<InvitationResult>: The renewed invitation, or NotFound if it isn't a pending one of the organization.
 
         */
 
         let mut result = self
             .db
-            .query("SELECT * FROM type::thing('user', $user_id)->subscribed->subscription;")
+            .query(format!(
+                "UPDATE $invitation SET token_hash = $token_hash, invited_by = type::thing('user', $user_id), expires_at = time::now() + duration::from::days($expiry) WHERE organization = $org AND status = 'pending' RETURN {INVITATION_FIELDS};"
+            ))
+            .bind(("invitation", invitation_id.record()))
+            .bind(("org", org_id.record()))
             .bind(("user_id", user_id.to_string()))
+            .bind(("token_hash", token_hash.to_string()))
+            .bind(("expiry", INVITATION_EXPIRY_DAYS))
             .await?;
 
-        match result.take::<Option<models::UserSubscriptionResult>>(0)? {
-            Some(subscription) => Ok(subscription),
-            None => Err(ApiError::InternalServerError(
-                "No subscription found.".to_string(),
-            )),
+        match result.take::<Option<InvitationResult>>(0)? {
+            Some(invitation) => Ok(invitation),
+            None => Err(ApiError::NotFound),
         }
     }
 
-    pub async fn override_subscription(
+    pub async fn revoke_invitation(
+        &self,
+        org_id: &OrgId,
+        invitation_id: &InvitationId,
+    ) -> Response<InvitationResult> {
+        /*
+            Withdraws a pending invitation so its token can't be used.
+
+            Params:
+                org_id (OrgId): The ID of the organization.
+                invitation_id (InvitationId): The ID of the invitation.
+
+            Returns:
+                Response<InvitationResult>: The revoked invitation, or NotFound if it isn't a pending one of the organization.
+
+        */
+
+        let mut result = self
+            .db
+            .query(format!(
+                "UPDATE $invitation SET status = $status, resolved_at = time::now() WHERE organization = $org AND status = 'pending' RETURN {INVITATION_FIELDS};"
+            ))
+            .bind(("invitation", invitation_id.record()))
+            .bind(("org", org_id.record()))
+            .bind(("status", InvitationStatus::Revoked))
+            .await?;
+
+        match result.take::<Option<InvitationResult>>(0)? {
+            Some(invitation) => Ok(invitation),
+            None => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn select_incoming_invitation(
         &self,
         user_id: &str,
-        subscription_id: &str,
-        new_tier: &str,
-    ) -> Response<models::UserSubscriptionResult> {
+        token_hash: &str,
+    ) -> Response<InvitationResult> {
         /*
-            Overrides a user's subscription in the database.
+            Selects a pending, unexpired invitation by its token, if it was sent to the user's email.
 
             Params:
-                user_id (string): The user's Auth0 ID.
-                subscription (models::UserSubscription): The new subscription object.
+                user_id (string): The invitee's Auth0 ID.
+                token_hash (string): The hash of the mailed token.
 
             Returns:
-                Response<models::UserSubscriptionResult>: The updated subscription object.
+                Response<InvitationResult>: The invitation, or NotFound if no pending invitation for this user has the token.
 
         */
 
         let mut result = self
             .db
-            .query("LET $user = type::thing('user', $user_id);
-            
-            UPDATE subscription SET tier = $tier, start_date = time::now(), end_date = time::now() WHERE subscription_id = $subscription_id;
-            
-            SELECT * FROM subscription WHERE subscription_id = $subscription_id LIMIT 1;")
+            .query(format!(
+                "SELECT {INVITATION_FIELDS} FROM invitation WHERE token_hash = $token_hash AND status = 'pending' AND expires_at > time::now() AND email = string::lowercase(type::thing('user', $user_id).email) LIMIT 1;"
+            ))
             .bind(("user_id", user_id.to_string()))
-            .bind(("tier", new_tier.to_string()))
-            .bind(("subscription_id", subscription_id.to_string() ))
+            .bind(("token_hash", token_hash.to_string()))
             .await?;
 
-        match result.take::<Option<models::UserSubscriptionResult>>(2)? {
-            Some(updated) => Ok(updated),
-            None => Err(ApiError::InternalServerError(
-                "Failed to update subscription.".to_string(),
-            )),
+        match result.take::<Option<InvitationResult>>(0)? {
+            Some(invitation) => Ok(invitation),
+            None => Err(ApiError::NotFound),
         }
     }
 
-    pub async fn set_subscription_status(
+    pub async fn accept_invitation(
         &self,
         user_id: &str,
-        status: &str,
-    ) -> Response<models::UserSubscriptionResult> {
+        token_hash: &str,
+    ) -> Response<MembershipResult> {
         /*
-            Sets a user's subscription status to inactive.
+            Accepts an invitation and makes the user a member with its role, in one transaction.
+            A user who already joined some other way keeps the role they have.
 
             Params:
-                user_id (string): The user's Auth0 ID.
+                user_id (string): The invitee's Auth0 ID.
+                token_hash (string): The hash of the mailed token.
 
             Returns:
-                Response<models::UserSubscriptionResult>: The updated subscription object.
+                Response<MembershipResult>: The organization the user joined with their role in it,
+                or NotFound if no pending invitation for this user has the token.
 
         */
 
+        let invitation = self.select_incoming_invitation(user_id, token_hash).await?;
+
         let mut result = self
             .db
-            .query("UPDATE type::thing('user', $user_id)->subscribed->subscription SET subscription_status = $status;")
+            .query(
+                "
+                BEGIN TRANSACTION;
+
+                LET $user = type::thing('user', $user_id);
+                LET $accepted = (UPDATE $invitation SET status = 'accepted', recipient = $user, resolved_at = time::now() WHERE status = 'pending' RETURN AFTER);
+
+                IF array::len($accepted) > 0 AND (SELECT VALUE id FROM member_of WHERE in = $user AND out = $org)[0] = NONE {
+                    RELATE $user->member_of->$org SET role = $role, created_at = time::now();
+                };
+
+                array::len($accepted) > 0;
+
+                SELECT out AS id, out.name AS name, role FROM member_of WHERE in = $user AND out = $org;
+
+                COMMIT TRANSACTION;",
+            )
             .bind(("user_id", user_id.to_string()))
-            .bind(("status", status.to_string()))
-            .await?;
+            .bind(("invitation", invitation.id.record()))
+            .bind(("org", invitation.organization.record()))
+            .bind(("role", invitation.role))
+            .await?
+            .check()?;
 
-        match result.take::<Option<models::UserSubscriptionResult>>(0)? {
-            Some(updated) => Ok(updated),
+        if !result.take::<Option<bool>>(3)?.unwrap_or(false) {
+            return Err(ApiError::NotFound);
+        }
+
+        match result.take::<Option<MembershipResult>>(4)? {
+            Some(membership) => Ok(membership),
             None => Err(ApiError::InternalServerError(
-                "Failed to update subscription.".to_string(),
+                "Failed to join organization.".to_string(),
             )),
         }
     }
 
-    pub async fn validate_subscription_status(&self, user_id: &str) -> Response<bool> {
+    pub async fn decline_invitation(
+        &self,
+        user_id: &str,
+        token_hash: &str,
+    ) -> Response<InvitationResult> {
         /*
-            Checks the status of a user's subscription.
+            Declines an invitation sent to the user's email.
 
             Params:
-                user_id (string): The user's Auth0 ID.
+                user_id (string): The invitee's Auth0 ID.
+                token_hash (string): The hash of the mailed token.
 
             Returns:
-                Response<String>: The user's subscription status.
+                Response<InvitationResult>: The declined invitation, or NotFound if no pending invitation for this user has the token.
 
         */
 
+        let invitation = self.select_incoming_invitation(user_id, token_hash).await?;
+
         let mut result = self
             .db
-            .query("SELECT subscription_status FROM type::thing('user', $user_id)->subscribed->subscription;")
+            .query(format!(
+                "UPDATE $invitation SET status = $status, recipient = type::thing('user', $user_id), resolved_at = time::now() WHERE status = 'pending' RETURN {INVITATION_FIELDS};"
+            ))
+            .bind(("invitation", invitation.id.record()))
             .bind(("user_id", user_id.to_string()))
+            .bind(("status", InvitationStatus::Declined))
             .await?;
 
-        match result.take::<Option<models::SubscriptionStatus>>(0)? {
-            Some(status) => {
-                if status.subscription_status == "complete" {
-                    Ok(true)
-                } else {
-                    Ok(false)
-                }
-            }
-            None => Ok(false),
+        match result.take::<Option<InvitationResult>>(0)? {
+            Some(declined) => Ok(declined),
+            None => Err(ApiError::NotFound),
         }
     }
 
-    pub async fn increment_usage(&self, user_id: &str) -> Response<models::UserSubscriptionResult> {
+    pub async fn insert_api_key(
+        &self,
+        user_id: &str,
+        owner: &models::Owner,
+        key: NewApiKey,
+        prefix: &str,
+        key_hash: &str,
+    ) -> Response<ApiKeyResult> {
         /*
-            Updates the usage of a user's subscription.
+            Stores an API key for a user's or an organization's codes.
 
             Params:
-                user_id (string): The user's Auth0 ID.
-                usage (int): The new usage value.
+                user_id (string): The Auth0 ID of the user making the key, whom it acts as.
+                owner (models::Owner): The user or organization whose codes the key reaches.
+                key (NewApiKey): The normalized name, scopes and expiry.
+                prefix (string): The start of the key, kept to tell keys apart.
+                key_hash (string): The hash of the key.
 
             Returns:
-                Response<models::UserSubscriptionResult>: The updated subscription object.
+                Response<ApiKeyResult>: The stored key, without the key itself.
 
         */
 
         let mut result = self
             .db
-            .query("UPDATE type::thing('user', $user_id)->subscribed->subscription SET usage = usage + 1;")
+            .query(format!(
+                "CREATE api_key SET owner = $owner, user = type::thing('user', $user_id), name = $name, prefix = $prefix, key_hash = $key_hash, scopes = $scopes, created_at = time::now(), expires_at = IF $expiry THEN time::now() + duration::from::days($expiry) ELSE NONE END RETURN {API_KEY_FIELDS};"
+            ))
+            .bind(("owner", owner.record()))
             .bind(("user_id", user_id.to_string()))
+            .bind(("name", key.name))
+            .bind(("prefix", prefix.to_string()))
+            .bind(("key_hash", key_hash.to_string()))
+            .bind(("scopes", key.scopes))
+            .bind(("expiry", key.expires_in_days))
             .await?;
 
-        match result.take::<Option<models::UserSubscriptionResult>>(0)? {
-            Some(updated) => Ok(updated),
+        match result.take::<Option<ApiKeyResult>>(0)? {
+            Some(created) => Ok(created),
             None => Err(ApiError::InternalServerError(
-                "Failed to update usage.".to_string(),
+                "Failed to create API key.".to_string(),
             )),
         }
     }
 
-    pub async fn decrement_usage(&self, user_id: &str) -> Response<models::UserSubscriptionResult> {
+    pub async fn list_api_keys(&self, owner: &models::Owner) -> Response<Vec<ApiKeyResult>> {
         /*
-            Updates the usage of a user's subscription.
+            Lists the API keys of a user or an organization, newest first.
 
             Params:
-                user_id (string): The user's Auth0 ID.
-                usage (int): The new usage value.
+                owner (models::Owner): The user or organization the keys reach.
 
             Returns:
-                Response<models::UserSubscriptionResult>: The updated subscription object.
+                Response<Vec<ApiKeyResult>>: The keys, without the keys themselves.
 
         */
 
         let mut result = self
             .db
-            .query("UPDATE type::thing('user', $user_id)->subscribed->subscription SET usage = usage - 1;")
-            .bind(("user_id", user_id.to_string()))
+            .query(format!(
+                "SELECT {API_KEY_FIELDS} FROM api_key WHERE owner = $owner ORDER BY created_at DESC;"
+            ))
+            .bind(("owner", owner.record()))
             .await?;
 
-        match result.take::<Option<models::UserSubscriptionResult>>(0)? {
-            Some(updated) => Ok(updated),
-            None => Err(ApiError::InternalServerError(
-                "Failed to update usage.".to_string(),
-            )),
+        Ok(result.take::<Vec<ApiKeyResult>>(0)?)
+    }
+
+    pub async fn delete_api_key(&self, owner: &models::Owner, key_id: &ApiKeyId) -> Response<bool> {
+        /*
+            Revokes an API key by deleting it.
+
+            Params:
+                owner (models::Owner): The user or organization the key reaches.
+                key_id (ApiKeyId): The ID of the key.
+
+            Returns:
+                Response<bool>: true once deleted, or NotFound if the owner has no such key.
+
+        */
+
+        let mut result = self
+            .db
+            .query("array::len((DELETE $key WHERE owner = $owner RETURN BEFORE)) > 0;")
+            .bind(("key", key_id.record()))
+            .bind(("owner", owner.record()))
+            .await?;
+
+        match result.take::<Option<bool>>(0)? {
+            Some(true) => Ok(true),
+            _ => Err(ApiError::NotFound),
+        }
+    }
+
+    pub async fn authenticate_api_key(&self, key_hash: &str) -> Response<Option<ApiKeyGrant>> {
+        /*
+            Looks up an unexpired API key by its hash and records that it was used.
+
+            Params:
+                key_hash (string): The hash of the key sent with the request.
+
+            Returns:
+                Response<Option<ApiKeyGrant>>: The user the key acts as, the owner it reaches and
+                its scopes, or None if no unexpired key has the hash.
+
+        */
+
+        let mut result = self
+            .db
+            .query("UPDATE api_key SET last_used_at = time::now() WHERE key_hash = $key_hash AND (expires_at = NONE OR expires_at > time::now()) RETURN record::id(user) AS user, owner, scopes;")
+            .bind(("key_hash", key_hash.to_string()))
+            .await?;
+
+        Ok(result.take::<Option<ApiKeyGrant>>(0)?)
+    }
+}
+
+async fn remove_files(files: &[HostedFileResult]) {
+    // The records are already gone, so a file that can't be removed is only logged.
+    for file in files {
+        if let Err(error) = tokio::fs::remove_file(&file.path).await {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to remove {}: {}", file.path, error);
+            }
         }
     }
 }
+
+fn usage_check(adding: &str) -> String {
+    // Throws `USAGE_LIMIT_REACHED` when `adding` more codes wouldn't fit in `$owner`'s tier, leaving
+    // paused codes out when `$counts_paused` is false. Run inside the transaction that adds the
+    // codes, so two requests can't both take the last of the room. Adding none always passes.
+    format!(
+        "
+        LET $usage = (SELECT VALUE usage FROM $owner->subscribed->subscription)[0] ?? 0;
+        LET $paused = IF $counts_paused THEN 0 ELSE count(SELECT VALUE id FROM $owner->created->dynamic_url WHERE status = 'paused' AND deleted_at = NONE) END;
+
+        IF {adding} > 0 AND $usage - $paused + {adding} > $max_usage {{
+            THROW '{USAGE_LIMIT_REACHED}';
+        }};"
+    )
+}
+
+fn check_thrown(mut result: surrealdb::Response) -> Response<surrealdb::Response> {
+    // A limit thrown by a transaction is a BadRequest and a record that went away a NotFound, any
+    // other error is passed on.
+    let errors = result.take_errors();
+    let thrown = |message: &str| {
+        errors
+            .values()
+            .any(|error| error.to_string().contains(message))
+    };
+
+    if thrown(USAGE_LIMIT_REACHED) || thrown(STORAGE_LIMIT_REACHED) {
+        return Err(ApiError::BadRequest);
+    }
+
+    if thrown(NO_LONGER_AVAILABLE) {
+        return Err(ApiError::NotFound);
+    }
+
+    if let Some(error) = errors.into_values().next() {
+        return Err(error.into());
+    }
+
+    Ok(result)
+}
@@ -1,5 +1,12 @@
+use crate::hosted::models::{StorageUsage, MIB};
+
+use base64::{engine::general_purpose, Engine};
 use core::fmt;
-use serde::{Deserialize, Serialize};
+use rocket::form::{FromForm, FromFormField};
+use rocket::http::Status;
+use rocket::request::FromParam;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 use surrealdb::{sql::Datetime, RecordId};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,6 +29,30 @@ impl SubscriptionTier {
         }
     }
 
+    // The largest file a file code can serve, in bytes
+    pub fn max_file_size(&self) -> i64 {
+        match self {
+            SubscriptionTier::Lite => 5 * MIB,
+            SubscriptionTier::Pro => 25 * MIB,
+        }
+    }
+
+    // The total size of all a user's files, in bytes
+    pub fn max_storage(&self) -> i64 {
+        match self {
+            SubscriptionTier::Lite => 50 * MIB,
+            SubscriptionTier::Pro => 500 * MIB,
+        }
+    }
+
+    // Whether paused codes still take up room in `max_usage`
+    pub fn counts_paused(&self) -> bool {
+        match self {
+            SubscriptionTier::Lite => true,
+            SubscriptionTier::Pro => false,
+        }
+    }
+
     // Convert a string to a SubscriptionTier enum
     pub fn from_str(tier_str: &str) -> Option<Self> {
         match tier_str {
@@ -51,6 +82,7 @@ pub struct UserResult {
 pub struct UserDetails {
     pub user: UserResult,
     pub subscription: Option<UserSubscriptionResult>,
+    pub storage: StorageUsage,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -86,6 +118,7 @@ pub struct PaymentSessionResult {
     pub tier: String,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewSubscription {
     pub new_tier: String,
@@ -110,25 +143,615 @@ pub struct UserSubscriptionResult {
     pub subscription_status: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum RedirectCode {
+    Moved,
+    // 302 is the default so browsers don't cache the target and miss later updates.
+    #[default]
+    Found,
+    Temporary,
+    Permanent,
+}
+
+impl RedirectCode {
+    // The HTTP status sent back from the scan route.
+    pub fn status(&self) -> Status {
+        match self {
+            RedirectCode::Moved => Status::MovedPermanently,
+            RedirectCode::Found => Status::Found,
+            RedirectCode::Temporary => Status::TemporaryRedirect,
+            RedirectCode::Permanent => Status::PermanentRedirect,
+        }
+    }
+}
+
+impl TryFrom<u16> for RedirectCode {
+    type Error = String;
+
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        match code {
+            301 => Ok(RedirectCode::Moved),
+            302 => Ok(RedirectCode::Found),
+            307 => Ok(RedirectCode::Temporary),
+            308 => Ok(RedirectCode::Permanent),
+            _ => Err(format!("Unsupported redirect code: {}", code)),
+        }
+    }
+}
+
+impl From<RedirectCode> for u16 {
+    fn from(code: RedirectCode) -> Self {
+        code.status().code
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum QrStatus {
+    #[default]
+    Active,
+    // Kept with its counts, but scans don't redirect.
+    Paused,
+}
+
+impl QrStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QrStatus::Active => "active",
+            QrStatus::Paused => "paused",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QrTarget {
+    #[default]
+    Url,
+    // Scans render a hosted landing page with a list of links instead of redirecting.
+    Page,
+    // Scans serve an uploaded PDF or image.
+    File,
+    // Scans open a mobile app, falling back to its store or a web page.
+    App,
+    // Scans show an editable contact card, or download it as a vCard.
+    Contact,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum QrColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Pink,
+    Gray,
+}
+
+impl QrColor {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QrColor::Red => "red",
+            QrColor::Orange => "orange",
+            QrColor::Yellow => "yellow",
+            QrColor::Green => "green",
+            QrColor::Blue => "blue",
+            QrColor::Purple => "purple",
+            QrColor::Pink => "pink",
+            QrColor::Gray => "gray",
+        }
+    }
+
+    pub fn hex(&self) -> &'static str {
+        match self {
+            QrColor::Red => "#e5484d",
+            QrColor::Orange => "#f76b15",
+            QrColor::Yellow => "#ffc53d",
+            QrColor::Green => "#30a46c",
+            QrColor::Blue => "#0090ff",
+            QrColor::Purple => "#8e4ec6",
+            QrColor::Pink => "#d6409f",
+            QrColor::Gray => "#8d8d8d",
+        }
+    }
+}
+
+pub const MAX_NAME_LENGTH: usize = 100;
+pub const MAX_DESCRIPTION_LENGTH: usize = 500;
+pub const MAX_TAGS: usize = 20;
+pub const MAX_TAG_LENGTH: usize = 32;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct DynamicQr {
+    // Left out for page codes, which have no target URL.
+    #[serde(default)]
     pub target_url: String,
+    #[serde(default)]
+    pub target_type: QrTarget,
+    #[serde(default)]
+    pub redirect_code: RedirectCode,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub color: Option<QrColor>,
+}
+
+impl DynamicQr {
+    pub fn normalize(&mut self) -> bool {
+        /*
+            Trims the name and description, drops blank ones and lowercases and dedups tags
+            so filtering by tag doesn't depend on how it was typed. Codes of other types than url drop the target URL.
+
+            Returns:
+                bool: false if any label is over its length limit, there are too many tags or a
+                URL code has no target URL.
+        */
+
+        match self.target_type {
+            QrTarget::Url if self.target_url.trim().is_empty() => return false,
+            QrTarget::Url => {}
+            QrTarget::Page | QrTarget::File | QrTarget::App | QrTarget::Contact => {
+                self.target_url.clear()
+            }
+        }
+
+        let trim = |label: &mut Option<String>| {
+            *label = label
+                .take()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty());
+        };
+
+        trim(&mut self.name);
+        trim(&mut self.description);
+
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.tags.drain(..) {
+            let tag = tag.trim().to_lowercase();
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        self.tags = tags;
+
+        let within = |label: &Option<String>, limit: usize| {
+            label
+                .as_ref()
+                .is_none_or(|value| value.chars().count() <= limit)
+        };
+
+        within(&self.name, MAX_NAME_LENGTH)
+            && within(&self.description, MAX_DESCRIPTION_LENGTH)
+            && self.tags.len() <= MAX_TAGS
+            && self
+                .tags
+                .iter()
+                .all(|tag| tag.chars().count() <= MAX_TAG_LENGTH)
+    }
+}
+
+fn valid_key(key: &str) -> bool {
+    // Record keys are generated ULIDs, anything else can't exist.
+    !key.is_empty() && key.len() <= 64 && key.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+pub fn valid_slug(slug: &str) -> bool {
+    // Server URLs are generated ULIDs or custom slugs of letters, digits, `-` and `_`.
+    (3..=64).contains(&slug.len())
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+macro_rules! record_key {
+    ($(#[$meta:meta])* $name:ident, $table:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn parse(key: &str) -> Option<Self> {
+                valid_key(key).then(|| $name(key.to_string()))
+            }
+
+            pub fn record(&self) -> RecordId {
+                RecordId::from(($table, self.0.as_str()))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl<'a> FromParam<'a> for $name {
+            type Error = &'a str;
+
+            fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+                $name::parse(param).ok_or(param)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            // Records come back from the database as `<table>:<key>`, the API only exposes the key.
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let id = RecordId::deserialize(deserializer)?;
+
+                Ok($name(id.key().to_string()))
+            }
+        }
+    };
+}
+
+record_key!(
+    /// The record ID of a dynamic QR code, used to address codes in the API.
+    QrCodeId,
+    "dynamic_url"
+);
+
+record_key!(
+    /// The record ID of a folder.
+    FolderId,
+    "folder"
+);
+
+record_key!(
+    /// The record ID of a webhook.
+    WebhookId,
+    "webhook"
+);
+
+record_key!(
+    /// The record ID of a webhook delivery.
+    DeliveryId,
+    "webhook_delivery"
+);
+
+record_key!(
+    /// The record ID of a target URL revision of a dynamic QR code.
+    RevisionId,
+    "dynamic_url_revision"
+);
+
+record_key!(
+    /// The record ID of an ownership transfer.
+    TransferId,
+    "transfer"
+);
+
+record_key!(
+    /// The record ID of a link on a landing page.
+    LinkId,
+    "landing_link"
+);
+
+record_key!(
+    /// The record ID of an organization.
+    OrgId,
+    "organization"
+);
+
+record_key!(
+    /// The record ID of an invitation to an organization.
+    InvitationId,
+    "invitation"
+);
+
+record_key!(
+    /// The record ID of an API key.
+    ApiKeyId,
+    "api_key"
+);
+
+// Whose codes, subscription and usage a request works on: a user's own, or an organization's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Owner {
+    User(String),
+    Organization(OrgId),
+}
+
+impl Owner {
+    pub fn record(&self) -> RecordId {
+        match self {
+            Owner::User(user_id) => RecordId::from(("user", user_id.as_str())),
+            Owner::Organization(org_id) => org_id.record(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Owner {
+    // Owners come back from the database as the `user` or `organization` record that created a code.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = RecordId::deserialize(deserializer)?;
+        let key = id.key().to_string();
+
+        Ok(match id.table() {
+            "organization" => Owner::Organization(OrgId(key)),
+            _ => Owner::User(key),
+        })
+    }
+}
+
+// The public slug of a dynamic QR code, only used by the scan path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QrSlug(String);
+
+impl QrSlug {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'a> FromParam<'a> for QrSlug {
+    type Error = &'a str;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        if valid_slug(param) {
+            Ok(QrSlug(param.to_string()))
+        } else {
+            Err(param)
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DynamicQrResult {
-    id: RecordId,
+    id: QrCodeId,
     server_url: String,
     target_url: String,
+    #[serde(default)]
+    target_type: QrTarget,
+    #[serde(default)]
+    redirect_code: RedirectCode,
     access_count: i32,
+    #[serde(default)]
+    bot_count: i32,
+    #[serde(default)]
+    status: QrStatus,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    color: Option<QrColor>,
+    #[serde(default)]
+    folder: Option<FolderId>,
+    #[serde(default)]
+    deleted_at: Option<Datetime>,
     last_accessed: Datetime,
     created_at: Datetime,
     updated_at: Datetime,
 }
 
+impl DynamicQrResult {
+    pub fn id(&self) -> &QrCodeId {
+        &self.id
+    }
+
+    pub fn server_url(&self) -> &str {
+        &self.server_url
+    }
+
+    pub fn target_type(&self) -> QrTarget {
+        self.target_type
+    }
+
+    // The cursor that continues a listing sorted by `sort` after this code.
+    pub fn cursor(&self, sort: QrSort) -> QrCursor {
+        let value = match sort {
+            QrSort::CreatedAt => Value::from(self.created_at.to_raw()),
+            QrSort::UpdatedAt => Value::from(self.updated_at.to_raw()),
+            QrSort::LastAccessed => Value::from(self.last_accessed.to_raw()),
+            QrSort::AccessCount => Value::from(self.access_count),
+        };
+
+        QrCursor {
+            value,
+            id: self.id.to_string(),
+        }
+    }
+}
+
+// One change of a code's target URL, with the scans made while it was live.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevisionResult {
+    pub id: RevisionId,
+    // None for the revision a code was created with.
+    pub previous_target: Option<String>,
+    pub target_url: String,
+    pub author: String,
+    // The revision that was rolled back to, if this change was a rollback.
+    pub restored_from: Option<RevisionId>,
+    pub access_count: i64,
+    pub bot_count: i64,
+    pub created_at: Datetime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum QrSort {
+    #[field(value = "created_at")]
+    CreatedAt,
+    #[field(value = "updated_at")]
+    UpdatedAt,
+    #[field(value = "access_count")]
+    AccessCount,
+    #[field(value = "last_accessed")]
+    LastAccessed,
+}
+
+impl QrSort {
+    pub fn field(&self) -> &'static str {
+        match self {
+            QrSort::CreatedAt => "created_at",
+            QrSort::UpdatedAt => "updated_at",
+            QrSort::AccessCount => "access_count",
+            QrSort::LastAccessed => "last_accessed",
+        }
+    }
+
+    pub fn is_datetime(&self) -> bool {
+        !matches!(self, QrSort::AccessCount)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum SortOrder {
+    #[field(value = "asc")]
+    Asc,
+    #[field(value = "desc")]
+    Desc,
+}
+
+#[derive(Debug, FromForm)]
+pub struct QrListQuery {
+    pub cursor: Option<String>,
+    #[field(default = 25, validate = range(1..=100))]
+    pub limit: usize,
+    #[field(default = QrSort::CreatedAt)]
+    pub sort: QrSort,
+    #[field(default = SortOrder::Desc)]
+    pub order: SortOrder,
+    pub domain: Option<String>,
+    pub status: Option<QrStatus>,
+    pub name: Option<String>,
+    #[field(name = "tag")]
+    pub tags: Vec<String>,
+    pub color: Option<QrColor>,
+    pub folder: Option<String>,
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub updated_after: Option<String>,
+    pub updated_before: Option<String>,
+}
+
+#[derive(Debug, FromForm)]
+pub struct QrSearchQuery {
+    pub q: String,
+    #[field(default = 20, validate = range(1..=50))]
+    pub limit: usize,
+}
+
+pub const MAX_SEARCH_LENGTH: usize = 100;
+
+pub fn search_terms(q: &str) -> Option<String> {
+    /*
+        Reduces a search to words separated by single spaces, since punctuation in the query
+        would have to match exactly (`shop.exa` finds nothing, `shop exa` does).
+
+        Returns:
+            Option<String>: The cleaned up query, or None if nothing searchable is left.
+    */
+
+    if q.chars().count() > MAX_SEARCH_LENGTH {
+        return None;
+    }
+
+    let cleaned: String = q
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let terms = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    (!terms.is_empty()).then_some(terms)
+}
+
+// Matched fields with the matches wrapped in `<mark>` tags, fields that didn't match are left out.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct QrHighlights {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub target_url: Option<String>,
+}
+
+impl QrHighlights {
+    pub fn matched_only(self) -> Self {
+        let matched = |field: &String| field.contains("<mark>");
+
+        QrHighlights {
+            name: self.name.filter(matched),
+            description: self.description.filter(matched),
+            tags: match self.tags.iter().any(matched) {
+                true => self.tags,
+                false => Vec::new(),
+            },
+            target_url: self.target_url.filter(matched),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
+pub struct QrSearchHit {
+    pub score: f64,
+    pub highlights: QrHighlights,
+    pub qrcode: DynamicQrResult,
+}
+
+// Position in a sorted listing: the sort value and key of the last code on the page.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct QrCursor {
+    pub value: Value,
+    pub id: String,
+}
+
+impl QrCursor {
+    pub fn encode(&self) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let bytes = general_purpose::URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DynamicQrPage {
+    pub urls: Vec<DynamicQrResult>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CountResult {
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LinkResult {
     pub target_url: String,
+    #[serde(default)]
+    pub target_type: QrTarget,
+    #[serde(default)]
+    pub redirect_code: RedirectCode,
+    #[serde(default)]
+    pub status: QrStatus,
+    // Shown on the page answered while the code is paused.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub color: Option<QrColor>,
+    // Trashed codes still resolve, so scans can show that they were deactivated.
+    #[serde(default)]
+    pub trashed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerUrl {
+    pub server_url: String,
 }
 
 impl fmt::Display for User {
@@ -139,5 +762,5 @@ impl fmt::Display for User {
 
 pub fn format_user_id(auth0_id: String) -> String {
     // removes the | encoded in auth_ids e.g. auth_0|id becomes auth_0_id
-    auth0_id.replace(&['|', '-'], "_")
+    auth0_id.replace(['|', '-'], "_")
 }
@@ -19,6 +19,8 @@ pub enum ApiError {
     BadRequest,
     NotFound,
     Unauthorized,
+    // Signed in, but without the role the action needs.
+    Forbidden,
     InternalServerError(String),
 }
 
@@ -66,6 +68,7 @@ impl fmt::Display for ApiError {
             ApiError::BadRequest => write!(f, "Bad Request"),
             ApiError::NotFound => write!(f, "Not Found"),
             ApiError::Unauthorized => write!(f, "Unauthorized"),
+            ApiError::Forbidden => write!(f, "Forbidden"),
             ApiError::InternalServerError(ref message) => {
                 write!(f, "Internal Server Error: {:?}", message)
             }
@@ -79,6 +82,7 @@ impl<'r> Responder<'r, 'static> for ApiError {
             ApiError::BadRequest => Status::BadRequest,
             ApiError::NotFound => Status::NotFound,
             ApiError::Unauthorized => Status::Unauthorized,
+            ApiError::Forbidden => Status::Forbidden,
             _ => Status::InternalServerError,
         };
 
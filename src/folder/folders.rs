@@ -0,0 +1,269 @@
+use crate::apikey::models::ApiScope;
+use crate::database::database::Database;
+use crate::database::models::{self, FolderId, QrCodeId};
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::folder::models::{Folder, FolderDeleteMode, MoveQrCode};
+use crate::routes::guard::Caller;
+use crate::routes::user::reach_account;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{delete, get, post, put};
+use serde_json::json;
+
+#[post("/user/<user_id>/folder", format = "json", data = "<folder>")]
+pub async fn create_folder(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    folder: Json<Folder>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Creates a folder for organizing the user's QR codes.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            folder: the folder name and optional parent folder ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the created folder.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_account(&caller, user_id)?;
+
+    let mut folder = folder.into_inner();
+
+    if !folder.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    let created = db.insert_folder(user_id, folder).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Created.code,
+        message: "Folder created".to_string(),
+        data: json!({"created": created}),
+    }))
+}
+
+#[get("/user/<user_id>/folder")]
+pub async fn read_folders(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Lists the user's folders with the scan counts of the codes in them.
+
+        Params:
+            user_id: the user's Auth0 ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: every folder with its parent, its own counts and the totals including subfolders.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+    reach_account(&caller, user_id)?;
+
+    let folders = db.list_folders(user_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Folders".to_string(),
+        data: json!({"folders": folders}),
+    }))
+}
+
+#[get("/user/<user_id>/folder/<folder_id>")]
+pub async fn read_folder(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    folder_id: FolderId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Reads one of the user's folders.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            folder_id: the folder ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the folder with its scan counts.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+    reach_account(&caller, user_id)?;
+
+    let folder = db.select_folder(user_id, &folder_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Folder".to_string(),
+        data: json!({"folder": folder}),
+    }))
+}
+
+#[put(
+    "/user/<user_id>/folder/<folder_id>",
+    format = "json",
+    data = "<folder>"
+)]
+pub async fn update_folder(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    folder_id: FolderId,
+    folder: Json<Folder>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Renames a folder or moves it under another parent.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            folder_id: the folder ID.
+            folder: the new name and parent folder ID, none for the top level.
+
+        Returns:
+            Response<Json<ApiResponse>>: the updated folder.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_account(&caller, user_id)?;
+
+    let mut folder = folder.into_inner();
+
+    if !folder.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    let updated = db.update_folder(user_id, &folder_id, folder).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Folder updated".to_string(),
+        data: json!({"updated": updated}),
+    }))
+}
+
+#[delete("/user/<user_id>/folder/<folder_id>?<mode>")]
+pub async fn delete_folder(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    folder_id: FolderId,
+    mode: Option<FolderDeleteMode>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Deletes a folder.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            folder_id: the folder ID.
+            mode: `move_to_parent` (default) moves its codes and subfolders up a level,
+                  `cascade` deletes its subfolders and every code in them.
+
+        Returns:
+            Response<Json<ApiResponse>>: how many folders and codes were deleted.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_account(&caller, user_id)?;
+
+    let deleted = db
+        .delete_folder(user_id, &folder_id, mode.unwrap_or_default())
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Folder deleted".to_string(),
+        data: json!({"deleted": deleted}),
+    }))
+}
+
+#[get("/user/<user_id>/folder/<folder_id>/qrcode?<query..>")]
+pub async fn read_folder_qrcodes(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    folder_id: FolderId,
+    query: models::QrListQuery,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Lists a page of the QR codes filed directly in a folder.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            folder_id: the folder ID.
+            query: the same paging, sorting and filters as the QR code listing.
+
+        Returns:
+            Response<Json<ApiResponse>>: the page of dynamic URLs, the total and the next cursor.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+    reach_account(&caller, user_id)?;
+
+    db.select_folder(user_id, &folder_id).await?;
+
+    let query = models::QrListQuery {
+        folder: Some(folder_id.to_string()),
+        ..query
+    };
+    let page = db
+        .list_user_urls(&models::Owner::User(user_id.to_string()), &query)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Dynamic Urls".to_string(),
+        data: json!(page),
+    }))
+}
+
+#[put(
+    "/user/<user_id>/qrcode/<qrcode_id>/folder",
+    format = "json",
+    data = "<target>"
+)]
+pub async fn move_qrcode(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+    target: Json<MoveQrCode>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Moves a QR code into a folder, or out of its folder.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            qrcode_id: the dynamic URL ID.
+            target: the folder ID to file the code in, none to unfile it.
+
+        Returns:
+            Response<Json<ApiResponse>>: the moved dynamic URL.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_account(&caller, user_id)?;
+
+    let moved = db
+        .move_dynamic_url(user_id, &qrcode_id, &target.folder)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Dynamic URL moved".to_string(),
+        data: json!({"moved": moved}),
+    }))
+}
@@ -0,0 +1,2 @@
+pub mod folders;
+pub mod models;
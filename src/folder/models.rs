@@ -0,0 +1,121 @@
+use crate::database::models::FolderId;
+
+use rocket::form::FromFormField;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+
+pub const MAX_FOLDER_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Folder {
+    pub name: String,
+    // The parent folder's ID, or none for a top level folder.
+    #[serde(default)]
+    pub parent: Option<String>,
+}
+
+impl Folder {
+    pub fn normalize(&mut self) -> bool {
+        // Folder names are trimmed and must not be blank.
+        self.name = self.name.trim().to_string();
+
+        !self.name.is_empty() && self.name.chars().count() <= MAX_FOLDER_NAME_LENGTH
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderCounts {
+    pub qrcodes: i64,
+    pub access_count: i64,
+    pub bot_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FolderResult {
+    pub id: FolderId,
+    pub name: String,
+    pub parent: Option<FolderId>,
+    // Codes filed directly in this folder.
+    pub qrcode_count: i64,
+    pub access_count: i64,
+    pub bot_count: i64,
+    // Codes in this folder and all of its subfolders, filled in by `rollup`.
+    #[serde(default)]
+    pub total: FolderCounts,
+    pub created_at: Datetime,
+    pub updated_at: Datetime,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderDeleteMode {
+    // Codes and subfolders move up to the deleted folder's parent, or to the top level.
+    #[default]
+    MoveToParent,
+    // Subfolders and every code in them are deleted as well.
+    Cascade,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderDeleted {
+    pub folders: usize,
+    pub qrcodes: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveQrCode {
+    // The folder to file the code in, or none to take it out of its folder.
+    #[serde(default)]
+    pub folder: Option<String>,
+}
+
+pub fn rollup(folders: &mut [FolderResult]) {
+    /*
+        Fills in each folder's `total` with the counts of its own codes plus those of all
+        its subfolders.
+    */
+
+    let mut totals = vec![FolderCounts::default(); folders.len()];
+
+    for (index, folder) in folders.iter().enumerate() {
+        let mut current = Some(index);
+        let mut depth = 0;
+
+        // The depth limit guards against a parent cycle looping forever.
+        while let Some(ancestor) = current.filter(|_| depth <= folders.len()) {
+            totals[ancestor].qrcodes += folder.qrcode_count;
+            totals[ancestor].access_count += folder.access_count;
+            totals[ancestor].bot_count += folder.bot_count;
+
+            current = folders[ancestor]
+                .parent
+                .as_ref()
+                .and_then(|parent| folders.iter().position(|f| &f.id == parent));
+            depth += 1;
+        }
+    }
+
+    for (folder, total) in folders.iter_mut().zip(totals) {
+        folder.total = total;
+    }
+}
+
+pub fn descendants(folders: &[FolderResult], root: &FolderId) -> Vec<FolderId> {
+    /*
+        Returns `root` followed by every folder nested anywhere beneath it.
+    */
+
+    let mut found = vec![root.clone()];
+    let mut index = 0;
+
+    while index < found.len() {
+        for folder in folders {
+            if folder.parent.as_ref() == Some(&found[index]) && !found.contains(&folder.id) {
+                found.push(folder.id.clone());
+            }
+        }
+        index += 1;
+    }
+
+    found
+}
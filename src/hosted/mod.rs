@@ -0,0 +1,4 @@
+pub mod models;
+pub mod routes;
+pub mod storage;
+pub mod stream;
@@ -0,0 +1,41 @@
+use rocket::form::FromForm;
+use rocket::fs::TempFile;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+
+pub const MIB: i64 = 1024 * 1024;
+
+// The largest upload any tier allows, used for Rocket's form limits.
+pub const MAX_UPLOAD_SIZE: i64 = 25 * MIB;
+
+#[derive(FromForm)]
+pub struct FileUpload<'r> {
+    pub file: TempFile<'r>,
+}
+
+// A stored file, as written to the database.
+#[derive(Debug, Clone, Serialize)]
+pub struct HostedFile {
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostedFileResult {
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    // Where the file is on disk, never sent to clients.
+    #[serde(skip_serializing)]
+    pub path: String,
+    pub updated_at: Datetime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageUsage {
+    // Bytes taken by the user's files, including codes in the trash.
+    pub used: i64,
+    pub limit: Option<i64>,
+}
@@ -0,0 +1,122 @@
+use crate::apikey::models::ApiScope;
+use crate::database::database::Database;
+use crate::database::models::{QrCodeId, SubscriptionTier};
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::hosted::models::FileUpload;
+use crate::hosted::storage::FileStore;
+use crate::organization::models::Role;
+use crate::routes::guard::Caller;
+use crate::routes::user::{available_storage, reach_code, validate_and_get_subscription};
+
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{get, put};
+use serde_json::json;
+
+#[get("/user/<user_id>/qrcode/<qrcode_id>/file")]
+pub async fn read_file(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Reads the name, content type and size of the file a file code serves.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            qrcode_id: the file code ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the hosted file object, or null before the first upload, as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let file = db.select_file_code(user_id, &qrcode_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Hosted file".to_string(),
+        data: json!({"file": file}),
+    }))
+}
+
+#[put("/user/<user_id>/qrcode/<qrcode_id>/file", data = "<upload>")]
+pub async fn upload_file(
+    caller: Caller,
+    db: &State<Database>,
+    store: &State<FileStore>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+    mut upload: Form<FileUpload<'_>>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Uploads the file a file code serves, replacing the previous one under the same server URL.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            qrcode_id: the file code ID.
+            upload: a multipart form with a PDF, PNG, JPEG, GIF or WebP file in `file`.
+
+        Returns:
+            Response<Json<ApiResponse>>: the hosted file object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+
+    // The file counts against the storage of whoever owns the code.
+    let owner = db
+        .dynamic_url_owner(user_id, &qrcode_id, Role::Editor)
+        .await?;
+
+    if !caller.reaches(&owner) {
+        return Err(ApiError::NotFound);
+    }
+
+    let previous = db.select_file_code(user_id, &qrcode_id).await?;
+
+    let subscription = validate_and_get_subscription(db, &owner).await?;
+    let tier = SubscriptionTier::from_str(&subscription.tier)
+        .ok_or_else(|| ApiError::InternalServerError("Invalid subscription tier".to_string()))?;
+
+    let size = upload.file.len() as i64;
+
+    if size > tier.max_file_size() {
+        return Err(ApiError::BadRequest);
+    }
+
+    // The replaced file's space is given back.
+    let room = available_storage(db, &owner).await? + previous.map_or(0, |file| file.size);
+
+    if size > room {
+        return Err(ApiError::BadRequest);
+    }
+
+    let staged = store.save(&qrcode_id.to_string(), &mut upload.file).await?;
+
+    // The previous file keeps being served until the new one is recorded.
+    let file = match db
+        .store_hosted_file(user_id, &qrcode_id, staged.file.clone())
+        .await
+    {
+        Ok(file) => file,
+        Err(error) => {
+            store.discard(staged).await;
+            return Err(error);
+        }
+    };
+
+    store.publish(staged).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "File uploaded".to_string(),
+        data: json!({"file": file}),
+    }))
+}
@@ -0,0 +1,147 @@
+use crate::errors::{ApiError, Response};
+use crate::hosted::models::HostedFile;
+use crate::utils::Environments;
+
+use rand::RngCore;
+use rocket::fs::TempFile;
+use std::path::PathBuf;
+use tokio::io::AsyncReadExt;
+
+const DEFAULT_STORAGE_DIR: &str = "uploads";
+
+// The file types codes can serve, recognised by their first bytes rather than the name
+// or the content type the browser sent.
+const SIGNATURES: &[(&[u8], &str, &str)] = &[
+    (b"%PDF-", "application/pdf", "pdf"),
+    (b"\x89PNG\r\n\x1a\n", "image/png", "png"),
+    (b"\xff\xd8\xff", "image/jpeg", "jpg"),
+    (b"GIF87a", "image/gif", "gif"),
+    (b"GIF89a", "image/gif", "gif"),
+];
+
+pub fn sniff(head: &[u8]) -> Option<(&'static str, &'static str)> {
+    /*
+        Works out the content type and extension of a file from its first bytes.
+
+        Returns:
+            Option<(&str, &str)>: The content type and extension, or None if the type isn't allowed.
+    */
+
+    // WebP files are a RIFF container with the format at offset 8.
+    if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        return Some(("image/webp", "webp"));
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(signature, _, _)| head.starts_with(signature))
+        .map(|(_, content_type, extension)| (*content_type, *extension))
+}
+
+// An upload written next to the code's file under a name of its own, until its record is stored.
+pub struct StagedFile {
+    upload: PathBuf,
+    pub file: HostedFile,
+}
+
+// Uploaded files on local disk, one per file code, named after the code's key.
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(env: &Environments) -> Self {
+        // `FILE_STORAGE_DIR` moves the files, by default they are kept next to the binary.
+        let dir = env
+            .get_optional("FILE_STORAGE_DIR")
+            .unwrap_or_else(|| DEFAULT_STORAGE_DIR.to_string());
+
+        FileStore {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    pub async fn save(&self, key: &str, file: &mut TempFile<'_>) -> Response<StagedFile> {
+        /*
+            Writes an upload for the file of a code under a unique temporary name, so concurrent
+            uploads to the same code don't overwrite each other. The code's file is left alone
+            until the upload is published.
+
+            Params:
+                key (string): The record key of the file code.
+                file (TempFile): The uploaded file.
+
+            Returns:
+                Response<StagedFile>: The staged file, or BadRequest if it is empty or of a type
+                that isn't allowed.
+        */
+
+        let io_error = |error: std::io::Error| ApiError::InternalServerError(error.to_string());
+
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(io_error)?;
+
+        let mut suffix = [0u8; 8];
+        rand::thread_rng().fill_bytes(&mut suffix);
+
+        let path = self.dir.join(key);
+        let upload = self
+            .dir
+            .join(format!("{}.{}.upload", key, hex::encode(suffix)));
+        let name = file.name().unwrap_or("file").to_string();
+        let size = file.len() as i64;
+
+        // Rocket's `move_copy_to` deletes what it wrote for contents kept in memory, so those are copied.
+        match file.path() {
+            Some(_) => file.move_copy_to(&upload).await,
+            None => file.copy_to(&upload).await,
+        }
+        .map_err(io_error)?;
+
+        let mut head = [0u8; 12];
+        let read = tokio::fs::File::open(&upload)
+            .await
+            .map_err(io_error)?
+            .read(&mut head)
+            .await
+            .map_err(io_error)?;
+
+        let (content_type, extension) = match sniff(&head[..read]) {
+            Some(kind) if size > 0 => kind,
+            _ => {
+                let _ = tokio::fs::remove_file(&upload).await;
+                return Err(ApiError::BadRequest);
+            }
+        };
+
+        Ok(StagedFile {
+            upload,
+            file: HostedFile {
+                file_name: format!("{}.{}", name, extension),
+                content_type: content_type.to_string(),
+                size,
+                path: path.to_string_lossy().into_owned(),
+            },
+        })
+    }
+
+    pub async fn publish(&self, staged: StagedFile) -> Response<()> {
+        /*
+            Replaces the file of a code with a staged upload in a single rename, so scans never
+            see a partly written file. Called once the upload's record is stored.
+
+            Params:
+                staged (StagedFile): The upload returned by `save`.
+        */
+
+        tokio::fs::rename(&staged.upload, &staged.file.path)
+            .await
+            .map_err(|error| ApiError::InternalServerError(error.to_string()))
+    }
+
+    pub async fn discard(&self, staged: StagedFile) {
+        // Removes a staged upload whose record couldn't be stored, keeping the code's file.
+        let _ = tokio::fs::remove_file(&staged.upload).await;
+    }
+}
@@ -0,0 +1,201 @@
+use crate::errors::{ApiError, Response};
+use crate::hosted::models::HostedFileResult;
+
+use rocket::http::{ContentType, Header, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::{self, Responder};
+use std::io::SeekFrom;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, Take};
+
+// The raw `Range` header of a request, if any.
+pub struct RangeHeader(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RangeHeader {
+    type Error = ();
+
+    async fn from_request(request: &'r rocket::Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RangeHeader(
+            request.headers().get_one("Range").map(str::to_string),
+        ))
+    }
+}
+
+impl RangeHeader {
+    pub fn as_deref(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    Full,
+    // Inclusive, as in `Content-Range`.
+    Partial { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+pub fn parse_range(header: Option<&str>, size: u64) -> ByteRange {
+    /*
+        Parses a single `bytes=` range against a file of `size` bytes. Multiple ranges and
+        malformed headers are ignored, which answers with the whole file.
+
+        Returns:
+            ByteRange: The bytes to send.
+    */
+
+    let spec = match header.and_then(|header| header.trim().strip_prefix("bytes=")) {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return ByteRange::Full,
+    };
+
+    let (first, last) = match spec.split_once('-') {
+        Some(bounds) => bounds,
+        None => return ByteRange::Full,
+    };
+
+    // `bytes=-n` asks for the last n bytes.
+    if first.is_empty() {
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial {
+                start: size.saturating_sub(suffix),
+                end: size - 1,
+            },
+            Err(_) => ByteRange::Full,
+        };
+    }
+
+    let start = match first.parse::<u64>() {
+        Ok(start) => start,
+        Err(_) => return ByteRange::Full,
+    };
+
+    if start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+
+    let end = if last.is_empty() {
+        size - 1
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => end.min(size - 1),
+            _ => return ByteRange::Full,
+        }
+    };
+
+    ByteRange::Partial { start, end }
+}
+
+enum FileBody {
+    Whole(File),
+    // The file positioned at the start of the range, limited to its length.
+    Slice(Take<File>),
+    Empty,
+}
+
+// A hosted file answered to a scan, whole or as the requested byte range.
+pub struct FileStream {
+    body: FileBody,
+    range: ByteRange,
+    size: u64,
+    content_type: String,
+    file_name: String,
+}
+
+impl FileStream {
+    pub async fn open(file: HostedFileResult, range: ByteRange) -> Response<Self> {
+        /*
+            Opens a hosted file for the given range. Ranges are streamed from disk like whole files,
+            so repeated range requests don't each hold a copy of the file in memory.
+
+            Returns:
+                Response<FileStream>: The file, or NotFound if it is missing from disk.
+        */
+
+        let mut opened = File::open(&file.path)
+            .await
+            .map_err(|_| ApiError::NotFound)?;
+
+        let io_error = |error: std::io::Error| ApiError::InternalServerError(error.to_string());
+
+        let size = opened.metadata().await.map_err(io_error)?.len();
+        let range = match range {
+            ByteRange::Partial { start, .. } if start >= size => ByteRange::Unsatisfiable,
+            ByteRange::Partial { start, end } => ByteRange::Partial {
+                start,
+                end: end.min(size.saturating_sub(1)),
+            },
+            other => other,
+        };
+
+        let body = match range {
+            ByteRange::Full => FileBody::Whole(opened),
+            ByteRange::Partial { start, end } => {
+                opened
+                    .seek(SeekFrom::Start(start))
+                    .await
+                    .map_err(io_error)?;
+                FileBody::Slice(opened.take(end - start + 1))
+            }
+            ByteRange::Unsatisfiable => FileBody::Empty,
+        };
+
+        Ok(FileStream {
+            body,
+            range,
+            size,
+            content_type: file.content_type,
+            file_name: file.file_name,
+        })
+    }
+}
+
+impl<'r> Responder<'r, 'static> for FileStream {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> response::Result<'static> {
+        let content_type =
+            ContentType::parse_flexible(&self.content_type).unwrap_or(ContentType::Binary);
+
+        // Replacing the file keeps the URL, so caches may not keep the old one.
+        let mut response = rocket::Response::build();
+        response
+            .header(content_type)
+            .header(Header::new("Accept-Ranges", "bytes"))
+            .header(Header::new(
+                "Content-Disposition",
+                format!("inline; filename=\"{}\"", self.file_name.replace('"', "")),
+            ))
+            .header(Header::new("Cache-Control", "no-store"));
+
+        match (self.body, self.range) {
+            (FileBody::Whole(file), _) => {
+                response
+                    .status(Status::Ok)
+                    .sized_body(self.size as usize, file);
+            }
+            (FileBody::Slice(slice), ByteRange::Partial { start, end }) => {
+                // A limited reader can't seek, so the length is set here rather than by Rocket.
+                response
+                    .status(Status::PartialContent)
+                    .header(Header::new(
+                        "Content-Range",
+                        format!("bytes {}-{}/{}", start, end, self.size),
+                    ))
+                    .header(Header::new("Content-Length", (end - start + 1).to_string()))
+                    .streamed_body(slice);
+            }
+            _ => {
+                response
+                    .status(Status::RangeNotSatisfiable)
+                    .header(Header::new(
+                        "Content-Range",
+                        format!("bytes */{}", self.size),
+                    ));
+            }
+        }
+
+        response.ok()
+    }
+}
@@ -0,0 +1,117 @@
+use crate::errors::{ApiError, Response};
+use crate::utils::Environments;
+
+use serde::Serialize;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+}
+
+#[rocket::async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, mail: &Mail) -> Response<()>;
+}
+
+// Logs that a mail would have been sent, used when no mail provider is configured. The body
+// carries the invitation token, so it is never written to the log.
+pub struct LogMailer;
+
+#[rocket::async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, mail: &Mail) -> Response<()> {
+        println!(
+            "No MAILER_URL set, not sending \"{}\" to {}",
+            mail.subject, mail.to
+        );
+
+        Ok(())
+    }
+}
+
+// Posts each mail as JSON to a provider's HTTP API.
+pub struct HttpMailer {
+    client: reqwest::Client,
+    url: String,
+    key: Option<String>,
+    from: Option<String>,
+}
+
+#[derive(Serialize)]
+struct OutgoingMail<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<&'a str>,
+    #[serde(flatten)]
+    mail: &'a Mail,
+}
+
+impl HttpMailer {
+    pub fn new(url: String, key: Option<String>, from: Option<String>) -> Self {
+        HttpMailer {
+            client: reqwest::Client::new(),
+            url,
+            key,
+            from,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Mailer for HttpMailer {
+    async fn send(&self, mail: &Mail) -> Response<()> {
+        /*
+            Sends a mail through the provider, authenticating with the key as a bearer token.
+
+            Params:
+                mail (Mail): The recipient, subject and plain text body.
+
+            Returns:
+                Response<()>: InternalServerError if the provider can't be reached or doesn't
+                answer with a 2xx status.
+        */
+
+        let mut request =
+            self.client
+                .post(&self.url)
+                .timeout(REQUEST_TIMEOUT)
+                .json(&OutgoingMail {
+                    from: self.from.as_deref(),
+                    mail,
+                });
+
+        if let Some(key) = &self.key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|error| ApiError::InternalServerError(error.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::InternalServerError(format!(
+                "Mail provider answered {}",
+                response.status().as_u16()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn from_env(env: &Environments) -> Box<dyn Mailer> {
+    // `MAILER_URL` switches to a real provider, `MAILER_KEY` and `MAILER_FROM` are passed along.
+    match env.get_optional("MAILER_URL") {
+        Some(url) => Box::new(HttpMailer::new(
+            url,
+            env.get_optional("MAILER_KEY"),
+            env.get_optional("MAILER_FROM"),
+        )),
+        None => Box::new(LogMailer),
+    }
+}
@@ -0,0 +1,4 @@
+pub mod mailer;
+pub mod models;
+pub mod routes;
+pub mod token;
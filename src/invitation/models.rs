@@ -0,0 +1,38 @@
+use crate::database::models::{InvitationId, OrgId};
+use crate::organization::models::Role;
+
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+
+// Pending invitations can no longer be accepted after this many days, unless resent.
+pub const INVITATION_EXPIRY_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvitationStatus {
+    Pending,
+    Accepted,
+    Declined,
+    // Withdrawn by an admin before the invitee answered.
+    Revoked,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvitationResult {
+    pub id: InvitationId,
+    pub organization: OrgId,
+    pub email: String,
+    pub role: Role,
+    // The user ID of the member who sent it.
+    pub invited_by: String,
+    pub status: InvitationStatus,
+    pub created_at: Datetime,
+    pub expires_at: Datetime,
+    pub resolved_at: Option<Datetime>,
+}
+
+// The token from an invitation mail, sent back to answer it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvitationToken {
+    pub token: String,
+}
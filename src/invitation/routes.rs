@@ -0,0 +1,328 @@
+use crate::database::database::Database;
+use crate::database::models::{format_user_id, InvitationId, OrgId};
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::invitation::mailer::{Mail, Mailer};
+use crate::invitation::models::{InvitationResult, InvitationToken, INVITATION_EXPIRY_DAYS};
+use crate::invitation::token::{generate_token, hash_token};
+use crate::organization::models::{NewMember, Role};
+use crate::organization::routes::can_assign;
+use crate::routes::guard::Claims;
+use crate::routes::user::require_role;
+use crate::utils::Environments;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{delete, get, post};
+use serde_json::json;
+
+fn invitation_mail(
+    env: &Environments,
+    organization: &str,
+    invitation: &InvitationResult,
+    token: &str,
+) -> Mail {
+    // `APP_URL` turns the token into a link to the frontend, otherwise the token is sent as is.
+    let answer = match env.get_optional("APP_URL") {
+        Some(app_url) => format!(
+            "Accept or decline it at {}/invitation/{}",
+            app_url.trim_end_matches('/'),
+            token
+        ),
+        None => format!("Accept or decline it with the token {}", token),
+    };
+
+    Mail {
+        to: invitation.email.clone(),
+        subject: format!("You're invited to join {}", organization),
+        text: format!(
+            "You have been invited to join {} as {}.\n\n{}\n\nThe invitation expires in {} days.",
+            organization,
+            invitation.role.as_str(),
+            answer,
+            INVITATION_EXPIRY_DAYS
+        ),
+    }
+}
+
+async fn send_invitation(
+    db: &Database,
+    env: &Environments,
+    mailer: &dyn Mailer,
+    org_id: &OrgId,
+    invitation: &InvitationResult,
+    token: &str,
+) -> Response<()> {
+    let organization = db.select_organization(org_id).await?;
+
+    mailer
+        .send(&invitation_mail(env, &organization.name, invitation, token))
+        .await
+}
+
+#[post(
+    "/user/<user_id>/org/<org_id>/invitation",
+    format = "json",
+    data = "<member>"
+)]
+pub async fn create_invitation(
+    token: Claims,
+    db: &State<Database>,
+    env: &State<Environments>,
+    mailer: &State<Box<dyn Mailer>>,
+    user_id: &str,
+    org_id: OrgId,
+    member: Json<NewMember>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Invites an email address to an organization, for admins and owners, and mails the invitee
+        a single-use token. Only owners can invite owners. If the mail can't be sent the invitation
+        stays pending and can be resent.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org_id: the organization ID.
+            member: the email to invite and the role it gets on accepting.
+
+        Returns:
+            Response<Json<ApiResponse>>: the pending invitation, without its token.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let granted = require_role(db, user_id, &org_id, Role::Admin).await?;
+
+    let mut member = member.into_inner();
+
+    if !member.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    if !can_assign(granted, None, member.role) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let secret = generate_token();
+    let invitation = db
+        .insert_invitation(
+            user_id,
+            &org_id,
+            &member.email,
+            member.role,
+            &hash_token(&secret),
+        )
+        .await?;
+
+    send_invitation(db, env, mailer.as_ref(), &org_id, &invitation, &secret).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Created.code,
+        message: "Invitation sent".to_string(),
+        data: json!({"invitation": invitation}),
+    }))
+}
+
+#[get("/user/<user_id>/org/<org_id>/invitation")]
+pub async fn read_invitations(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org_id: OrgId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Lists an organization's invitations, for admins and owners.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org_id: the organization ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the invitations, newest first.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    require_role(db, user_id, &org_id, Role::Admin).await?;
+
+    let invitations = db.list_invitations(&org_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Invitations".to_string(),
+        data: json!({"invitations": invitations}),
+    }))
+}
+
+#[post("/user/<user_id>/org/<org_id>/invitation/<invitation_id>/resend")]
+pub async fn resend_invitation(
+    token: Claims,
+    db: &State<Database>,
+    env: &State<Environments>,
+    mailer: &State<Box<dyn Mailer>>,
+    user_id: &str,
+    org_id: OrgId,
+    invitation_id: InvitationId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Mails a pending invitation again with a new token and a fresh expiry, for admins and owners.
+        The previous token stops working. Only owners can resend invitations for owners.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org_id: the organization ID.
+            invitation_id: the invitation ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the renewed invitation, without its token.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let granted = require_role(db, user_id, &org_id, Role::Admin).await?;
+    let invitation = db.select_invitation(&org_id, &invitation_id).await?;
+
+    if !can_assign(granted, None, invitation.role) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let secret = generate_token();
+    let invitation = db
+        .renew_invitation(user_id, &org_id, &invitation_id, &hash_token(&secret))
+        .await?;
+
+    send_invitation(db, env, mailer.as_ref(), &org_id, &invitation, &secret).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Invitation resent".to_string(),
+        data: json!({"invitation": invitation}),
+    }))
+}
+
+#[delete("/user/<user_id>/org/<org_id>/invitation/<invitation_id>")]
+pub async fn revoke_invitation(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org_id: OrgId,
+    invitation_id: InvitationId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Revokes a pending invitation, for admins and owners. Only owners can revoke invitations
+        for owners.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org_id: the organization ID.
+            invitation_id: the invitation ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the revoked invitation.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let granted = require_role(db, user_id, &org_id, Role::Admin).await?;
+    let invitation = db.select_invitation(&org_id, &invitation_id).await?;
+
+    if !can_assign(granted, None, invitation.role) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let revoked = db.revoke_invitation(&org_id, &invitation_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Invitation revoked".to_string(),
+        data: json!({"invitation": revoked}),
+    }))
+}
+
+#[post(
+    "/user/<user_id>/invitation/accept",
+    format = "json",
+    data = "<invitation>"
+)]
+pub async fn accept_invitation(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    invitation: Json<InvitationToken>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Accepts an invitation with the token from its mail, making the user a member. The
+        invitation has to be sent to the email of the user's account.
+
+        Params:
+            user_id: the invitee's Auth0 ID.
+            invitation: the token from the invitation mail.
+
+        Returns:
+            Response<Json<ApiResponse>>: the organization joined with the user's role in it.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let joined = db
+        .accept_invitation(user_id, &hash_token(invitation.token.trim()))
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Invitation accepted".to_string(),
+        data: json!({"organization": joined}),
+    }))
+}
+
+#[post(
+    "/user/<user_id>/invitation/decline",
+    format = "json",
+    data = "<invitation>"
+)]
+pub async fn decline_invitation(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    invitation: Json<InvitationToken>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Declines an invitation with the token from its mail.
+
+        Params:
+            user_id: the invitee's Auth0 ID.
+            invitation: the token from the invitation mail.
+
+        Returns:
+            Response<Json<ApiResponse>>: the declined invitation.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let declined = db
+        .decline_invitation(user_id, &hash_token(invitation.token.trim()))
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Invitation declined".to_string(),
+        data: json!({"invitation": declined}),
+    }))
+}
@@ -0,0 +1,25 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    format!("inv_{}", hex::encode(bytes))
+}
+
+pub fn hash_token(token: &str) -> String {
+    /*
+        Hashes an invitation token for storage and lookup.
+
+        Only the hash is kept, so the database alone can't be used to accept an invitation.
+
+        Params:
+            token (str): The token as mailed to the invitee.
+
+        Returns:
+            String: The hex encoded SHA-256 of the token.
+    */
+
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
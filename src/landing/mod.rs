@@ -0,0 +1,3 @@
+pub mod models;
+pub mod render;
+pub mod routes;
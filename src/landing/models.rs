@@ -0,0 +1,113 @@
+use crate::bulk::import::validate_target_url;
+use crate::database::models::LinkId;
+
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+use surrealdb::RecordId;
+
+pub const MAX_PAGE_LINKS: usize = 50;
+pub const MAX_PAGE_TITLE_LENGTH: usize = 100;
+pub const MAX_LINK_TITLE_LENGTH: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LandingPage {
+    pub title: String,
+    // The URL of an image shown above the title.
+    #[serde(default)]
+    pub avatar: Option<String>,
+    // Shown in this order. Links sent with the `id` of an existing link keep its clicks.
+    #[serde(default)]
+    pub links: Vec<PageLink>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageLink {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub title: String,
+    pub url: String,
+}
+
+impl LandingPage {
+    pub fn normalize(&mut self) -> bool {
+        /*
+            Trims the title, avatar and links and drops a blank avatar.
+
+            Returns:
+                bool: false if a title is blank or too long, a URL is invalid, a link ID is
+                malformed or repeated, or there are too many links.
+        */
+
+        self.title = self.title.trim().to_string();
+        self.avatar = self
+            .avatar
+            .take()
+            .map(|avatar| avatar.trim().to_string())
+            .filter(|avatar| !avatar.is_empty());
+
+        let titled =
+            |title: &str, limit: usize| !title.is_empty() && title.chars().count() <= limit;
+
+        if !titled(&self.title, MAX_PAGE_TITLE_LENGTH)
+            || self.links.len() > MAX_PAGE_LINKS
+            || self
+                .avatar
+                .as_deref()
+                .is_some_and(|avatar| validate_target_url(avatar).is_err())
+        {
+            return false;
+        }
+
+        for link in self.links.iter_mut() {
+            link.title = link.title.trim().to_string();
+            link.url = link.url.trim().to_string();
+
+            if !titled(&link.title, MAX_LINK_TITLE_LENGTH)
+                || validate_target_url(&link.url).is_err()
+            {
+                return false;
+            }
+        }
+
+        let mut ids: Vec<&str> = Vec::new();
+
+        for id in self.links.iter().filter_map(|link| link.id.as_deref()) {
+            if LinkId::parse(id).is_none() || ids.contains(&id) {
+                return false;
+            }
+            ids.push(id);
+        }
+
+        true
+    }
+}
+
+// One link as written to the database, in page order.
+#[derive(Debug, Serialize)]
+pub struct LinkRecord {
+    pub id: Option<RecordId>,
+    pub title: String,
+    pub url: String,
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageLinkResult {
+    pub id: LinkId,
+    pub title: String,
+    pub url: String,
+    #[serde(default)]
+    pub clicks: i64,
+    #[serde(default)]
+    pub bot_clicks: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LandingPageResult {
+    pub title: String,
+    #[serde(default)]
+    pub avatar: Option<String>,
+    #[serde(default)]
+    pub links: Vec<PageLinkResult>,
+    pub updated_at: Datetime,
+}
@@ -0,0 +1,82 @@
+use crate::database::models::QrColor;
+use crate::landing::models::LandingPageResult;
+use crate::routes::qrcode::redirect_location;
+use crate::scan::page::escape_html;
+
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder};
+use std::io::Cursor;
+
+const DEFAULT_ACCENT: &str = "#222222";
+
+// The hosted landing page of a page code, answered to scans in place of a redirect.
+pub struct PageView {
+    server_url: String,
+    page: LandingPageResult,
+    accent: &'static str,
+}
+
+impl PageView {
+    pub fn new(server_url: &str, page: LandingPageResult, color: Option<QrColor>) -> Self {
+        PageView {
+            server_url: server_url.to_string(),
+            page,
+            accent: color.map_or(DEFAULT_ACCENT, |color| color.hex()),
+        }
+    }
+
+    pub fn html(&self) -> String {
+        // Links go through the scan path, so each click is counted before redirecting.
+        let links: String = self
+            .page
+            .links
+            .iter()
+            .map(|link| {
+                format!(
+                    "<li><a href=\"/scan/{}/link/{}\" rel=\"noopener\">{}</a></li>",
+                    escape_html(&self.server_url),
+                    escape_html(&link.id.to_string()),
+                    escape_html(&link.title),
+                )
+            })
+            .collect();
+
+        let avatar = self.page.avatar.as_deref().map_or(String::new(), |avatar| {
+            format!(
+                "<img src=\"{}\" alt=\"\">",
+                escape_html(&redirect_location(avatar))
+            )
+        });
+
+        format!(
+            "<!DOCTYPE html>\
+            <html lang=\"en\">\
+            <head>\
+            <meta charset=\"utf-8\">\
+            <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+            <title>{title}</title>\
+            <style>body{{font-family:system-ui,sans-serif;margin:0;color:#222;border-top:6px solid {accent}}}main{{padding:32px 24px;max-width:480px;margin:0 auto;text-align:center}}img{{width:96px;height:96px;border-radius:50%;object-fit:cover}}ul{{list-style:none;padding:0}}li{{margin:12px 0}}a{{display:block;padding:14px;border:2px solid {accent};border-radius:8px;color:{accent};text-decoration:none;font-weight:600}}</style>\
+            </head>\
+            <body><main>{avatar}<h1>{title}</h1><ul>{links}</ul></main></body>\
+            </html>",
+            title = escape_html(&self.page.title),
+            accent = self.accent,
+            avatar = avatar,
+            links = links,
+        )
+    }
+}
+
+impl<'r> Responder<'r, 'static> for PageView {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> response::Result<'static> {
+        let body = self.html();
+
+        // Page content can be edited at any time, like a redirect target.
+        rocket::Response::build()
+            .status(Status::Ok)
+            .header(ContentType::HTML)
+            .header(Header::new("Cache-Control", "no-store"))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
@@ -0,0 +1,131 @@
+use crate::apikey::models::ApiScope;
+use crate::database::database::Database;
+use crate::database::models::{LinkId, QrCodeId, QrSlug, QrTarget};
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::landing::models::LandingPage;
+use crate::routes::guard::Caller;
+use crate::routes::qrcode::{redirect_location, unavailable, ScanRedirect, ScanResponse};
+use crate::routes::user::reach_code;
+use crate::scan::bot::ScanClient;
+use crate::utils::Environments;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{get, put};
+use serde_json::json;
+
+#[get("/user/<user_id>/qrcode/<qrcode_id>/page")]
+pub async fn read_page(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Reads the landing page of a page code, with the clicks on each link.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            qrcode_id: the page code ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the landing page object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let page = db.select_landing_page(user_id, &qrcode_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Landing page".to_string(),
+        data: json!({"page": page}),
+    }))
+}
+
+#[put(
+    "/user/<user_id>/qrcode/<qrcode_id>/page",
+    format = "json",
+    data = "<page>"
+)]
+pub async fn update_page(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+    page: Json<LandingPage>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Replaces the content of a page code's landing page. The printed code keeps working.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            qrcode_id: the page code ID.
+            page: the title, optional avatar URL and ordered links. Links sent with the `id` of an
+                  existing link keep its clicks, links left out are removed.
+
+        Returns:
+            Response<Json<ApiResponse>>: the updated landing page object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let mut page = page.into_inner();
+
+    if !page.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    let updated = db.update_landing_page(user_id, &qrcode_id, &page).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Landing page updated".to_string(),
+        data: json!({"page": updated}),
+    }))
+}
+
+#[get("/scan/<server_url>/link/<link_id>")]
+pub async fn follow_link(
+    server_url: QrSlug,
+    link_id: LinkId,
+    client: ScanClient,
+    db: &State<Database>,
+    env: &State<Environments>,
+) -> Response<ScanResponse> {
+    /*
+       Counts a click on a landing page link and redirects to where it goes.
+
+       Params:
+           server_url (QrSlug): The server URL of the page code.
+           link_id (LinkId): The ID of the link on its landing page.
+           client (ScanClient): Whether the click came from a person or a bot.
+
+       Returns:
+           Response<ScanResponse>: Redirects to the link, or the page a paused or trashed code answers with.
+
+    */
+
+    let link = db.lookup_dynamic_url(&server_url).await?;
+
+    if let Some(page) = unavailable(&link, env) {
+        return Ok(page);
+    }
+
+    if link.target_type != QrTarget::Page {
+        return Err(ApiError::NotFound);
+    }
+
+    let url = db
+        .record_link_click(&server_url, &link_id, client.kind)
+        .await?;
+
+    Ok(ScanResponse::Redirect(ScanRedirect::found(
+        redirect_location(&url),
+    )))
+}
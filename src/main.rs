@@ -1,12 +1,34 @@
+mod apikey;
+mod applink;
+mod bulk;
+mod contact;
 mod database;
 mod errors;
+mod folder;
+mod hosted;
+mod invitation;
+mod landing;
+mod organization;
 mod payment;
 mod routes;
+mod scan;
 mod tests;
+mod transfer;
+mod trash;
 mod utils;
+mod webhook;
 
-use rocket::{get, routes};
+use database::database::Database;
+use errors::ApiResponse;
+use hosted::models::{MAX_UPLOAD_SIZE, MIB};
+use hosted::storage::FileStore;
+use rocket::data::{Limits, ToByteUnit};
+use rocket::fairing::AdHoc;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, routes, State};
 use rocket_cors::{AllowedOrigins, CorsOptions};
+use serde_json::json;
 use shuttle_runtime::SecretStore;
 use utils::Environments;
 
@@ -29,39 +51,127 @@ fn index() -> &'static str {
     "#
 }
 
+#[get("/health")]
+fn health(db: &State<Database>) -> Json<ApiResponse> {
+    Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Healthy".to_string(),
+        data: json!({"scan_cache": db.scan_cache_stats()}),
+    })
+}
+
 #[shuttle_runtime::main]
 async fn main(#[shuttle_runtime::Secrets] secrets: SecretStore) -> shuttle_rocket::ShuttleRocket {
     let env = Environments::new(secrets);
-    let db = database::database::Database::new(&env).await.unwrap();
+    let db = Database::new(&env).await.unwrap();
+    scan::batch::spawn_flusher(db.clone());
+    webhook::delivery::spawn_dispatcher(db.clone());
+    trash::purge::spawn_purger(db.clone());
     let stripe = stripe::Client::new(env.get("STRIPE_SECRET"));
+    let files = FileStore::new(&env);
+    let mailer = invitation::mailer::from_env(&env);
+
+    // Uploads of file codes are far larger than Rocket's default form limits.
+    let limits = Limits::default()
+        .limit("file", MAX_UPLOAD_SIZE.bytes())
+        .limit("data-form", (MAX_UPLOAD_SIZE + MIB).bytes());
 
     let cors = CorsOptions::default()
         .allowed_origins(AllowedOrigins::all())
         .to_cors()
         .expect("Error creating CORS middleware");
 
-    let rocket = rocket::build()
+    let rocket = rocket::custom(rocket::Config::figment().merge(("limits", limits)))
         .mount(
             "/api",
             routes![
                 index,
+                health,
                 routes::qrcode::scan,
+                routes::qrcode::scan_head,
                 routes::user::create_user,
                 routes::user::get_user_details,
                 routes::user::create_qrcodes,
                 routes::user::read_qrcodes,
+                routes::user::search_qrcodes,
+                bulk::routes::import_qrcodes,
+                bulk::routes::bulk_update_qrcodes,
+                bulk::routes::bulk_delete_qrcodes,
+                routes::user::read_qrcode,
                 routes::user::update_qrcodes,
                 routes::user::delete_qrcodes,
+                routes::user::read_qrcode_history,
+                routes::user::rollback_qrcode,
+                routes::user::pause_qrcode,
+                routes::user::resume_qrcode,
+                routes::user::clone_qrcode,
+                trash::routes::read_trash,
+                trash::routes::restore_qrcode,
+                landing::routes::read_page,
+                landing::routes::update_page,
+                landing::routes::follow_link,
+                hosted::routes::read_file,
+                hosted::routes::upload_file,
+                applink::routes::read_app_link,
+                applink::routes::update_app_link,
+                contact::routes::read_contact,
+                contact::routes::update_contact,
+                contact::routes::download_contact,
+                transfer::routes::create_transfer,
+                transfer::routes::read_transfers,
+                transfer::routes::accept_transfer,
+                transfer::routes::decline_transfer,
+                transfer::routes::cancel_transfer,
                 payment::payments::stripe_webhook,
                 payment::payments::create_checkout_session,
                 payment::payments::update_subscription,
                 payment::payments::cancel_subscription,
+                webhook::webhooks::create_webhook,
+                webhook::webhooks::read_webhooks,
+                webhook::webhooks::delete_webhook,
+                webhook::webhooks::read_webhook_deliveries,
+                webhook::webhooks::redeliver_webhook,
+                folder::folders::create_folder,
+                folder::folders::read_folders,
+                folder::folders::read_folder,
+                folder::folders::update_folder,
+                folder::folders::delete_folder,
+                folder::folders::read_folder_qrcodes,
+                folder::folders::move_qrcode,
+                organization::routes::create_organization,
+                organization::routes::read_organizations,
+                organization::routes::read_organization,
+                organization::routes::update_organization,
+                organization::routes::delete_organization,
+                organization::routes::add_member,
+                organization::routes::update_member,
+                organization::routes::remove_member,
+                invitation::routes::create_invitation,
+                invitation::routes::read_invitations,
+                invitation::routes::resend_invitation,
+                invitation::routes::revoke_invitation,
+                invitation::routes::accept_invitation,
+                invitation::routes::decline_invitation,
+                apikey::routes::create_api_key,
+                apikey::routes::read_api_keys,
+                apikey::routes::delete_api_key,
             ],
         )
         .attach(cors)
+        .attach(AdHoc::on_shutdown("Flush scans", |rocket| {
+            Box::pin(async move {
+                if let Some(db) = rocket.state::<Database>() {
+                    if let Err(error) = db.flush_scans().await {
+                        eprintln!("Failed to flush scans on shutdown: {:?}", error);
+                    }
+                }
+            })
+        }))
         .manage(env)
         .manage(db)
-        .manage(stripe);
+        .manage(stripe)
+        .manage(files)
+        .manage(mailer);
 
     Ok(rocket.into())
 }
@@ -0,0 +1,108 @@
+use crate::database::models::{OrgId, Owner, UserSubscriptionResult, MAX_NAME_LENGTH};
+use crate::hosted::models::StorageUsage;
+use crate::transfer::models::valid_email;
+
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+
+// Ordered from least to most access, each role can do everything the ones before it can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    // Reads the organization's codes and their history.
+    Viewer,
+    // Also creates, edits and deletes codes.
+    Editor,
+    // Also renames the organization and manages members below owner.
+    Admin,
+    // Also manages owners and the subscription, and can delete the organization.
+    Owner,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+            Role::Owner => "owner",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Organization {
+    pub name: String,
+}
+
+impl Organization {
+    pub fn normalize(&mut self) -> bool {
+        // Names are labels like a code's name, trimmed and up to the same length.
+        self.name = self.name.trim().to_string();
+
+        !self.name.is_empty() && self.name.chars().count() <= MAX_NAME_LENGTH
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrganizationResult {
+    pub id: OrgId,
+    pub name: String,
+    pub created_at: Datetime,
+}
+
+// An organization as listed for one of its members, with their role in it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MembershipResult {
+    pub id: OrgId,
+    pub name: String,
+    pub role: Role,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemberResult {
+    // The member's user ID.
+    pub user: String,
+    pub username: String,
+    pub email: String,
+    pub role: Role,
+    pub joined_at: Datetime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewMember {
+    // The email address of an existing account.
+    pub email: String,
+    pub role: Role,
+}
+
+impl NewMember {
+    pub fn normalize(&mut self) -> bool {
+        // Emails are matched case-insensitively against the member's account.
+        self.email = self.email.trim().to_lowercase();
+
+        valid_email(&self.email)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemberUpdate {
+    pub role: Role,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrganizationDetails {
+    pub organization: OrganizationResult,
+    // The role of the member asking.
+    pub role: Role,
+    pub members: Vec<MemberResult>,
+    pub subscription: Option<UserSubscriptionResult>,
+    pub storage: StorageUsage,
+}
+
+// Who owns a code, and the role a user has on it, if any.
+#[derive(Debug, Deserialize)]
+pub struct CodeAccess {
+    pub owner: Option<Owner>,
+    pub role: Option<Role>,
+}
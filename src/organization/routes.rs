@@ -0,0 +1,376 @@
+use crate::database::database::Database;
+use crate::database::models::{format_user_id, OrgId, Owner, SubscriptionTier};
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::hosted::models::StorageUsage;
+use crate::organization::models::{
+    MemberUpdate, NewMember, Organization, OrganizationDetails, Role,
+};
+use crate::routes::guard::Claims;
+use crate::routes::user::require_role;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{delete, get, post, put};
+use serde_json::json;
+
+pub(crate) fn can_assign(granted: Role, current: Option<Role>, role: Role) -> bool {
+    // Only owners make owners or change what an owner can do, admins manage everyone else.
+    granted == Role::Owner || (current != Some(Role::Owner) && role != Role::Owner)
+}
+
+#[post("/user/<user_id>/org", format = "json", data = "<organization>")]
+pub async fn create_organization(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    organization: Json<Organization>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Creates an organization with the user as its owner.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            organization: the organization's name.
+
+        Returns:
+            Response<Json<ApiResponse>>: the created organization.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let mut organization = organization.into_inner();
+
+    if !organization.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    let created = db.insert_organization(user_id, organization).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Created.code,
+        message: "Organization created".to_string(),
+        data: json!({"created": created}),
+    }))
+}
+
+#[get("/user/<user_id>/org")]
+pub async fn read_organizations(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Lists the organizations the user is a member of.
+
+        Params:
+            user_id: the user's Auth0 ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: every organization with the user's role in it.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let organizations = db.list_organizations(user_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Organizations".to_string(),
+        data: json!({"organizations": organizations}),
+    }))
+}
+
+#[get("/user/<user_id>/org/<org_id>")]
+pub async fn read_organization(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org_id: OrgId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Reads an organization with its members, subscription and storage, for any member.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org_id: the organization ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the organization details and the user's role.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let role = require_role(db, user_id, &org_id, Role::Viewer).await?;
+
+    let owner = Owner::Organization(org_id.clone());
+    let subscription = db.get_subscription(&owner).await.ok();
+
+    let storage = StorageUsage {
+        used: db.storage_usage(&owner).await?,
+        limit: subscription
+            .as_ref()
+            .and_then(|subscription| SubscriptionTier::from_str(&subscription.tier))
+            .map(|tier| tier.max_storage()),
+    };
+
+    let details = OrganizationDetails {
+        organization: db.select_organization(&org_id).await?,
+        role,
+        members: db.list_members(&org_id).await?,
+        subscription,
+        storage,
+    };
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Organization found".to_string(),
+        data: json!(details),
+    }))
+}
+
+#[put(
+    "/user/<user_id>/org/<org_id>",
+    format = "json",
+    data = "<organization>"
+)]
+pub async fn update_organization(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org_id: OrgId,
+    organization: Json<Organization>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Renames an organization, for admins and owners.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org_id: the organization ID.
+            organization: the new name.
+
+        Returns:
+            Response<Json<ApiResponse>>: the renamed organization.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    require_role(db, user_id, &org_id, Role::Admin).await?;
+
+    let mut organization = organization.into_inner();
+
+    if !organization.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    let updated = db.update_organization(&org_id, organization).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Organization updated".to_string(),
+        data: json!({"updated": updated}),
+    }))
+}
+
+#[delete("/user/<user_id>/org/<org_id>")]
+pub async fn delete_organization(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org_id: OrgId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Deletes an organization with all of its codes, for owners. An organization with a live
+        subscription has to cancel it first.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org_id: the organization ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: whether the organization was deleted.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    require_role(db, user_id, &org_id, Role::Owner).await?;
+
+    if db
+        .validate_subscription_status(&Owner::Organization(org_id.clone()))
+        .await?
+    {
+        return Err(ApiError::BadRequest);
+    }
+
+    let deleted = db.delete_organization(&org_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Organization deleted".to_string(),
+        data: json!({"deleted": deleted}),
+    }))
+}
+
+#[post(
+    "/user/<user_id>/org/<org_id>/member",
+    format = "json",
+    data = "<member>"
+)]
+pub async fn add_member(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org_id: OrgId,
+    member: Json<NewMember>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Adds an existing account to an organization by email, for admins and owners. Only owners
+        can add owners.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org_id: the organization ID.
+            member: the email of the account and its role.
+
+        Returns:
+            Response<Json<ApiResponse>>: the new member.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let granted = require_role(db, user_id, &org_id, Role::Admin).await?;
+
+    let mut member = member.into_inner();
+
+    if !member.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    if !can_assign(granted, None, member.role) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let added = db.add_member(&org_id, member).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Created.code,
+        message: "Member added".to_string(),
+        data: json!({"created": added}),
+    }))
+}
+
+#[put(
+    "/user/<user_id>/org/<org_id>/member/<member_id>",
+    format = "json",
+    data = "<update>"
+)]
+pub async fn update_member(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org_id: OrgId,
+    member_id: &str,
+    update: Json<MemberUpdate>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Changes a member's role, for admins and owners. Only owners can change an owner's role
+        or make someone an owner, and the last owner can't step down.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org_id: the organization ID.
+            member_id: the member's user ID.
+            update: the new role.
+
+        Returns:
+            Response<Json<ApiResponse>>: the member with the new role.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let granted = require_role(db, user_id, &org_id, Role::Admin).await?;
+    let current = db
+        .member_role(member_id, &org_id)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    if !can_assign(granted, Some(current), update.role) {
+        return Err(ApiError::Forbidden);
+    }
+
+    let updated = db.set_member_role(&org_id, member_id, update.role).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Member updated".to_string(),
+        data: json!({"updated": updated}),
+    }))
+}
+
+#[delete("/user/<user_id>/org/<org_id>/member/<member_id>")]
+pub async fn remove_member(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org_id: OrgId,
+    member_id: &str,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Removes a member from an organization. Any member can leave, admins remove members below
+        owner and owners remove anyone, as long as an owner is left.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org_id: the organization ID.
+            member_id: the member's user ID, the user's own to leave.
+
+        Returns:
+            Response<Json<ApiResponse>>: whether the member was removed.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    if member_id != user_id {
+        let granted = require_role(db, user_id, &org_id, Role::Admin).await?;
+        let current = db
+            .member_role(member_id, &org_id)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+
+        if !can_assign(granted, Some(current), current) {
+            return Err(ApiError::Forbidden);
+        }
+    }
+
+    let removed = db.remove_member(&org_id, member_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Member removed".to_string(),
+        data: json!({"removed": removed}),
+    }))
+}
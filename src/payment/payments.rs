@@ -1,10 +1,12 @@
 use crate::database::database::Database;
 use crate::database::models::{
-    format_user_id, PaymentSession, SubscriptionAction, UpdateRequest, UserSubscription,
+    format_user_id, Owner, PaymentSession, SubscriptionAction, UpdateRequest, UserSubscription,
 };
 use crate::errors::{ApiError, ApiResponse, Response};
+use crate::organization::models::Role;
 use crate::payment::models::PaymentRequest;
 use crate::routes::guard::Claims;
+use crate::routes::user::workspace;
 use crate::utils::Environments;
 
 use rocket::data::{FromData, ToByteUnit};
@@ -22,20 +24,23 @@ use stripe::{
 };
 use stripe::{Client, Subscription, SubscriptionId};
 
-#[post("/subscription/<user_id>", format = "json", data = "<payment>")]
+#[post("/subscription/<user_id>?<org>", format = "json", data = "<payment>")]
 pub async fn create_checkout_session(
     token: Claims,
     payment: Json<PaymentRequest>,
     db: &State<Database>,
     user_id: &str,
+    org: Option<&str>,
     stripe: &State<Client>,
     secrets: &State<Environments>,
 ) -> Response<Json<ApiResponse>> {
     /*
-        Creates a new checkout session for a payment.
+        Creates a new checkout session for a payment, for the user or, with `org`, for an
+        organization they own. The user is the billing contact either way.
 
         Params:
             payment: payment object containing the payment details.
+            org: optional organization ID to subscribe the organization.
 
         Returns:
             Response<Value>: the created checkout session url in a json response.
@@ -46,13 +51,15 @@ pub async fn create_checkout_session(
         return Err(ApiError::Unauthorized);
     }
 
+    let owner = workspace(db, user_id, org, Role::Owner).await?;
+
     // check if user has a valid subscription.
 
-    if db.validate_subscription_status(&user_id).await? {
+    if db.validate_subscription_status(&owner).await? {
         return Err(ApiError::BadRequest);
     }
 
-    let user = match db.select_user(&user_id).await? {
+    let user = match db.select_user(user_id).await? {
         Some(user) => user,
         None => return Err(ApiError::NotFound),
     };
@@ -60,7 +67,7 @@ pub async fn create_checkout_session(
     // create a customer with user info.
 
     let customer = Customer::create(
-        &stripe,
+        stripe,
         CreateCustomer {
             name: Some(&user.username),
             email: Some(&user.email),
@@ -77,7 +84,7 @@ pub async fn create_checkout_session(
     // create a checkout session with the customer id and payment details.
 
     let session = CheckoutSession::create(
-        &stripe,
+        stripe,
         CreateCheckoutSession {
             cancel_url: Some(&secrets.get("STRIPE_CANCEL_URL")),
             success_url: Some(&secrets.get("STRIPE_SUCCESS_URL")),
@@ -101,7 +108,7 @@ pub async fn create_checkout_session(
     .await?;
 
     db.insert_session(
-        user_id,
+        &owner,
         PaymentSession {
             session_id: session.id.to_string(),
             tier: payment.tier.clone(),
@@ -116,20 +123,26 @@ pub async fn create_checkout_session(
     }))
 }
 
-#[put("/subscription/<user_id>", format = "json", data = "<update_request>")]
+#[put(
+    "/subscription/<user_id>?<org>",
+    format = "json",
+    data = "<update_request>"
+)]
 pub async fn update_subscription(
     token: Claims,
     update_request: Json<UpdateRequest>,
     db: &State<Database>,
     user_id: &str,
+    org: Option<&str>,
     stripe: &State<Client>,
     secrets: &State<Environments>,
 ) -> Response<Json<ApiResponse>> {
     /*
-        Updates a subscription for a user.
+        Updates a subscription for a user, or for an organization they own.
 
         Params:
             subscription: subscription object containing the subscription details.
+            org: optional organization ID to update the organization's subscription.
 
         Returns:
             Response<Value>: the updated subscription object in a json response.
@@ -140,7 +153,9 @@ pub async fn update_subscription(
         return Err(ApiError::Unauthorized);
     }
 
-    let subscription_id = match db.get_subscription_id(&user_id).await? {
+    let owner = workspace(db, user_id, org, Role::Owner).await?;
+
+    let subscription_id = match db.get_subscription_id(&owner).await? {
         Some(id) => id,
         None => return Err(ApiError::NotFound),
     };
@@ -148,7 +163,7 @@ pub async fn update_subscription(
     match update_request.action {
         SubscriptionAction::Cancel => {
             let cancelled = Subscription::update(
-                &stripe,
+                stripe,
                 &SubscriptionId::from_str(&subscription_id).unwrap(),
                 stripe::UpdateSubscription {
                     cancel_at_period_end: Some(true),
@@ -157,16 +172,16 @@ pub async fn update_subscription(
             )
             .await?;
 
-            return Ok(Json(ApiResponse {
+            Ok(Json(ApiResponse {
                 status: Status::Ok.code,
                 message: "Subscription cancelled. ".to_string(),
                 data: json!({"cancelled": cancelled}),
-            }));
+            }))
         }
 
         SubscriptionAction::Upgrade => {
             let sub_item = stripe::Subscription::retrieve(
-                &stripe,
+                stripe,
                 &SubscriptionId::from_str(&subscription_id).unwrap(),
                 &[],
             )
@@ -177,7 +192,7 @@ pub async fn update_subscription(
                 .clone();
 
             let upgraded = Subscription::update(
-                &stripe,
+                stripe,
                 &SubscriptionId::from_str(&subscription_id).unwrap(),
                 stripe::UpdateSubscription {
                     items: Some(vec![stripe::UpdateSubscriptionItems {
@@ -195,16 +210,16 @@ pub async fn update_subscription(
             )
             .await?;
 
-            return Ok(Json(ApiResponse {
+            Ok(Json(ApiResponse {
                 status: Status::Ok.code,
                 message: "Subscription cancelled. ".to_string(),
                 data: json!({"upgraded": upgraded}),
-            }));
+            }))
         }
 
         SubscriptionAction::Downgrade => {
             let sub_item = stripe::Subscription::retrieve(
-                &stripe,
+                stripe,
                 &SubscriptionId::from_str(&subscription_id).unwrap(),
                 &[],
             )
@@ -215,7 +230,7 @@ pub async fn update_subscription(
                 .clone();
 
             let downgraded = Subscription::update(
-                &stripe,
+                stripe,
                 &SubscriptionId::from_str(&subscription_id).unwrap(),
                 stripe::UpdateSubscription {
                     items: Some(vec![stripe::UpdateSubscriptionItems {
@@ -233,16 +248,16 @@ pub async fn update_subscription(
             )
             .await?;
 
-            return Ok(Json(ApiResponse {
+            Ok(Json(ApiResponse {
                 status: Status::Ok.code,
                 message: "Subscription cancelled. ".to_string(),
                 data: json!({"downgraded": downgraded}),
-            }));
+            }))
         }
 
         SubscriptionAction::Resume => {
             let resumed = Subscription::update(
-                &stripe,
+                stripe,
                 &SubscriptionId::from_str(&subscription_id).unwrap(),
                 stripe::UpdateSubscription {
                     cancel_at_period_end: Some(false),
@@ -251,27 +266,29 @@ pub async fn update_subscription(
             )
             .await?;
 
-            return Ok(Json(ApiResponse {
+            Ok(Json(ApiResponse {
                 status: Status::Ok.code,
                 message: "Subscription cancelled. ".to_string(),
                 data: json!({"resumed": resumed}),
-            }));
+            }))
         }
     }
 }
 
-#[delete("/subscription/<user_id>", format = "json")]
+#[delete("/subscription/<user_id>?<org>", format = "json")]
 pub async fn cancel_subscription(
     token: Claims,
     user_id: &str,
+    org: Option<&str>,
     db: &State<Database>,
     stripe: &State<Client>,
 ) -> Response<Json<ApiResponse>> {
     /*
-        Cancels a subscription for a user.
+        Cancels a subscription for a user, or for an organization they own.
 
         Params:
             subscription: subscription object containing the subscription details.
+            org: optional organization ID to cancel the organization's subscription.
 
         Returns:
             Response<Value>: the cancelled subscription object in a json response.
@@ -282,13 +299,15 @@ pub async fn cancel_subscription(
         return Err(ApiError::Unauthorized);
     }
 
-    let subscription_id = match db.get_subscription_id(&user_id).await? {
+    let owner = workspace(db, user_id, org, Role::Owner).await?;
+
+    let subscription_id = match db.get_subscription_id(&owner).await? {
         Some(id) => id,
         None => return Err(ApiError::NotFound),
     };
 
     let result = Subscription::cancel(
-        &stripe,
+        stripe,
         &SubscriptionId::from_str(&subscription_id).unwrap(),
         stripe::CancelSubscription {
             prorate: Some(true),
@@ -332,13 +351,13 @@ pub async fn stripe_webhook(
         match event.type_ {
             EventType::CheckoutSessionCompleted => {
                 if let EventObject::CheckoutSession(session) = event.data.object {
-                    let user = db.get_user_from_session(&session.id).await?;
+                    let owner = db.get_owner_from_session(&session.id).await?;
 
-                    let _subscription = match &session.subscription {
+                    match &session.subscription {
                         Some(sub) => {
                             let subscription = db
                                 .insert_subscription(
-                                    &user.id.key().to_string(),
+                                    &owner,
                                     UserSubscription {
                                         sub_id: sub.id().to_string(),
                                         tier: session.client_reference_id.unwrap().to_string(),
@@ -347,18 +366,14 @@ pub async fn stripe_webhook(
                                 )
                                 .await?;
 
-                            dbg!(&user.id.key().to_string());
-
-                            return Ok(Json(ApiResponse {
+                            Ok(Json(ApiResponse {
                                 status: Status::Ok.code,
                                 message: "Subscription inserted. ".to_string(),
                                 data: json!({"subscribed": subscription}),
-                            }));
-                        }
-                        None => {
-                            return Err(ApiError::BadRequest);
+                            }))
                         }
-                    };
+                        None => Err(ApiError::BadRequest),
+                    }
                 } else {
                     Err(ApiError::BadRequest)
                 }
@@ -366,8 +381,7 @@ pub async fn stripe_webhook(
 
             EventType::CustomerSubscriptionUpdated => {
                 if let EventObject::Subscription(subscription) = event.data.object {
-                    dbg!(&subscription);
-                    let user = db.get_user_from_subscription(&subscription.id).await?;
+                    let owner = db.get_owner_from_subscription(&subscription.id).await?;
 
                     let tier = match &subscription.items.data[0]
                         .price
@@ -382,50 +396,45 @@ pub async fn stripe_webhook(
                     };
 
                     let updated = db
-                        .override_subscription(
-                            &user.id.key().to_string(),
-                            &subscription.id.to_string(),
-                            &tier,
-                        )
+                        .override_subscription(&owner, subscription.id.as_ref(), &tier)
                         .await?;
 
-                    return Ok(Json(ApiResponse {
+                    Ok(Json(ApiResponse {
                         status: Status::Ok.code,
                         message: "Subscription updated. ".to_string(),
                         data: json!({"updated": updated}),
-                    }));
+                    }))
                 } else {
-                    return Err(ApiError::BadRequest);
+                    Err(ApiError::BadRequest)
                 }
             }
 
             EventType::CustomerSubscriptionDeleted => {
                 if let EventObject::Subscription(subscription) = event.data.object {
-                    let user = db.get_user_from_subscription(&subscription.id).await?;
-
-                    let _ = db.delete_user_data(&user.id.key().to_string()).await?;
+                    let _ = match db.get_owner_from_subscription(&subscription.id).await? {
+                        Owner::User(user_id) => db.delete_user_data(&user_id).await?,
+                        Owner::Organization(org_id) => db.delete_organization_data(&org_id).await?,
+                    };
 
-                    return Ok(Json(ApiResponse {
+                    Ok(Json(ApiResponse {
                         status: Status::Ok.code,
                         message: "Subscription deleted. ".to_string(),
                         data: json!({"deleted": subscription.id().to_string()}),
-                    }));
+                    }))
                 } else {
-                    return Err(ApiError::BadRequest);
+                    Err(ApiError::BadRequest)
                 }
             }
-            _ => {
-                return Ok(Json(ApiResponse {
-                    status: Status::PartialContent.code,
-                    message: "Event received. ".to_string(),
-                    data: json!(event),
-                }))
-            }
+            _ => Ok(Json(ApiResponse {
+                status: Status::PartialContent.code,
+                message: "Event received. ".to_string(),
+                data: json!(event),
+            })),
         }
     } else {
-        return Err(ApiError::InternalServerError(
+        Err(ApiError::InternalServerError(
             "Stripe signature invalid. ".to_string(),
-        ));
+        ))
     }
 }
 
@@ -1,3 +1,8 @@
+use crate::apikey::key::{hash_key, API_KEY_PREFIX};
+use crate::apikey::models::{ApiKeyGrant, ApiScope};
+use crate::database::database::Database;
+use crate::database::models::{format_user_id, Owner};
+use crate::errors::ApiError;
 use crate::utils::decode_jwt;
 use crate::utils::Environments;
 
@@ -6,6 +11,7 @@ use rocket::request::{FromRequest, Outcome};
 use rocket::State;
 use serde::Deserialize;
 
+#[allow(dead_code)]
 #[derive(Deserialize)]
 pub struct Claims {
     pub sub: String,
@@ -25,6 +31,11 @@ impl<'r> FromRequest<'r> for Claims {
         if let Some(bearer_token) = token {
             let token_str = bearer_token.trim_start_matches("Bearer ").trim();
 
+            // API keys only open the routes that take a `Caller`.
+            if token_str.starts_with(API_KEY_PREFIX) {
+                return Outcome::Error((Status::Unauthorized, ()));
+            }
+
             match decode_jwt(token_str, secrets.inner()).await {
                 Ok(claims) => Outcome::Success(claims),
                 Err(_) => Outcome::Error((Status::Unauthorized, ())),
@@ -34,3 +45,96 @@ impl<'r> FromRequest<'r> for Claims {
         }
     }
 }
+
+// Who made a request: a user signed in through Auth0, or a script with one of their API keys.
+pub struct Caller {
+    pub user_id: String,
+    // Only set for API keys, which are held to their scopes and their one workspace.
+    pub key: Option<ApiKeyGrant>,
+}
+
+impl Caller {
+    pub fn authorize(&self, user_id: &str, scope: ApiScope) -> Result<(), ApiError> {
+        /*
+            Checks that the request is for the caller's own account and that a key has the scope
+            the route needs.
+
+            Returns:
+                (): Unauthorized for another user's account, Forbidden for a key without the scope.
+        */
+
+        if user_id != self.user_id {
+            return Err(ApiError::Unauthorized);
+        }
+
+        match &self.key {
+            Some(key) if !key.scopes.contains(&scope) => Err(ApiError::Forbidden),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn workspace(&self, org: Option<&str>) -> Result<Option<String>, ApiError> {
+        /*
+            Narrows the `?org=` of a collection route to what the caller can reach. Organization
+            keys work on their organization without it.
+
+            Returns:
+                Option<String>: The organization to work on, if any. Forbidden if a key asks for
+                another workspace.
+        */
+
+        let Some(key) = &self.key else {
+            return Ok(org.map(str::to_string));
+        };
+
+        match (&key.owner, org) {
+            (Owner::User(_), None) => Ok(None),
+            (Owner::Organization(org_id), None) => Ok(Some(org_id.to_string())),
+            (Owner::Organization(org_id), Some(org)) if org == org_id.to_string() => {
+                Ok(Some(org.to_string()))
+            }
+            _ => Err(ApiError::Forbidden),
+        }
+    }
+
+    pub fn reaches(&self, owner: &Owner) -> bool {
+        // Signed-in users reach whatever their roles allow, keys only their own workspace.
+        self.key.as_ref().is_none_or(|key| &key.owner == owner)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Caller {
+    type Error = ();
+
+    async fn from_request(request: &'r rocket::Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(bearer_token) = request.headers().get_one("Authorization") else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        let token = bearer_token.trim_start_matches("Bearer ").trim();
+
+        if token.starts_with(API_KEY_PREFIX) {
+            let db = request.guard::<&State<Database>>().await.unwrap();
+
+            return match db.authenticate_api_key(&hash_key(token)).await {
+                Ok(Some(key)) => Outcome::Success(Caller {
+                    user_id: key.user.clone(),
+                    key: Some(key),
+                }),
+                Ok(None) => Outcome::Error((Status::Unauthorized, ())),
+                Err(_) => Outcome::Error((Status::InternalServerError, ())),
+            };
+        }
+
+        let secrets = request.guard::<&State<Environments>>().await.unwrap();
+
+        match decode_jwt(token, secrets.inner()).await {
+            Ok(claims) => Outcome::Success(Caller {
+                user_id: format_user_id(claims.sub),
+                key: None,
+            }),
+            Err(_) => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
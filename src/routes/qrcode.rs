@@ -1,28 +1,216 @@
+use crate::applink::models::AppLaunch;
+use crate::applink::platform::{Platform, ScanPlatform};
+use crate::applink::render::LaunchPage;
+use crate::contact::render::ContactView;
+use crate::contact::vcard::VCardFile;
 use crate::database::database::Database;
+use crate::database::models::{LinkResult, QrSlug, QrStatus, QrTarget, RedirectCode};
 use crate::errors::Response;
+use crate::hosted::stream::{parse_range, FileStream, RangeHeader};
+use crate::landing::render::PageView;
+use crate::scan::bot::ScanClient;
+use crate::scan::page::StatusPage;
+use crate::utils::Environments;
 
-use rocket::get;
-use rocket::response::Redirect;
+use rocket::http::Header;
+use rocket::response::{self, Responder};
 use rocket::State;
+use rocket::{get, head};
+
+#[derive(Responder)]
+pub enum ScanResponse {
+    Redirect(ScanRedirect),
+    Page(StatusPage),
+    Landing(PageView),
+    File(FileStream),
+    App(LaunchPage),
+    Contact(ContactView),
+    VCard(VCardFile),
+}
+
+pub struct ScanRedirect {
+    code: RedirectCode,
+    location: String,
+}
+
+impl ScanRedirect {
+    pub fn new(link: LinkResult) -> Self {
+        ScanRedirect {
+            code: link.redirect_code,
+            location: redirect_location(&link.target_url),
+        }
+    }
+
+    pub fn found(location: String) -> Self {
+        ScanRedirect {
+            code: RedirectCode::Found,
+            location,
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ScanRedirect {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> response::Result<'static> {
+        // Targets can change at any time, so neither browsers nor proxies may keep the redirect.
+        rocket::Response::build()
+            .status(self.code.status())
+            .header(Header::new("Location", self.location))
+            .header(Header::new("Cache-Control", "no-store"))
+            .ok()
+    }
+}
+
+pub(crate) fn unavailable(link: &LinkResult, env: &Environments) -> Option<ScanResponse> {
+    // Trashed codes go to `DEACTIVATED_URL` when it is set, or to a built-in page.
+    if link.trashed {
+        return Some(match env.get_optional("DEACTIVATED_URL") {
+            Some(location) => ScanResponse::Redirect(ScanRedirect::found(location)),
+            None => ScanResponse::Page(StatusPage::deactivated()),
+        });
+    }
+
+    match link.status {
+        QrStatus::Paused => Some(ScanResponse::Page(StatusPage::paused(
+            link.name.as_deref(),
+            link.color,
+        ))),
+        QrStatus::Active => None,
+    }
+}
+
+async fn resolve(
+    server_url: &QrSlug,
+    link: LinkResult,
+    range: &RangeHeader,
+    platform: Platform,
+    db: &Database,
+) -> Response<ScanResponse> {
+    // Page and contact codes render their page, file codes serve their file and app codes pick by
+    // platform, the rest redirect.
+    match link.target_type {
+        QrTarget::Url => Ok(ScanResponse::Redirect(ScanRedirect::new(link))),
+        QrTarget::Page => {
+            let page = db.lookup_landing_page(server_url).await?;
+
+            Ok(ScanResponse::Landing(PageView::new(
+                server_url.as_str(),
+                page,
+                link.color,
+            )))
+        }
+        QrTarget::File => {
+            let file = db.lookup_hosted_file(server_url).await?;
+            let range = parse_range(range.as_deref(), file.size.max(0) as u64);
+
+            Ok(ScanResponse::File(FileStream::open(file, range).await?))
+        }
+        QrTarget::App => {
+            let app_link = db.lookup_app_link(server_url).await?;
+
+            // Deep links can have any allowed scheme, so they are only ever opened from the page.
+            Ok(match app_link.launch(platform) {
+                AppLaunch::Page {
+                    deep_link,
+                    fallback,
+                } => {
+                    ScanResponse::App(LaunchPage::new(link.name.as_deref(), &deep_link, &fallback))
+                }
+                AppLaunch::Redirect(location) => {
+                    ScanResponse::Redirect(ScanRedirect::found(redirect_location(&location)))
+                }
+            })
+        }
+        QrTarget::Contact => {
+            let card = db.lookup_contact_card(server_url).await?;
+
+            Ok(if card.download {
+                ScanResponse::VCard(VCardFile::new(&card))
+            } else {
+                ScanResponse::Contact(ContactView::new(server_url.as_str(), card, link.color))
+            })
+        }
+    }
+}
+
+pub fn redirect_location(target_url: &str) -> String {
+    // Targets saved without a scheme are treated as plain http.
+    let lowered = target_url.to_ascii_lowercase();
+
+    if lowered.starts_with("https://") || lowered.starts_with("http://") {
+        return target_url.to_string();
+    }
+
+    format!("http://{}", target_url)
+}
 
 #[get("/scan/<server_url>")]
-pub async fn scan(server_url: &str, db: &State<Database>) -> Response<Redirect> {
+pub async fn scan(
+    server_url: QrSlug,
+    client: ScanClient,
+    range: RangeHeader,
+    platform: ScanPlatform,
+    db: &State<Database>,
+    env: &State<Environments>,
+) -> Response<ScanResponse> {
+    /*
+       Redirects to the target URL of a dynamic QR code, renders the landing page of a page code
+       or serves the file of a file code, honouring a single byte `Range`. App codes send phones to
+       a page that tries the deep link, and everything else to the fallback. Contact codes show
+       their contact card, or download it as a vCard.
+
+       Bots and link previews are still redirected, but counted apart from people.
+       Paused and trashed codes answer with a page instead, and aren't counted.
+
+       Params:
+           server_url (QrSlug): The server URL of the dynamic QR code.
+           client (ScanClient): Whether the request came from a person or a bot.
+           range (RangeHeader): The requested byte range of a file, if any.
+           platform (ScanPlatform): The phone platform, for app codes.
+
+       Returns:
+           Response<ScanResponse>: Redirects to the target URL with the code's redirect status, the landing page, the file, the app launch page, the contact page or the vCard.
+
+    */
+
+    let link = db.lookup_dynamic_url(&server_url).await?;
+
+    if let Some(page) = unavailable(&link, env) {
+        return Ok(page);
+    }
+
+    let response = resolve(&server_url, link, &range, platform.platform, db).await?;
+
+    db.record_scan(&server_url, client.kind);
+
+    Ok(response)
+}
+
+#[head("/scan/<server_url>")]
+pub async fn scan_head(
+    server_url: QrSlug,
+    range: RangeHeader,
+    platform: ScanPlatform,
+    db: &State<Database>,
+    env: &State<Environments>,
+) -> Response<ScanResponse> {
     /*
-       Redirects to the target URL of a dynamic QR code.
+       Answers HEAD requests for a dynamic QR code without counting them as a scan.
 
        Params:
-           server_url (str): The server URL of the dynamic QR code.
+           server_url (QrSlug): The server URL of the dynamic QR code.
+           range (RangeHeader): The requested byte range of a file, if any.
+           platform (ScanPlatform): The phone platform, for app codes.
 
        Returns:
-           Response<Redirect>: Redirects to the target URL.
+           Response<ScanResponse>: The same response a GET would receive.
 
     */
 
-    let url = db.lookup_dynamic_url(&server_url).await?;
+    let link = db.lookup_dynamic_url(&server_url).await?;
 
-    if url.contains("Https://") || url.contains("http://") {
-        return Ok(Redirect::to(url));
+    if let Some(page) = unavailable(&link, env) {
+        return Ok(page);
     }
 
-    Ok(Redirect::to(format!("http://{}", url)))
+    resolve(&server_url, link, &range, platform.platform, db).await
 }
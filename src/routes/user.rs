@@ -1,7 +1,13 @@
+use crate::apikey::models::ApiScope;
 use crate::database::database::Database;
-use crate::database::models::{self, format_user_id, SubscriptionTier, User, UserDetails};
+use crate::database::models::{
+    self, format_user_id, OrgId, Owner, QrCodeId, QrStatus, RevisionId, SubscriptionTier, User,
+    UserDetails,
+};
 use crate::errors::{ApiError, ApiResponse, Response};
-use crate::routes::guard::Claims;
+use crate::hosted::models::StorageUsage;
+use crate::organization::models::Role;
+use crate::routes::guard::{Caller, Claims};
 
 use rocket::http::Status;
 use rocket::serde::json::Json;
@@ -9,20 +15,158 @@ use rocket::State;
 use rocket::{delete, get, post, put};
 use serde_json::json;
 
-async fn validate_and_get_subscription(
+pub(crate) async fn require_role(
     db: &State<Database>,
     user_id: &str,
+    org_id: &OrgId,
+    role: Role,
+) -> Result<Role, ApiError> {
+    /*
+        Checks that a user has at least `role` in an organization.
+
+        Returns:
+            Role: The user's role. NotFound if they aren't a member, Forbidden if the role is lower.
+    */
+
+    match db.member_role(user_id, org_id).await? {
+        Some(granted) if granted >= role => Ok(granted),
+        Some(_) => Err(ApiError::Forbidden),
+        None => Err(ApiError::NotFound),
+    }
+}
+
+pub(crate) async fn workspace(
+    db: &State<Database>,
+    user_id: &str,
+    org: Option<&str>,
+    role: Role,
+) -> Result<Owner, ApiError> {
+    /*
+        Picks whose codes a collection route works on: the user's own, or with `?org=` those of
+        an organization the user has at least `role` in.
+
+        Returns:
+            Owner: The user or the organization. BadRequest for a malformed `org`.
+    */
+
+    match org {
+        Some(org) => {
+            let org_id = OrgId::parse(org).ok_or(ApiError::BadRequest)?;
+
+            require_role(db, user_id, &org_id, role).await?;
+
+            Ok(Owner::Organization(org_id))
+        }
+        None => Ok(Owner::User(user_id.to_string())),
+    }
+}
+
+pub(crate) async fn reach_code(
+    db: &State<Database>,
+    caller: &Caller,
+    user_id: &str,
+    qrcode_id: &QrCodeId,
+) -> Result<(), ApiError> {
+    /*
+        Checks that an API key reaches a code, since keys only work on the codes of the user or
+        organization they were made for. Signed-in users are checked by the route's own lookup.
+
+        Returns:
+            (): NotFound if the code belongs to another workspace.
+    */
+
+    let Some(key) = &caller.key else {
+        return Ok(());
+    };
+
+    if db
+        .dynamic_url_owner(user_id, qrcode_id, Role::Viewer)
+        .await?
+        != key.owner
+    {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(())
+}
+
+pub(crate) fn reach_account(caller: &Caller, user_id: &str) -> Result<(), ApiError> {
+    /*
+        Checks that an API key reaches the user's own account, for routes like folders and
+        transfers that only exist on personal accounts.
+
+        Returns:
+            (): Forbidden for an organization key.
+    */
+
+    if !caller.reaches(&Owner::User(user_id.to_string())) {
+        return Err(ApiError::Forbidden);
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn validate_and_get_subscription(
+    db: &State<Database>,
+    owner: &Owner,
 ) -> Result<models::UserSubscriptionResult, ApiError> {
-    let subscription = db.get_subscription(&user_id).await?;
+    let subscription = db.get_subscription(owner).await?;
 
     // Check if the subscription is valid (you could check subscription status or expiration here)
-    if !db.validate_subscription_status(user_id).await? {
+    if !db.validate_subscription_status(owner).await? {
         return Err(ApiError::Unauthorized);
     }
 
     Ok(subscription)
 }
 
+pub(crate) async fn available_usage(
+    db: &State<Database>,
+    owner: &Owner,
+) -> Result<(SubscriptionTier, i32), ApiError> {
+    /*
+        Works out how many more codes fit in the owner's tier.
+
+        Returns:
+            (SubscriptionTier, i32): The owner's tier and the room left in it. Tiers that don't
+            count paused codes leave them out of the usage.
+    */
+
+    let subscription = validate_and_get_subscription(db, owner).await?;
+
+    let tier = SubscriptionTier::from_str(&subscription.tier)
+        .ok_or_else(|| ApiError::InternalServerError("Invalid subscription tier".to_string()))?;
+
+    let mut usage = subscription.usage;
+
+    if !tier.counts_paused() {
+        usage -= db.count_paused_urls(owner).await?;
+    }
+
+    let available = tier.max_usage() - usage;
+
+    Ok((tier, available))
+}
+
+pub(crate) async fn available_storage(
+    db: &State<Database>,
+    owner: &Owner,
+) -> Result<i64, ApiError> {
+    /*
+        Works out how many more bytes of files fit in the owner's tier.
+
+        Returns:
+            i64: The room left for files, in bytes.
+    */
+
+    let subscription = validate_and_get_subscription(db, owner).await?;
+
+    let tier = SubscriptionTier::from_str(&subscription.tier)
+        .ok_or_else(|| ApiError::InternalServerError("Invalid subscription tier".to_string()))?;
+
+    Ok(tier.max_storage() - db.storage_usage(owner).await?)
+}
+
 #[post("/user", format = "json", data = "<user>")]
 pub async fn create_user(
     token: Claims,
@@ -58,25 +202,40 @@ pub async fn create_user(
 
 #[get("/user/<user_id>")]
 pub async fn get_user_details(
-    token: Claims,
+    caller: Caller,
     db: &State<Database>,
     user_id: &str,
 ) -> Response<Json<ApiResponse>> {
-    if user_id != format_user_id(token.sub) {
-        return Err(ApiError::Unauthorized);
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+
+    let owner = Owner::User(user_id.to_string());
+
+    // Organization keys don't see the account of the member who made them.
+    if !caller.reaches(&owner) {
+        return Err(ApiError::Forbidden);
     }
 
-    let user = match db.select_user(&user_id).await? {
+    let user = match db.select_user(user_id).await? {
         Some(user) => user,
         None => return Err(ApiError::NotFound),
     };
 
-    let subscription = match db.get_subscription(&user_id).await {
-        Ok(subscription) => Some(subscription),
-        _ => None,
+    let subscription = db.get_subscription(&owner).await.ok();
+
+    // Storage sits next to the code usage, with the limit of the user's tier if they have one.
+    let storage = StorageUsage {
+        used: db.storage_usage(&owner).await?,
+        limit: subscription
+            .as_ref()
+            .and_then(|subscription| SubscriptionTier::from_str(&subscription.tier))
+            .map(|tier| tier.max_storage()),
     };
 
-    let details = UserDetails { user, subscription };
+    let details = UserDetails {
+        user,
+        subscription,
+        storage,
+    };
 
     Ok(Json(ApiResponse {
         status: Status::Ok.code,
@@ -85,107 +244,172 @@ pub async fn get_user_details(
     }))
 }
 
-#[post("/user/<user_id>/qrcode", format = "json", data = "<qrcode>")]
+#[post("/user/<user_id>/qrcode?<org>", format = "json", data = "<qrcode>")]
 pub async fn create_qrcodes(
-    token: Claims,
+    caller: Caller,
     db: &State<Database>,
     user_id: &str,
+    org: Option<&str>,
     qrcode: Json<models::DynamicQr>,
 ) -> Response<Json<ApiResponse>> {
     /*
-           Creates a dynamic URL for a user.
+           Creates a dynamic URL for a user, or for an organization they can edit in.
 
            Params:
                user_id: the user's Auth0 ID.
-               qrcode: the dynamic URL object containing the target URL.
+               org: optional organization ID, the code then belongs to and counts against the organization.
+               qrcode: the dynamic URL object containing the target URL and optional name, description, tags and color.
 
            Returns:
                Response<Json<ApiResponse>>: the created dynamic URL object as a json response.
 
     */
 
-    if user_id != format_user_id(token.sub) {
-        return Err(ApiError::Unauthorized);
-    }
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
 
-    // Validate the user's subscription and get the subscription details
+    let org = caller.workspace(org)?;
+    let owner = workspace(db, user_id, org.as_deref(), Role::Editor).await?;
 
-    match validate_and_get_subscription(&db, &user_id).await {
-        Ok(subscription) => {
-            // Check if the usage is within allowed limits for the tier
+    // The insert checks the tier's limit and counts the code in one transaction.
+    let (tier, _available) = available_usage(db, &owner).await?;
 
-            let tier = SubscriptionTier::from_str(&subscription.tier).ok_or_else(|| {
-                ApiError::InternalServerError("Invalid subscription tier".to_string())
-            })?;
+    let mut qrcode = qrcode.into_inner();
 
-            if subscription.usage >= tier.max_usage() {
-                return Err(ApiError::InternalServerError(
-                    "Usage limit reached".to_string(),
-                ));
-            }
-            // Create the dynamic URL
-            let created = db.insert_dynamic_url(&user_id, qrcode.into_inner()).await?;
+    if !qrcode.normalize() {
+        return Err(ApiError::BadRequest);
+    }
 
-            // Increment usage after successful creation
-            db.increment_usage(&user_id).await?;
+    let created = db
+        .insert_dynamic_url(user_id, &owner, qrcode, &tier)
+        .await?;
 
-            // Return a success response
-            Ok(Json(ApiResponse {
-                status: Status::Created.code,
-                message: "Dynamic URL created".to_string(),
-                data: json!({"created": created}),
-            }))
-        }
-        Err(error) => Err(error), // Handle errors from subscription logic
-    }
+    // Return a success response
+    Ok(Json(ApiResponse {
+        status: Status::Created.code,
+        message: "Dynamic URL created".to_string(),
+        data: json!({"created": created}),
+    }))
 }
 
-#[get("/user/<user_id>/qrcode")]
+#[get("/user/<user_id>/qrcode?<org>&<query..>")]
 pub async fn read_qrcodes(
-    token: Claims,
+    caller: Caller,
     user_id: &str,
+    org: Option<&str>,
+    query: models::QrListQuery,
     db: &State<Database>,
 ) -> Response<Json<ApiResponse>> {
     /*
-              Lists all dynamic URLs created by a user.
+              Lists a page of the dynamic URLs created by a user, or by an organization they are a member of.
 
               Params:
                 user_id: the user's Auth0 ID.
+                org: optional organization ID to list the organization's codes.
+                query: optional `cursor`, `limit`, `sort` (created_at, updated_at, access_count, last_accessed),
+                       `order` (asc, desc) and filters `domain`, `status`, `name`, `tag` (repeatable), `color`,
+                       `folder` (a folder ID), `created_after`, `created_before`,
+                       `updated_after` and `updated_before` (RFC 3339 datetimes).
 
               Returns:
-                Response<Json<ApiResponse>>: the list of dynamic URLs as a json response.
+                Response<Json<ApiResponse>>: the page of dynamic URLs, the total matching the filters and the next cursor.
     */
 
-    if user_id != format_user_id(token.sub) {
-        return Err(ApiError::Unauthorized);
-    }
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
 
-    match validate_and_get_subscription(&db, &user_id).await {
+    let org = caller.workspace(org)?;
+    let owner = workspace(db, user_id, org.as_deref(), Role::Viewer).await?;
+
+    match validate_and_get_subscription(db, &owner).await {
         Ok(_subscription) => {
-            // Create the dynamic URL
-            let urls = db.list_user_urls(&user_id).await?;
+            let page = db.list_user_urls(&owner, &query).await?;
 
             // Return a success response
             Ok(Json(ApiResponse {
-                status: Status::Created.code,
+                status: Status::Ok.code,
                 message: "Dynamic Urls".to_string(),
-                data: json!({"urls": urls}),
+                data: json!(page),
             }))
         }
         Err(error) => Err(error), // Handle errors from subscription logic
     }
 }
 
+#[get("/user/<user_id>/qrcode/search?<org>&<query..>")]
+pub async fn search_qrcodes(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    org: Option<&str>,
+    query: models::QrSearchQuery,
+) -> Response<Json<ApiResponse>> {
+    /*
+           Searches a user's dynamic URLs, or an organization's, by name, description, tags and target URL.
+
+           Params:
+               user_id: the user's Auth0 ID.
+               org: optional organization ID to search the organization's codes.
+               query: `q`, the words to search for, and an optional `limit` (1 to 50, default 20).
+
+           Returns:
+               Response<Json<ApiResponse>>: the matching dynamic URLs ranked by relevance, with the matches highlighted.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+
+    let org = caller.workspace(org)?;
+    let owner = workspace(db, user_id, org.as_deref(), Role::Viewer).await?;
+
+    let results = db.search_user_urls(&owner, &query).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Search results".to_string(),
+        data: json!({"results": results}),
+    }))
+}
+
+#[get("/user/<user_id>/qrcode/<qrcode_id>")]
+pub async fn read_qrcode(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+) -> Response<Json<ApiResponse>> {
+    /*
+           Reads a single dynamic URL of a user.
+
+           Params:
+               user_id: the user's Auth0 ID.
+               qrcode_id: the dynamic URL ID.
+
+           Returns:
+               Response<Json<ApiResponse>>: the dynamic URL object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let qrcode = db.select_dynamic_url(user_id, &qrcode_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Dynamic URL".to_string(),
+        data: json!({"qrcode": qrcode}),
+    }))
+}
+
 #[put(
     "/user/<user_id>/qrcode/<qrcode_id>",
     format = "json",
     data = "<qrcode>"
 )]
 pub async fn update_qrcodes(
-    token: Claims,
+    caller: Caller,
     db: &State<Database>,
     user_id: &str,
-    qrcode_id: &str,
+    qrcode_id: QrCodeId,
     qrcode: Json<models::DynamicQr>,
 ) -> Response<Json<ApiResponse>> {
     /*
@@ -194,20 +418,30 @@ pub async fn update_qrcodes(
            Params:
                user_id: the user's Auth0 ID.
                qrcode_id: the dynamic URL ID.
-               qrcode: the dynamic URL object containing the target URL.
+               qrcode: the dynamic URL object containing the target URL, redirect code, name, description, tags and color.
 
            Returns:
                Response<Json<ApiResponse>>: the updated dynamic URL object as a json response.
 
     */
 
-    if user_id != format_user_id(token.sub) {
-        return Err(ApiError::Unauthorized);
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let mut qrcode = qrcode.into_inner();
+
+    // A code keeps the type it was created with, so the update is checked against that type
+    // rather than the one in the body.
+    qrcode.target_type = db
+        .select_dynamic_url(user_id, &qrcode_id)
+        .await?
+        .target_type();
+
+    if !qrcode.normalize() {
+        return Err(ApiError::BadRequest);
     }
 
-    let updated = db
-        .update_dynamic_url(&qrcode_id, &qrcode.target_url)
-        .await?;
+    let updated = db.update_dynamic_url(user_id, &qrcode_id, &qrcode).await?;
 
     Ok(Json(ApiResponse {
         status: Status::Ok.code,
@@ -218,40 +452,237 @@ pub async fn update_qrcodes(
 
 #[delete("/user/<user_id>/qrcode/<qrcode_id>")]
 pub async fn delete_qrcodes(
-    token: Claims,
+    caller: Caller,
     db: &State<Database>,
     user_id: &str,
-    qrcode_id: &str,
+    qrcode_id: QrCodeId,
 ) -> Response<Json<ApiResponse>> {
     /*
-           Deletes a dynamic URL for a user.
+           Deletes a dynamic URL for a user by moving it to the trash, where it can be restored
+           until it is purged.
 
            Params:
                user_id: the user's Auth0 ID.
                qrcode_id: the dynamic URL ID.
 
            Returns:
-               Response<Json<ApiResponse>>: the deleted dynamic URL object as a json response.
+               Response<Json<ApiResponse>>: whether the dynamic URL was deleted, as a json response.
     */
 
-    if user_id != format_user_id(token.sub) {
-        return Err(ApiError::Unauthorized);
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+
+    let owner = db
+        .dynamic_url_owner(user_id, &qrcode_id, Role::Editor)
+        .await?;
+
+    if !caller.reaches(&owner) {
+        return Err(ApiError::NotFound);
     }
 
-    match validate_and_get_subscription(&db, &user_id).await {
+    match validate_and_get_subscription(db, &owner).await {
         Ok(_subscription) => {
-            // Create the dynamic URL
-            let deleted = db.delete_dynamic_url(&qrcode_id).await?;
-
-            db.decrement_usage(user_id).await?;
+            // Trash the dynamic URL, its usage is given back in the same transaction
+            let deleted = db.delete_dynamic_url(user_id, &qrcode_id).await?;
 
             // Return a success response
             Ok(Json(ApiResponse {
-                status: Status::Created.code,
-                message: "Dynamic Urls".to_string(),
+                status: Status::Ok.code,
+                message: "Dynamic URL moved to trash".to_string(),
                 data: json!({"deleted": deleted}),
             }))
         }
         Err(error) => Err(error), // Handle errors from subscription logic
     }
 }
+
+#[get("/user/<user_id>/qrcode/<qrcode_id>/history")]
+pub async fn read_qrcode_history(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+) -> Response<Json<ApiResponse>> {
+    /*
+           Reads the target URL history of a dynamic URL, newest first.
+
+           Params:
+               user_id: the user's Auth0 ID.
+               qrcode_id: the dynamic URL ID.
+
+           Returns:
+               Response<Json<ApiResponse>>: the revisions, each with the scans made while it was live, as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::AnalyticsRead)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let revisions = db.list_revisions(user_id, &qrcode_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Dynamic URL history".to_string(),
+        data: json!({"revisions": revisions}),
+    }))
+}
+
+#[post("/user/<user_id>/qrcode/<qrcode_id>/rollback/<revision_id>")]
+pub async fn rollback_qrcode(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+    revision_id: RevisionId,
+) -> Response<Json<ApiResponse>> {
+    /*
+           Restores the target URL of an earlier revision of a dynamic URL.
+
+           Params:
+               user_id: the user's Auth0 ID.
+               qrcode_id: the dynamic URL ID.
+               revision_id: the revision to restore, from the history.
+
+           Returns:
+               Response<Json<ApiResponse>>: the updated dynamic URL object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let updated = db
+        .rollback_dynamic_url(user_id, &qrcode_id, &revision_id)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Dynamic URL rolled back".to_string(),
+        data: json!({"updated": updated}),
+    }))
+}
+
+#[post("/user/<user_id>/qrcode/<qrcode_id>/pause")]
+pub async fn pause_qrcode(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+) -> Response<Json<ApiResponse>> {
+    /*
+           Pauses a dynamic URL. Scans answer with a temporarily unavailable page until it is resumed.
+
+           Params:
+               user_id: the user's Auth0 ID.
+               qrcode_id: the dynamic URL ID.
+
+           Returns:
+               Response<Json<ApiResponse>>: the paused dynamic URL object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_code(db, &caller, user_id, &qrcode_id).await?;
+
+    let paused = db
+        .set_dynamic_url_status(user_id, &qrcode_id, QrStatus::Paused, None)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Dynamic URL paused".to_string(),
+        data: json!({"updated": paused}),
+    }))
+}
+
+#[post("/user/<user_id>/qrcode/<qrcode_id>/resume")]
+pub async fn resume_qrcode(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+) -> Response<Json<ApiResponse>> {
+    /*
+           Resumes a paused dynamic URL so scans redirect again.
+
+           Params:
+               user_id: the user's Auth0 ID.
+               qrcode_id: the dynamic URL ID.
+
+           Returns:
+               Response<Json<ApiResponse>>: the resumed dynamic URL object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+
+    let owner = db
+        .dynamic_url_owner(user_id, &qrcode_id, Role::Editor)
+        .await?;
+
+    if !caller.reaches(&owner) {
+        return Err(ApiError::NotFound);
+    }
+
+    // On tiers where paused codes are free, resuming one takes up room again, which the update checks.
+    let (tier, _available) = available_usage(db, &owner).await?;
+
+    let resumed = db
+        .set_dynamic_url_status(user_id, &qrcode_id, QrStatus::Active, Some(&tier))
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Dynamic URL resumed".to_string(),
+        data: json!({"updated": resumed}),
+    }))
+}
+
+#[post("/user/<user_id>/qrcode/<qrcode_id>/clone")]
+pub async fn clone_qrcode(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+) -> Response<Json<ApiResponse>> {
+    /*
+           Creates a copy of a dynamic URL with a new server URL, for the same user or organization.
+           A hosted file is copied too, so it has to fit in the storage left.
+
+           Params:
+               user_id: the user's Auth0 ID.
+               qrcode_id: the ID of the dynamic URL to copy.
+
+           Returns:
+               Response<Json<ApiResponse>>: the new dynamic URL object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+
+    // The copy belongs to the original's owner, so it counts against their limits.
+    let owner = db
+        .dynamic_url_owner(user_id, &qrcode_id, Role::Editor)
+        .await?;
+
+    if !caller.reaches(&owner) {
+        return Err(ApiError::NotFound);
+    }
+    // The tier limit itself is checked in the clone's transaction, so parallel clones can't pass it.
+    let (tier, _available) = available_usage(db, &owner).await?;
+
+    let size = db
+        .hosted_files_size(std::slice::from_ref(&qrcode_id))
+        .await?;
+
+    if size > 0 && size > available_storage(db, &owner).await? {
+        return Err(ApiError::BadRequest);
+    }
+
+    let cloned = db.clone_dynamic_url(user_id, &qrcode_id, &tier).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Created.code,
+        message: "Dynamic URL cloned".to_string(),
+        data: json!({"created": cloned}),
+    }))
+}
@@ -0,0 +1,166 @@
+use crate::database::database::Database;
+use crate::scan::bot::ClientKind;
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use surrealdb::sql::Datetime;
+use tokio::sync::Notify;
+
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+// Flush early once this many scans are waiting, e.g. during a traffic spike.
+pub const FLUSH_THRESHOLD: usize = 1_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanCount {
+    pub server_url: String,
+    pub humans: i64,
+    pub bots: i64,
+    pub last_accessed: Option<Datetime>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanEvent {
+    pub server_url: String,
+    pub client: ClientKind,
+    pub scanned_at: Datetime,
+}
+
+#[derive(Debug, Default)]
+pub struct ScanBatch {
+    pub counts: Vec<ScanCount>,
+    pub events: Vec<ScanEvent>,
+}
+
+#[derive(Default)]
+struct PendingScans {
+    counts: HashMap<String, ScanCount>,
+    events: Vec<ScanEvent>,
+}
+
+impl PendingScans {
+    fn add(&mut self, server_url: &str, client: ClientKind, scanned_at: Datetime) {
+        let count = self
+            .counts
+            .entry(server_url.to_string())
+            .or_insert_with(|| ScanCount {
+                server_url: server_url.to_string(),
+                humans: 0,
+                bots: 0,
+                last_accessed: None,
+            });
+
+        match client {
+            ClientKind::Human => {
+                count.humans += 1;
+                count.last_accessed = Some(scanned_at.clone());
+            }
+            ClientKind::Bot => count.bots += 1,
+        }
+
+        self.events.push(ScanEvent {
+            server_url: server_url.to_string(),
+            client,
+            scanned_at,
+        });
+    }
+}
+
+pub struct ScanBuffer {
+    /*
+        Accumulates scan counters and events in memory so they can be written to the
+        database in batches instead of one UPDATE per scan.
+    */
+    pending: Mutex<PendingScans>,
+    threshold: usize,
+    full: Notify,
+}
+
+impl ScanBuffer {
+    pub fn new(threshold: usize) -> Self {
+        ScanBuffer {
+            pending: Mutex::new(PendingScans::default()),
+            threshold,
+            full: Notify::new(),
+        }
+    }
+
+    pub fn push(&self, server_url: &str, client: ClientKind) {
+        let mut pending = self.pending.lock().unwrap();
+
+        pending.add(server_url, client, Datetime::default());
+
+        if pending.events.len() >= self.threshold {
+            self.full.notify_one();
+        }
+    }
+
+    pub fn drain(&self) -> ScanBatch {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        ScanBatch {
+            counts: pending.counts.into_values().collect(),
+            events: pending.events,
+        }
+    }
+
+    pub fn restore(&self, batch: ScanBatch) {
+        // Puts back a batch that failed to flush so no scans are lost.
+        let mut pending = self.pending.lock().unwrap();
+
+        for count in batch.counts {
+            let merged = pending
+                .counts
+                .entry(count.server_url.clone())
+                .or_insert_with(|| ScanCount {
+                    humans: 0,
+                    bots: 0,
+                    ..count.clone()
+                });
+
+            merged.humans += count.humans;
+            merged.bots += count.bots;
+
+            if merged.last_accessed.is_none() {
+                merged.last_accessed = count.last_accessed;
+            }
+        }
+
+        let newer = std::mem::replace(&mut pending.events, batch.events);
+        pending.events.extend(newer);
+    }
+
+    pub async fn filled(&self) {
+        self.full.notified().await
+    }
+}
+
+impl Default for ScanBuffer {
+    fn default() -> Self {
+        ScanBuffer::new(FLUSH_THRESHOLD)
+    }
+}
+
+pub fn spawn_flusher(db: Database) {
+    /*
+        Periodically writes buffered scans to the database, or sooner once the buffer
+        reaches its threshold. The final flush on shutdown happens in a Rocket fairing.
+    */
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = db.scans_filled() => {}
+            }
+
+            if let Err(error) = db.flush_scans().await {
+                eprintln!("Failed to flush scans: {:?}", error);
+            }
+        }
+    });
+}
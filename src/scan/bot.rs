@@ -0,0 +1,153 @@
+use rocket::request::{FromRequest, Outcome};
+use serde::{Deserialize, Serialize};
+
+// Lowercase fragments of User-Agents sent by crawlers, link-preview fetchers,
+// security scanners and HTTP libraries. Matched as substrings, so keep
+// entries specific enough not to catch real mobile browsers.
+pub const BOT_SIGNATURES: &[&str] = &[
+    // Search engines and generic crawlers
+    "bot",
+    "crawler",
+    "spider",
+    "slurp",
+    "ia_archiver",
+    "yandex",
+    "baiduspider",
+    "bingpreview",
+    // Messaging and social link previews
+    "facebookexternalhit",
+    "facebookcatalog",
+    "whatsapp",
+    "telegram",
+    "slack-imgproxy",
+    "skypeuripreview",
+    "viber",
+    "vkshare",
+    "pinterest",
+    "embedly",
+    "iframely",
+    "mastodon",
+    "google-pagerenderer",
+    "google-read-aloud",
+    "feedfetcher",
+    // Mail and URL security scanners
+    "proofpoint",
+    "mimecast",
+    "barracuda",
+    "urlscan",
+    "virustotal",
+    "safe browsing",
+    "google-safety",
+    // Headless browsers and HTTP libraries
+    "headlesschrome",
+    "phantomjs",
+    "lighthouse",
+    "curl/",
+    "wget/",
+    "httpie/",
+    "python-requests",
+    "python-urllib",
+    "aiohttp",
+    "go-http-client",
+    "java/",
+    "okhttp",
+    "axios/",
+    "node-fetch",
+    "undici",
+    "libwww-perl",
+    "apache-httpclient",
+    "postmanruntime",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientKind {
+    Human,
+    Bot,
+}
+
+// The request headers that decide whether a scan came from a person.
+#[derive(Debug, Default)]
+pub struct ScanHeaders<'a> {
+    pub user_agent: Option<&'a str>,
+    pub accept: Option<&'a str>,
+    pub purpose: Option<&'a str>,
+    pub sec_purpose: Option<&'a str>,
+    pub x_purpose: Option<&'a str>,
+    pub x_moz: Option<&'a str>,
+}
+
+pub fn classify(headers: &ScanHeaders<'_>) -> ClientKind {
+    /*
+       Classifies a scan request as a human or a bot.
+
+       A request is a bot when it is a browser prefetch or preview, has no
+       User-Agent, matches a known bot signature, or lacks the Accept header
+       every browser sends on navigation.
+
+       Params:
+           headers (ScanHeaders): The relevant headers of the scan request.
+
+       Returns:
+           ClientKind: Whether the request should count as a human scan.
+    */
+
+    let is_prefetch = |value: Option<&str>| {
+        value.is_some_and(|value| {
+            let value = value.to_ascii_lowercase();
+            value.contains("prefetch") || value.contains("preview") || value.contains("prerender")
+        })
+    };
+
+    if is_prefetch(headers.purpose)
+        || is_prefetch(headers.sec_purpose)
+        || is_prefetch(headers.x_purpose)
+        || is_prefetch(headers.x_moz)
+    {
+        return ClientKind::Bot;
+    }
+
+    let user_agent = match headers.user_agent.map(str::trim) {
+        Some(user_agent) if !user_agent.is_empty() => user_agent.to_ascii_lowercase(),
+        _ => return ClientKind::Bot,
+    };
+
+    if BOT_SIGNATURES
+        .iter()
+        .any(|signature| user_agent.contains(signature))
+    {
+        return ClientKind::Bot;
+    }
+
+    if headers.accept.is_none() {
+        return ClientKind::Bot;
+    }
+
+    ClientKind::Human
+}
+
+pub struct ScanClient {
+    pub kind: ClientKind,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ScanClient {
+    type Error = ();
+
+    async fn from_request(request: &'r rocket::Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+
+        let scan_headers = ScanHeaders {
+            user_agent: headers.get_one("User-Agent"),
+            accept: headers.get_one("Accept"),
+            purpose: headers.get_one("Purpose"),
+            sec_purpose: headers.get_one("Sec-Purpose"),
+            x_purpose: headers.get_one("X-Purpose"),
+            x_moz: headers.get_one("X-Moz"),
+        };
+
+        Outcome::Success(ScanClient {
+            kind: classify(&scan_headers),
+        })
+    }
+}
@@ -0,0 +1,120 @@
+use crate::database::models::LinkResult;
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+// Other instances can't invalidate this cache, so the TTL bounds how long they
+// may keep serving a target that was changed elsewhere.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+struct CacheEntry {
+    link: LinkResult,
+    inserted_at: Instant,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+pub struct ScanCache {
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    capacity: usize,
+    ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ScanCache {
+    /*
+        In-process cache of `server_url` -> resolved redirect, so popular codes can be
+        redirected without a database round-trip.
+    */
+
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        ScanCache {
+            entries: Mutex::new(HashMap::new()),
+            capacity,
+            ttl,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, server_url: &str) -> Option<LinkResult> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let link = match entries.get(server_url) {
+            Some(entry) if entry.inserted_at.elapsed() < self.ttl => Some(entry.link.clone()),
+            Some(_) => {
+                entries.remove(server_url);
+                None
+            }
+            None => None,
+        };
+
+        match link {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+
+        link
+    }
+
+    pub fn insert(&self, server_url: &str, link: LinkResult) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= self.capacity && !entries.contains_key(server_url) {
+            // Drop expired entries first, then the oldest one if the cache is still full.
+            entries.retain(|_, entry| entry.inserted_at.elapsed() < self.ttl);
+
+            if entries.len() >= self.capacity {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.inserted_at)
+                    .map(|(key, _)| key.clone());
+
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        entries.insert(
+            server_url.to_string(),
+            CacheEntry {
+                link,
+                inserted_at: Instant::now(),
+            },
+        );
+    }
+
+    pub fn invalidate(&self, server_url: &str) {
+        self.entries.lock().unwrap().remove(server_url);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+}
+
+impl Default for ScanCache {
+    fn default() -> Self {
+        ScanCache::new(DEFAULT_CAPACITY, DEFAULT_TTL)
+    }
+}
@@ -0,0 +1,4 @@
+pub mod batch;
+pub mod bot;
+pub mod cache;
+pub mod page;
@@ -0,0 +1,87 @@
+use crate::database::models::QrColor;
+
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder};
+use std::io::Cursor;
+
+const DEFAULT_ACCENT: &str = "#222222";
+
+// A small standalone HTML page answered in place of a redirect.
+pub struct StatusPage {
+    status: Status,
+    title: String,
+    message: String,
+    accent: &'static str,
+}
+
+impl StatusPage {
+    pub fn deactivated() -> Self {
+        StatusPage {
+            status: Status::Gone,
+            title: "QR code deactivated".to_string(),
+            message: "The owner of this QR code has deactivated it.".to_string(),
+            accent: DEFAULT_ACCENT,
+        }
+    }
+
+    pub fn paused(name: Option<&str>, color: Option<QrColor>) -> Self {
+        // Uses the code's own name and color label, so visitors recognise what they scanned.
+        let subject = name.unwrap_or("This QR code");
+
+        StatusPage {
+            status: Status::ServiceUnavailable,
+            title: "Temporarily unavailable".to_string(),
+            message: format!("{} is paused right now. Please check back later.", subject),
+            accent: color.map_or(DEFAULT_ACCENT, |color| color.hex()),
+        }
+    }
+
+    pub fn html(&self) -> String {
+        format!(
+            "<!DOCTYPE html>\
+            <html lang=\"en\">\
+            <head>\
+            <meta charset=\"utf-8\">\
+            <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+            <meta name=\"robots\" content=\"noindex\">\
+            <title>{title}</title>\
+            <style>body{{font-family:system-ui,sans-serif;display:flex;min-height:100vh;margin:0;align-items:center;justify-content:center;text-align:center;color:#222;border-top:6px solid {accent}}}main{{padding:24px;max-width:420px}}h1{{color:{accent}}}</style>\
+            </head>\
+            <body><main><h1>{title}</h1><p>{message}</p></main></body>\
+            </html>",
+            title = escape_html(&self.title),
+            message = escape_html(&self.message),
+            accent = self.accent,
+        )
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+impl<'r> Responder<'r, 'static> for StatusPage {
+    fn respond_to(self, _request: &'r rocket::Request<'_>) -> response::Result<'static> {
+        let body = self.html();
+
+        rocket::Response::build()
+            .status(self.status)
+            .header(ContentType::HTML)
+            .header(Header::new("Cache-Control", "no-store"))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
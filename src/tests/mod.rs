@@ -1,7 +1,59 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::database::models::format_user_id;
-    use crate::utils::{cleanse_jwk, decode_jwt, pad_base64_url}; // Ensure correct module path
+    use crate::apikey::key::{generate_key, hash_key, visible_prefix, API_KEY_PREFIX};
+    use crate::apikey::models::{ApiScope, NewApiKey, MAX_KEY_EXPIRY_DAYS};
+    use crate::applink::models::{valid_deep_link, AppLaunch, AppLink};
+    use crate::applink::platform::{detect, Platform};
+    use crate::applink::render::LaunchPage;
+    use crate::bulk::import::parse_csv;
+    use crate::bulk::models::{BulkOperation, BulkTarget, NewQrCode, RowStatus};
+    use crate::bulk::update::{normalize, parse_ids, plan, replace_domain};
+    use crate::contact::models::ContactCard;
+    use crate::contact::render::ContactView;
+    use crate::contact::vcard::{file_name, tel_uri, vcard};
+    use crate::database::database::Database;
+    use crate::database::models::{
+        format_user_id, search_terms, DynamicQr, FolderId, LinkResult, Owner, QrCodeId, QrColor,
+        QrCursor, QrListQuery, QrSearchQuery, QrSlug, QrStatus, QrTarget, RedirectCode, RevisionId,
+        SubscriptionTier, User, UserSubscription,
+    };
+    use crate::errors::ApiError;
+    use crate::folder::models::{Folder, FolderDeleteMode, FolderResult};
+    use crate::hosted::models::{HostedFile, MIB};
+    use crate::hosted::storage::{sniff, FileStore};
+    use crate::hosted::stream::{parse_range, ByteRange};
+    use crate::invitation::mailer::{HttpMailer, Mail, Mailer};
+    use crate::invitation::models::InvitationStatus;
+    use crate::invitation::token::{generate_token, hash_token};
+    use crate::landing::models::{LandingPage, PageLink};
+    use crate::landing::render::PageView;
+    use crate::organization::models::{NewMember, Organization, Role};
+    use crate::routes::guard::Caller;
+    use crate::routes::qrcode::redirect_location;
+    use crate::scan::batch::ScanBuffer;
+    use crate::scan::bot::{classify, ClientKind, ScanHeaders};
+    use crate::scan::cache::ScanCache;
+    use crate::scan::page::StatusPage;
+    use crate::transfer::models::{TransferRequest, TransferStatus};
+    use crate::utils::pad_base64_url; // Ensure correct module path
+    use crate::utils::Environments;
+    use crate::webhook::delivery::{
+        backoff, deliver, delivery_client, public_address, public_endpoint, sign, DeliveryOutcome,
+    };
+    use crate::webhook::models::{DueDelivery, Webhook, WebhookMode};
+
+    use rocket::form::Form;
+    use rocket::fs::TempFile;
+    use rocket::http::{Header, Status};
+    use rocket::local::asynchronous::Client;
+    use rocket::request::FromParam;
+    use serde_json::json;
+    use std::time::Duration;
+    use surrealdb::RecordId;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
     #[test]
     fn test_pad_base_url() {
         // Test case for one extra padding
@@ -24,4 +76,3246 @@ mod tests {
             "google_oauth2_103365148753481340229"
         )
     }
+
+    #[test]
+    fn test_redirect_code() {
+        assert_eq!(RedirectCode::default(), RedirectCode::Found);
+        assert_eq!(RedirectCode::try_from(307), Ok(RedirectCode::Temporary));
+        assert_eq!(u16::from(RedirectCode::Permanent), 308);
+        assert!(RedirectCode::try_from(303).is_err());
+    }
+
+    #[test]
+    fn test_redirect_location() {
+        assert_eq!(redirect_location("example.com"), "http://example.com");
+        assert_eq!(
+            redirect_location("https://example.com"),
+            "https://example.com"
+        );
+        assert_eq!(
+            redirect_location("HTTP://example.com"),
+            "HTTP://example.com"
+        );
+    }
+
+    #[test]
+    fn test_classify_scan_client() {
+        let browser = ScanHeaders {
+            user_agent: Some("Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1"),
+            accept: Some("text/html"),
+            ..Default::default()
+        };
+        assert_eq!(classify(&browser), ClientKind::Human);
+
+        // Link previews from messaging apps
+        let preview = ScanHeaders {
+            user_agent: Some("WhatsApp/2.23.20.0 A"),
+            accept: Some("*/*"),
+            ..Default::default()
+        };
+        assert_eq!(classify(&preview), ClientKind::Bot);
+
+        // Browser prefetches still look like a real browser
+        let prefetch = ScanHeaders {
+            sec_purpose: Some("prefetch;prerender"),
+            ..browser
+        };
+        assert_eq!(classify(&prefetch), ClientKind::Bot);
+
+        // No User-Agent at all
+        let anonymous = ScanHeaders {
+            accept: Some("*/*"),
+            ..Default::default()
+        };
+        assert_eq!(classify(&anonymous), ClientKind::Bot);
+    }
+
+    fn link(target_url: &str) -> LinkResult {
+        LinkResult {
+            target_url: target_url.to_string(),
+            redirect_code: RedirectCode::Found,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_scan_cache() {
+        let cache = ScanCache::new(2, Duration::from_secs(60));
+
+        assert!(cache.get("a").is_none());
+        cache.insert("a", link("https://a.example"));
+        assert_eq!(cache.get("a").unwrap().target_url, "https://a.example");
+
+        // Invalidated entries must go back to the database
+        cache.invalidate("a");
+        assert!(cache.get("a").is_none());
+
+        // Inserting past capacity evicts the oldest entry
+        cache.insert("a", link("https://a.example"));
+        cache.insert("b", link("https://b.example"));
+        cache.insert("c", link("https://c.example"));
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("c").is_some());
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 3, 2));
+    }
+
+    #[test]
+    fn test_scan_cache_expiry() {
+        let cache = ScanCache::new(10, Duration::ZERO);
+
+        cache.insert("a", link("https://a.example"));
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn test_scan_buffer() {
+        let buffer = ScanBuffer::new(100);
+
+        buffer.push("a", ClientKind::Human);
+        buffer.push("a", ClientKind::Human);
+        buffer.push("a", ClientKind::Bot);
+        buffer.push("b", ClientKind::Bot);
+
+        let batch = buffer.drain();
+        assert_eq!(batch.events.len(), 4);
+
+        let a = batch.counts.iter().find(|c| c.server_url == "a").unwrap();
+        assert_eq!((a.humans, a.bots), (2, 1));
+        assert!(a.last_accessed.is_some());
+
+        let b = batch.counts.iter().find(|c| c.server_url == "b").unwrap();
+        assert_eq!((b.humans, b.bots), (0, 1));
+        assert!(b.last_accessed.is_none());
+
+        assert!(buffer.drain().events.is_empty());
+
+        // A failed flush is merged back with scans that arrived in the meantime
+        buffer.push("a", ClientKind::Human);
+        buffer.restore(batch);
+
+        let retried = buffer.drain();
+        assert_eq!(retried.events.len(), 5);
+
+        let a = retried.counts.iter().find(|c| c.server_url == "a").unwrap();
+        assert_eq!((a.humans, a.bots), (3, 1));
+    }
+
+    #[test]
+    fn test_webhook_backoff() {
+        assert_eq!(backoff(1), Duration::from_secs(30));
+        assert_eq!(backoff(2), Duration::from_secs(60));
+        assert_eq!(backoff(4), Duration::from_secs(240));
+        assert_eq!(backoff(20), Duration::from_secs(60 * 60));
+    }
+
+    async fn webhook_stand_in(status: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        // Accepts a single request, answers with `status` and returns the raw request.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+
+            loop {
+                let read = socket.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            line.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+
+                    if body.len() >= length {
+                        break;
+                    }
+                }
+            }
+
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                status
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        (url, handle)
+    }
+
+    fn due_delivery(url: String) -> DueDelivery {
+        DueDelivery {
+            id: RecordId::from(("webhook_delivery", "test")),
+            event: "scan".to_string(),
+            payload: json!({"server_url": "abc", "client": "human"}),
+            attempts: 0,
+            url,
+            secret: "whsec_test".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_delivery_is_signed() {
+        let (url, request) = webhook_stand_in("200 OK").await;
+        let delivery = due_delivery(url);
+
+        let outcome = deliver(&reqwest::Client::new(), &delivery).await;
+        assert_eq!(outcome, DeliveryOutcome::Delivered(200));
+
+        let request = request.await.unwrap();
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        assert_eq!(body, delivery.payload.to_string());
+
+        let signature = head
+            .lines()
+            .find_map(|line| line.strip_prefix("x-qr-signature: "))
+            .unwrap();
+        let (timestamp, digest) = signature.split_once(",v1=").unwrap();
+        let timestamp = timestamp
+            .strip_prefix("t=")
+            .unwrap()
+            .parse::<u64>()
+            .unwrap();
+
+        assert_eq!(digest, sign("whsec_test", timestamp, body));
+        assert_ne!(digest, sign("whsec_other", timestamp, body));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_delivery_failure() {
+        let (url, request) = webhook_stand_in("500 Internal Server Error").await;
+
+        let outcome = deliver(&reqwest::Client::new(), &due_delivery(url)).await;
+        request.await.unwrap();
+
+        assert!(matches!(
+            outcome,
+            DeliveryOutcome::Failed {
+                status: Some(500),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_endpoints() {
+        for private in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!public_address(private.parse().unwrap()), "{}", private);
+        }
+        assert!(public_address("93.184.215.14".parse().unwrap()));
+        assert!(public_address("2606:2800:21f:cb07::1".parse().unwrap()));
+
+        assert!(public_endpoint("https://93.184.215.14/hook").await);
+        assert!(!public_endpoint("http://169.254.169.254/latest/meta-data").await);
+        assert!(!public_endpoint("http://[::1]:8000/hook").await);
+        assert!(!public_endpoint("http://localhost:8000/hook").await);
+        assert!(!public_endpoint("ftp://93.184.215.14/hook").await);
+        assert!(!public_endpoint("not a url").await);
+
+        // Redirects count as a failed attempt instead of being followed
+        let (url, request) =
+            webhook_stand_in("302 Found\r\nlocation: http://169.254.169.254/").await;
+        let outcome = deliver(&delivery_client(), &due_delivery(url)).await;
+        request.await.unwrap();
+
+        assert!(matches!(
+            outcome,
+            DeliveryOutcome::Failed {
+                status: Some(302),
+                ..
+            }
+        ));
+
+        // Hosts are resolved again when sending, so one that now points inside isn't reached
+        let (url, _request) = webhook_stand_in("200 OK").await;
+        let url = url.replace("127.0.0.1", "localhost");
+        let outcome = deliver(&delivery_client(), &due_delivery(url)).await;
+
+        assert!(matches!(
+            outcome,
+            DeliveryOutcome::Failed { status: None, .. }
+        ));
+    }
+
+    #[test]
+    fn test_qr_cursor() {
+        let cursor = QrCursor {
+            value: json!("2024-01-01T00:00:00Z"),
+            id: "01JAB3XYZ".to_string(),
+        };
+
+        assert_eq!(QrCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(QrCursor::decode("not a cursor"), None);
+    }
+
+    #[test]
+    fn test_qr_code_id() {
+        let id = QrCodeId::from_param("01JAB3XYZ0000000000000000").unwrap();
+        assert_eq!(
+            id.record(),
+            RecordId::from(("dynamic_url", "01JAB3XYZ0000000000000000"))
+        );
+        assert_eq!(json!(id), json!("01JAB3XYZ0000000000000000"));
+
+        assert!(QrCodeId::from_param("").is_err());
+        assert!(QrCodeId::from_param("dynamic_url:abc").is_err());
+        assert!(QrSlug::from_param("abc; DELETE dynamic_url").is_err());
+    }
+
+    async fn user_with_qrcode(db: &Database, name: &str) -> (String, QrCodeId) {
+        let user = db
+            .insert_user(User {
+                id: format!("auth0|{}", name),
+                username: name.to_string(),
+                email: format!("{}@example.com", name),
+            })
+            .await
+            .unwrap();
+        let user_id = user.id.key().to_string();
+
+        let created = db
+            .insert_dynamic_url(
+                &user_id,
+                &Owner::User(user_id.to_string()),
+                DynamicQr {
+                    target_url: format!("https://{}.example", name),
+                    ..Default::default()
+                },
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let qrcode_id = QrCodeId::from_param(json!(created[0])["id"].as_str().unwrap()).unwrap();
+
+        (user_id, qrcode_id)
+    }
+
+    #[tokio::test]
+    async fn test_qr_codes_are_scoped_to_owner() {
+        let db = Database::memory().await.unwrap();
+        let (alice, alice_code) = user_with_qrcode(&db, "alice").await;
+        let (bob, bob_code) = user_with_qrcode(&db, "bob").await;
+
+        let hijack = DynamicQr {
+            target_url: "https://evil.example".to_string(),
+            redirect_code: RedirectCode::Permanent,
+            ..Default::default()
+        };
+
+        // Someone else's code is indistinguishable from one that doesn't exist
+        assert!(matches!(
+            db.select_dynamic_url(&bob, &alice_code).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.update_dynamic_url(&bob, &alice_code, &hijack).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.delete_dynamic_url(&bob, &alice_code).await,
+            Err(ApiError::NotFound)
+        ));
+
+        let untouched = db.select_dynamic_url(&alice, &alice_code).await.unwrap();
+        assert_eq!(json!(untouched)["target_url"], "https://alice.example");
+        assert_eq!(json!(untouched)["redirect_code"], 302);
+
+        let query = Form::<QrListQuery>::parse("").unwrap();
+        let page = db
+            .list_user_urls(&Owner::User(bob.to_string()), &query)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(json!(page.urls[0])["id"], json!(bob_code));
+
+        // Owners keep full access to their own codes
+        assert!(db
+            .update_dynamic_url(&alice, &alice_code, &hijack)
+            .await
+            .is_ok());
+        assert!(db.delete_dynamic_url(&alice, &alice_code).await.unwrap());
+        assert!(matches!(
+            db.select_dynamic_url(&alice, &alice_code).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(db.select_dynamic_url(&bob, &bob_code).await.is_ok());
+    }
+
+    #[test]
+    fn test_qr_labels() {
+        let mut qrcode = DynamicQr {
+            target_url: "https://example.com".to_string(),
+            name: Some("  Spring flyer ".to_string()),
+            description: Some("   ".to_string()),
+            tags: vec!["Print".to_string(), "print ".to_string(), "".to_string()],
+            ..Default::default()
+        };
+
+        assert!(qrcode.normalize());
+        assert_eq!(qrcode.name.as_deref(), Some("Spring flyer"));
+        assert_eq!(qrcode.description, None);
+        assert_eq!(qrcode.tags, vec!["print"]);
+
+        qrcode.tags = (0..21).map(|tag| tag.to_string()).collect();
+        assert!(!qrcode.normalize());
+    }
+
+    #[tokio::test]
+    async fn test_filter_qr_codes_by_label() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, _) = user_with_qrcode(&db, "carol").await;
+
+        db.insert_dynamic_url(
+            &user_id,
+            &Owner::User(user_id.to_string()),
+            DynamicQr {
+                target_url: "https://shop.example".to_string(),
+                name: Some("Spring Campaign".to_string()),
+                tags: vec!["spring".to_string(), "print".to_string()],
+                color: Some(QrColor::Green),
+                ..Default::default()
+            },
+            &SubscriptionTier::Pro,
+        )
+        .await
+        .unwrap();
+
+        for (filter, total) in [
+            ("", 2),
+            ("tag=spring", 1),
+            ("tag=spring&tag=print", 1),
+            ("tag=spring&tag=web", 0),
+            ("color=green", 1),
+            ("color=red", 0),
+            ("name=CAMPAIGN", 1),
+        ] {
+            let query = Form::<QrListQuery>::parse(filter).unwrap();
+            let page = db
+                .list_user_urls(&Owner::User(user_id.to_string()), &query)
+                .await
+                .unwrap();
+
+            assert_eq!(page.total, total, "{}", filter);
+        }
+    }
+
+    fn folder(name: &str, parent: Option<&FolderId>) -> Folder {
+        Folder {
+            name: name.to_string(),
+            parent: parent.map(|parent| parent.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_folders() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, code) = user_with_qrcode(&db, "dave").await;
+        let (other_id, other_code) = user_with_qrcode(&db, "erin").await;
+
+        let campaigns = db
+            .insert_folder(&user_id, folder("Campaigns", None))
+            .await
+            .unwrap();
+        let spring = db
+            .insert_folder(&user_id, folder("Spring", Some(&campaigns.id)))
+            .await
+            .unwrap();
+
+        db.move_dynamic_url(&user_id, &code, &Some(spring.id.to_string()))
+            .await
+            .unwrap();
+
+        // Totals include the codes of subfolders
+        let totals = |folders: &[FolderResult], id: &FolderId| {
+            let folder = folders.iter().find(|folder| &folder.id == id).unwrap();
+            (folder.qrcode_count, folder.total.qrcodes)
+        };
+        let folders = db.list_folders(&user_id).await.unwrap();
+        assert_eq!(totals(&folders, &campaigns.id), (0, 1));
+        assert_eq!(totals(&folders, &spring.id), (1, 1));
+
+        let query = Form::<QrListQuery>::parse(&format!("folder={}", spring.id)).unwrap();
+        assert_eq!(
+            db.list_user_urls(&Owner::User(user_id.to_string()), &query)
+                .await
+                .unwrap()
+                .total,
+            1
+        );
+
+        // A folder can't move inside itself, nor be reached by someone else
+        assert!(matches!(
+            db.update_folder(
+                &user_id,
+                &campaigns.id,
+                folder("Campaigns", Some(&spring.id))
+            )
+            .await,
+            Err(ApiError::BadRequest)
+        ));
+        assert!(matches!(
+            db.move_dynamic_url(&other_id, &other_code, &Some(spring.id.to_string()))
+                .await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.delete_folder(&other_id, &spring.id, FolderDeleteMode::Cascade)
+                .await,
+            Err(ApiError::NotFound)
+        ));
+
+        // Deleting moves codes up to the parent
+        db.delete_folder(&user_id, &spring.id, FolderDeleteMode::MoveToParent)
+            .await
+            .unwrap();
+        let folders = db.list_folders(&user_id).await.unwrap();
+        assert_eq!(folders.len(), 1);
+        assert_eq!(totals(&folders, &campaigns.id), (1, 1));
+
+        // Cascading takes the codes with it
+        let deleted = db
+            .delete_folder(&user_id, &campaigns.id, FolderDeleteMode::Cascade)
+            .await
+            .unwrap();
+        assert_eq!((deleted.folders, deleted.qrcodes), (1, 1));
+        assert!(matches!(
+            db.select_dynamic_url(&user_id, &code).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(db.select_dynamic_url(&other_id, &other_code).await.is_ok());
+    }
+
+    #[test]
+    fn test_search_terms() {
+        assert_eq!(
+            search_terms("  shop.example/spring "),
+            Some("shop example spring".to_string())
+        );
+        assert_eq!(search_terms("..."), None);
+        assert_eq!(search_terms(&"a".repeat(101)), None);
+    }
+
+    #[tokio::test]
+    async fn test_search_qr_codes() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, _) = user_with_qrcode(&db, "frank").await;
+        let (other_id, _) = user_with_qrcode(&db, "grace").await;
+
+        for (owner, name, description, target_url) in [
+            (
+                &user_id,
+                "Spring Campaign",
+                None,
+                "https://shop.example.com/sale",
+            ),
+            (
+                &user_id,
+                "Menu",
+                Some("Restaurant menu for spring"),
+                "https://food.example/menu",
+            ),
+            (
+                &other_id,
+                "Spring Campaign",
+                None,
+                "https://grace.example/spring",
+            ),
+        ] {
+            db.insert_dynamic_url(
+                owner,
+                &Owner::User(owner.to_string()),
+                DynamicQr {
+                    target_url: target_url.to_string(),
+                    name: Some(name.to_string()),
+                    description: description.map(str::to_string),
+                    ..Default::default()
+                },
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        }
+
+        let search = |q: &str| Form::<QrSearchQuery>::parse(&format!("q={}", q)).unwrap();
+
+        // Both fields match, and other users' codes never show up
+        let results = db
+            .search_user_urls(&Owner::User(user_id.to_string()), &search("spring"))
+            .await
+            .unwrap();
+        let mut highlights: Vec<_> = results
+            .iter()
+            .map(|hit| {
+                (
+                    hit.highlights.name.as_deref(),
+                    hit.highlights.description.as_deref(),
+                )
+            })
+            .collect();
+        highlights.sort();
+        assert_eq!(
+            highlights,
+            vec![
+                (None, Some("Restaurant menu for <mark>spring</mark>")),
+                (Some("<mark>Spring</mark> Campaign"), None),
+            ]
+        );
+
+        // Partial words and URLs
+        let results = db
+            .search_user_urls(&Owner::User(user_id.to_string()), &search("camp"))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        let results = db
+            .search_user_urls(&Owner::User(user_id.to_string()), &search("shop.exa"))
+            .await
+            .unwrap();
+        assert_eq!(json!(results[0].qrcode)["name"], "Spring Campaign");
+
+        assert!(matches!(
+            db.search_user_urls(&Owner::User(user_id.to_string()), &search("--"))
+                .await,
+            Err(ApiError::BadRequest)
+        ));
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let rows = parse_csv(
+            "target_url,name,tags,slug
+https://shop.example,Spring sale,\"print;Spring\",spring-sale
+example.com/menu,,,
+ftp://files.example,,,
+https://two.example,,,spring-sale
+https://three.example,,,no",
+        )
+        .unwrap();
+
+        let statuses: Vec<_> = rows.iter().map(|row| row.report.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                RowStatus::Valid,
+                RowStatus::Valid,
+                RowStatus::Invalid,
+                RowStatus::Invalid,
+                RowStatus::Invalid
+            ]
+        );
+        assert_eq!(rows[0].report.row, 2);
+
+        let first = rows[0].code.as_ref().unwrap();
+        assert_eq!(first.tags, vec!["print", "spring"]);
+        assert_eq!(first.slug.as_deref(), Some("spring-sale"));
+        assert_eq!(rows[1].code.as_ref().unwrap().slug, None);
+
+        assert!(parse_csv("url,name\nhttps://a.example,A").is_err());
+        assert!(parse_csv("target_url\n").is_err());
+    }
+
+    fn new_code(target_url: &str, slug: Option<&str>) -> NewQrCode {
+        NewQrCode::new(
+            DynamicQr {
+                target_url: target_url.to_string(),
+                ..Default::default()
+            },
+            slug.map(str::to_string),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_insert_dynamic_urls() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, _) = user_with_qrcode(&db, "heidi").await;
+
+        let created = db
+            .insert_dynamic_urls(
+                &user_id,
+                &Owner::User(user_id.to_string()),
+                vec![
+                    new_code("https://a.example", Some("promo")),
+                    new_code("https://b.example", None),
+                ],
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        assert_eq!(created.len(), 2);
+        assert_eq!(created[0].server_url(), "promo");
+
+        let slug = QrSlug::from_param("promo").unwrap();
+        assert_eq!(
+            db.lookup_dynamic_url(&slug).await.unwrap().target_url,
+            "https://a.example"
+        );
+
+        // A taken slug rolls back the whole batch
+        assert!(db
+            .insert_dynamic_urls(
+                &user_id,
+                &Owner::User(user_id.to_string()),
+                vec![
+                    new_code("https://c.example", None),
+                    new_code("https://d.example", Some("promo")),
+                ],
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .is_err());
+
+        // So does a batch that doesn't fit in the tier
+        let owner = Owner::User(user_id.to_string());
+        db.insert_subscription(
+            &owner,
+            UserSubscription {
+                sub_id: "sub_heidi".to_string(),
+                tier: "Lite".to_string(),
+                status: "active".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let batch = (0..6)
+            .map(|_| new_code("https://e.example", None))
+            .collect();
+        assert!(matches!(
+            db.insert_dynamic_urls(&user_id, &owner, batch, &SubscriptionTier::Lite)
+                .await,
+            Err(ApiError::BadRequest)
+        ));
+        assert_eq!(db.get_subscription(&owner).await.unwrap().usage, 0);
+
+        let query = Form::<QrListQuery>::parse("").unwrap();
+        assert_eq!(
+            db.list_user_urls(&Owner::User(user_id.to_string()), &query)
+                .await
+                .unwrap()
+                .total,
+            3
+        );
+        assert_eq!(
+            db.slugs_taken(vec!["promo".to_string(), "free".to_string()])
+                .await
+                .unwrap(),
+            vec!["promo"]
+        );
+    }
+
+    #[test]
+    fn test_bulk_operations() {
+        assert_eq!(
+            replace_domain(
+                "https://Old.example:8080/menu?x=1",
+                "old.example",
+                "new.example"
+            ),
+            Some("https://new.example:8080/menu?x=1".to_string())
+        );
+        assert_eq!(
+            replace_domain("https://sub.old.example/", "old.example", "new.example"),
+            None
+        );
+        assert_eq!(
+            replace_domain(
+                "https://example.com/old.example",
+                "old.example",
+                "new.example"
+            ),
+            None
+        );
+
+        let ids = vec!["a1".to_string(), "b2".to_string(), "a1".to_string()];
+        assert_eq!(parse_ids(&ids).unwrap().len(), 2);
+        assert!(parse_ids(&[]).is_none());
+        assert!(parse_ids(&["not/an/id".to_string()]).is_none());
+
+        let mut retarget = BulkOperation::Retarget {
+            target_url: " ftp://files.example ".to_string(),
+        };
+        assert!(!normalize(&mut retarget));
+
+        let mut retag = BulkOperation::Retag {
+            add: vec![" Summer ".to_string(), "summer".to_string()],
+            remove: vec!["Spring".to_string()],
+        };
+        assert!(normalize(&mut retag));
+
+        let target = BulkTarget {
+            id: QrCodeId::parse("a1").unwrap(),
+            server_url: "a1".to_string(),
+            target_url: "https://old.example".to_string(),
+            target_type: QrTarget::Url,
+            tags: vec!["spring".to_string(), "promo".to_string()],
+            status: QrStatus::Active,
+        };
+
+        let change = plan(&retag, &target).unwrap().unwrap();
+        assert_eq!(
+            change.tags,
+            Some(vec!["promo".to_string(), "summer".to_string()])
+        );
+        assert_eq!(change.target_url, None);
+
+        // Codes already in the requested state are unchanged
+        assert_eq!(plan(&BulkOperation::Resume, &target), Ok(None));
+        assert_eq!(
+            plan(&BulkOperation::Pause, &target)
+                .unwrap()
+                .unwrap()
+                .status,
+            Some(QrStatus::Paused)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bulk_update_and_delete() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, _) = user_with_qrcode(&db, "ivan").await;
+        let (_, foreign) = user_with_qrcode(&db, "judy").await;
+
+        db.insert_subscription(
+            &Owner::User(user_id.to_string()),
+            UserSubscription {
+                sub_id: "sub_ivan".to_string(),
+                tier: "Free".to_string(),
+                status: "active".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+
+        let created = db
+            .insert_dynamic_urls(
+                &user_id,
+                &Owner::User(user_id.to_string()),
+                vec![
+                    new_code("https://old.example/a", Some("bulk-a")),
+                    new_code("https://other.example/b", Some("bulk-b")),
+                    new_code("https://old.example/c", Some("bulk-c")),
+                ],
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let ids = created
+            .iter()
+            .map(|qrcode| qrcode.id().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            db.get_subscription(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            3
+        );
+
+        let replace = BulkOperation::ReplaceDomain {
+            find: "old.example".to_string(),
+            replace: "new.example".to_string(),
+        };
+        let summary = db
+            .bulk_update_dynamic_urls(&user_id, &Owner::User(user_id.to_string()), &ids, &replace)
+            .await
+            .unwrap();
+        assert_eq!((summary.updated, summary.unchanged), (2, 1));
+
+        let slug = QrSlug::from_param("bulk-c").unwrap();
+        assert_eq!(
+            db.lookup_dynamic_url(&slug).await.unwrap().target_url,
+            "https://new.example/c"
+        );
+
+        // Paused codes still resolve, so scans can show the unavailable page
+        db.bulk_update_dynamic_urls(
+            &user_id,
+            &Owner::User(user_id.to_string()),
+            &ids,
+            &BulkOperation::Pause,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            db.lookup_dynamic_url(&slug).await.unwrap().status,
+            QrStatus::Paused
+        );
+
+        // A single foreign code fails the whole batch
+        let mut mixed = ids.clone();
+        mixed.push(foreign);
+        assert!(matches!(
+            db.bulk_update_dynamic_urls(
+                &user_id,
+                &Owner::User(user_id.to_string()),
+                &mixed,
+                &BulkOperation::Resume
+            )
+            .await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.delete_dynamic_urls(&Owner::User(user_id.to_string()), &mixed)
+                .await,
+            Err(ApiError::NotFound)
+        ));
+        assert_eq!(
+            db.get_subscription(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            3
+        );
+
+        assert_eq!(
+            db.delete_dynamic_urls(&Owner::User(user_id.to_string()), &ids[..2])
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            db.get_subscription(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            1
+        );
+
+        assert!(db.delete_dynamic_url(&user_id, &ids[2]).await.unwrap());
+        assert_eq!(
+            db.get_subscription(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revision_history() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, qrcode_id) = user_with_qrcode(&db, "karl").await;
+        let (mallory, mallory_code) = user_with_qrcode(&db, "mallory").await;
+
+        let qrcode = db.select_dynamic_url(&user_id, &qrcode_id).await.unwrap();
+        let slug = QrSlug::from_param(qrcode.server_url()).unwrap();
+
+        db.record_scan(&slug, ClientKind::Human);
+        db.flush_scans().await.unwrap();
+
+        let retarget = |target_url: &str| DynamicQr {
+            target_url: target_url.to_string(),
+            ..Default::default()
+        };
+        db.update_dynamic_url(&user_id, &qrcode_id, &retarget("https://v2.example"))
+            .await
+            .unwrap();
+        // Label-only updates don't add a revision
+        db.update_dynamic_url(&user_id, &qrcode_id, &retarget("https://v2.example"))
+            .await
+            .unwrap();
+
+        db.record_scan(&slug, ClientKind::Human);
+        db.record_scan(&slug, ClientKind::Bot);
+        db.flush_scans().await.unwrap();
+
+        let history = db.list_revisions(&user_id, &qrcode_id).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].target_url, "https://v2.example");
+        assert_eq!(
+            history[0].previous_target.as_deref(),
+            Some("https://karl.example")
+        );
+        assert_eq!((history[0].access_count, history[0].bot_count), (1, 1));
+        assert_eq!(history[1].previous_target, None);
+        assert_eq!((history[1].access_count, history[1].bot_count), (1, 0));
+        assert_eq!(history[1].author, user_id);
+
+        let original = &history[1].id;
+        let restored = db
+            .rollback_dynamic_url(&user_id, &qrcode_id, original)
+            .await
+            .unwrap();
+        assert_eq!(json!(restored)["target_url"], json!("https://karl.example"));
+        assert_eq!(
+            db.lookup_dynamic_url(&slug).await.unwrap().target_url,
+            "https://karl.example"
+        );
+
+        let history = db.list_revisions(&user_id, &qrcode_id).await.unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].restored_from.as_ref(), Some(original));
+
+        // Revisions of other codes, and other users' codes, can't be restored
+        let foreign = db.list_revisions(&mallory, &mallory_code).await.unwrap();
+        assert!(matches!(
+            db.rollback_dynamic_url(&user_id, &qrcode_id, &foreign[0].id)
+                .await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.list_revisions(&mallory, &qrcode_id).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.rollback_dynamic_url(&user_id, &qrcode_id, &RevisionId::parse("missing").unwrap())
+                .await,
+            Err(ApiError::NotFound)
+        ));
+
+        let retarget = BulkOperation::Retarget {
+            target_url: "https://v3.example".to_string(),
+        };
+        db.bulk_update_dynamic_urls(
+            &user_id,
+            &Owner::User(user_id.to_string()),
+            std::slice::from_ref(&qrcode_id),
+            &retarget,
+        )
+        .await
+        .unwrap();
+        let history = db.list_revisions(&user_id, &qrcode_id).await.unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[0].target_url, "https://v3.example");
+    }
+
+    #[tokio::test]
+    async fn test_trash() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, qrcode_id) = user_with_qrcode(&db, "nina").await;
+
+        db.insert_subscription(
+            &Owner::User(user_id.to_string()),
+            UserSubscription {
+                sub_id: "sub_nina".to_string(),
+                tier: "Free".to_string(),
+                status: "active".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        db.increment_usage(&Owner::User(user_id.to_string()))
+            .await
+            .unwrap();
+
+        let qrcode = db.select_dynamic_url(&user_id, &qrcode_id).await.unwrap();
+        let slug = QrSlug::from_param(qrcode.server_url()).unwrap();
+        assert!(!db.lookup_dynamic_url(&slug).await.unwrap().trashed);
+
+        assert!(db.delete_dynamic_url(&user_id, &qrcode_id).await.unwrap());
+        assert_eq!(
+            db.get_subscription(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            0
+        );
+
+        // Trashed codes still resolve so scans can say they were deactivated
+        assert!(db.lookup_dynamic_url(&slug).await.unwrap().trashed);
+        assert!(matches!(
+            db.select_dynamic_url(&user_id, &qrcode_id).await,
+            Err(ApiError::NotFound)
+        ));
+        let query = Form::<QrListQuery>::parse("").unwrap();
+        assert_eq!(
+            db.list_user_urls(&Owner::User(user_id.to_string()), &query)
+                .await
+                .unwrap()
+                .total,
+            0
+        );
+        assert_eq!(
+            db.list_trash(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let restored = db
+            .restore_dynamic_url(
+                &Owner::User(user_id.to_string()),
+                &qrcode_id,
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        assert_eq!(restored.server_url(), slug.as_str());
+        assert_eq!(
+            db.get_subscription(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            1
+        );
+        assert!(!db.lookup_dynamic_url(&slug).await.unwrap().trashed);
+        assert!(matches!(
+            db.restore_dynamic_url(
+                &Owner::User(user_id.to_string()),
+                &qrcode_id,
+                &SubscriptionTier::Pro
+            )
+            .await,
+            Err(ApiError::NotFound)
+        ));
+
+        db.delete_dynamic_url(&user_id, &qrcode_id).await.unwrap();
+
+        // A full tier can't take a code back out of the trash
+        for _ in 0..SubscriptionTier::Lite.max_usage() {
+            db.increment_usage(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            db.restore_dynamic_url(
+                &Owner::User(user_id.to_string()),
+                &qrcode_id,
+                &SubscriptionTier::Lite
+            )
+            .await,
+            Err(ApiError::BadRequest)
+        ));
+        assert!(db.lookup_dynamic_url(&slug).await.unwrap().trashed);
+
+        // Codes are only purged once their retention window has passed
+        assert_eq!(db.purge_trash(30).await.unwrap(), 0);
+        assert_eq!(db.purge_trash(0).await.unwrap(), 1);
+        assert!(db.lookup_dynamic_url(&slug).await.is_err());
+        assert!(db
+            .list_trash(&Owner::User(user_id.to_string()))
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            db.restore_dynamic_url(
+                &Owner::User(user_id.to_string()),
+                &qrcode_id,
+                &SubscriptionTier::Pro
+            )
+            .await,
+            Err(ApiError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, qrcode_id) = user_with_qrcode(&db, "oscar").await;
+        let (mallory, _) = user_with_qrcode(&db, "mallory").await;
+
+        let qrcode = db.select_dynamic_url(&user_id, &qrcode_id).await.unwrap();
+        let slug = QrSlug::from_param(qrcode.server_url()).unwrap();
+
+        // Warm the cache so pausing has to invalidate it
+        assert_eq!(
+            db.lookup_dynamic_url(&slug).await.unwrap().status,
+            QrStatus::Active
+        );
+
+        let paused = db
+            .set_dynamic_url_status(&user_id, &qrcode_id, QrStatus::Paused, None)
+            .await
+            .unwrap();
+        assert_eq!(json!(paused)["status"], json!("paused"));
+        assert_eq!(
+            db.lookup_dynamic_url(&slug).await.unwrap().status,
+            QrStatus::Paused
+        );
+        assert_eq!(
+            db.count_paused_urls(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            db.count_paused_urls(&Owner::User(mallory.to_string()))
+                .await
+                .unwrap(),
+            0
+        );
+
+        assert!(matches!(
+            db.set_dynamic_url_status(&mallory, &qrcode_id, QrStatus::Active, None)
+                .await,
+            Err(ApiError::NotFound)
+        ));
+
+        db.set_dynamic_url_status(&user_id, &qrcode_id, QrStatus::Active, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.count_paused_urls(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            db.lookup_dynamic_url(&slug).await.unwrap().status,
+            QrStatus::Active
+        );
+
+        // On tiers where paused codes are free, a full tier can't take a paused code back
+        let owner = Owner::User(user_id.to_string());
+        db.insert_subscription(
+            &owner,
+            UserSubscription {
+                sub_id: "sub_oscar".to_string(),
+                tier: "Pro".to_string(),
+                status: "active".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        db.increment_usage(&owner).await.unwrap();
+        db.set_dynamic_url_status(&user_id, &qrcode_id, QrStatus::Paused, None)
+            .await
+            .unwrap();
+        let batch = (0..SubscriptionTier::Pro.max_usage())
+            .map(|_| new_code("https://more.example", None))
+            .collect();
+        db.insert_dynamic_urls(&user_id, &owner, batch, &SubscriptionTier::Pro)
+            .await
+            .unwrap();
+        assert!(matches!(
+            db.set_dynamic_url_status(
+                &user_id,
+                &qrcode_id,
+                QrStatus::Active,
+                Some(&SubscriptionTier::Pro)
+            )
+            .await,
+            Err(ApiError::BadRequest)
+        ));
+        assert_eq!(db.count_paused_urls(&owner).await.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_status_pages() {
+        let page = StatusPage::paused(Some("<Gala> & friends"), Some(QrColor::Green)).html();
+        assert!(page.contains("&lt;Gala&gt; &amp; friends is paused"));
+        assert!(page.contains(QrColor::Green.hex()));
+        assert!(!page.contains("<Gala>"));
+
+        assert!(StatusPage::paused(None, None)
+            .html()
+            .contains("This QR code is paused"));
+        assert!(StatusPage::deactivated().html().contains("deactivated"));
+
+        assert!(SubscriptionTier::Lite.counts_paused());
+        assert!(!SubscriptionTier::Pro.counts_paused());
+    }
+
+    #[tokio::test]
+    async fn test_clone_qr_code() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, qrcode_id) = user_with_qrcode(&db, "peggy").await;
+        let (mallory, _) = user_with_qrcode(&db, "mallory").await;
+
+        db.insert_subscription(
+            &Owner::User(user_id.to_string()),
+            UserSubscription {
+                sub_id: "sub_peggy".to_string(),
+                tier: "Lite".to_string(),
+                status: "active".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        db.increment_usage(&Owner::User(user_id.to_string()))
+            .await
+            .unwrap();
+
+        let series = db
+            .insert_folder(&user_id, folder("Series", None))
+            .await
+            .unwrap();
+        db.move_dynamic_url(&user_id, &qrcode_id, &Some(series.id.to_string()))
+            .await
+            .unwrap();
+        let labels = DynamicQr {
+            target_url: "https://peggy.example/episode-1".to_string(),
+            redirect_code: RedirectCode::Temporary,
+            name: Some("Episode".to_string()),
+            tags: vec!["series".to_string()],
+            color: Some(QrColor::Blue),
+            ..Default::default()
+        };
+        db.update_dynamic_url(&user_id, &qrcode_id, &labels)
+            .await
+            .unwrap();
+        let original = json!(db.select_dynamic_url(&user_id, &qrcode_id).await.unwrap());
+
+        let cloned = db
+            .clone_dynamic_url(&user_id, &qrcode_id, &SubscriptionTier::Lite)
+            .await
+            .unwrap();
+        let copy = json!(cloned);
+
+        assert_ne!(copy["id"], original["id"]);
+        assert_ne!(copy["server_url"], original["server_url"]);
+        for field in [
+            "target_url",
+            "redirect_code",
+            "name",
+            "tags",
+            "color",
+            "folder",
+        ] {
+            assert_eq!(copy[field], original[field], "{}", field);
+        }
+        assert_eq!(copy["access_count"], json!(0));
+        assert_eq!(
+            db.get_subscription(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            2
+        );
+        assert_eq!(
+            db.list_revisions(&user_id, cloned.id())
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert!(matches!(
+            db.clone_dynamic_url(&mallory, &qrcode_id, &SubscriptionTier::Pro)
+                .await,
+            Err(ApiError::NotFound)
+        ));
+
+        // The tier limit is enforced with the copy, not before it
+        for _ in 0..3 {
+            db.clone_dynamic_url(&user_id, &qrcode_id, &SubscriptionTier::Lite)
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            db.clone_dynamic_url(&user_id, &qrcode_id, &SubscriptionTier::Lite)
+                .await,
+            Err(ApiError::BadRequest)
+        ));
+        let code = DynamicQr {
+            target_url: "https://full.example".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            db.insert_dynamic_url(
+                &user_id,
+                &Owner::User(user_id.to_string()),
+                code,
+                &SubscriptionTier::Lite
+            )
+            .await,
+            Err(ApiError::BadRequest)
+        ));
+        assert_eq!(
+            db.get_subscription(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            5
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transfer_qr_codes() {
+        let db = Database::memory().await.unwrap();
+        let (quinn, quinn_code) = user_with_qrcode(&db, "quinn").await;
+        let (rupert, _) = user_with_qrcode(&db, "rupert").await;
+        let codes = [quinn_code.clone()];
+
+        for (user_id, sub_id) in [(&quinn, "sub_quinn"), (&rupert, "sub_rupert")] {
+            db.insert_subscription(
+                &Owner::User(user_id.to_string()),
+                UserSubscription {
+                    sub_id: sub_id.to_string(),
+                    tier: "Lite".to_string(),
+                    status: "active".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+            db.increment_usage(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let mut request = TransferRequest {
+            email: " Rupert@Example.com ".to_string(),
+            qrcodes: vec![],
+        };
+        assert!(request.normalize());
+        assert_eq!(request.email, "rupert@example.com");
+        request.email = "not-an-email".to_string();
+        assert!(!request.normalize());
+
+        // Only the owner can offer a code, and never to themselves
+        assert!(matches!(
+            db.insert_transfer(&rupert, "quinn@example.com", &codes)
+                .await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.insert_transfer(&quinn, "quinn@example.com", &codes)
+                .await,
+            Err(ApiError::BadRequest)
+        ));
+
+        let offered = db
+            .insert_transfer(&quinn, "rupert@example.com", &codes)
+            .await
+            .unwrap();
+        assert_eq!(offered.status, TransferStatus::Pending);
+
+        // A code can only be in one pending transfer at a time
+        assert!(matches!(
+            db.insert_transfer(&quinn, "rupert@example.com", &codes)
+                .await,
+            Err(ApiError::BadRequest)
+        ));
+
+        let listed = db.list_transfers(&rupert).await.unwrap();
+        assert!(listed.outgoing.is_empty());
+        assert_eq!(listed.incoming.len(), 1);
+        assert_eq!(db.list_transfers(&quinn).await.unwrap().outgoing.len(), 1);
+
+        // The sender can't accept their own offer
+        assert!(matches!(
+            db.accept_transfer(&quinn, &offered.id, &SubscriptionTier::Pro)
+                .await,
+            Err(ApiError::NotFound)
+        ));
+
+        let accepted = db
+            .accept_transfer(&rupert, &offered.id, &SubscriptionTier::Pro)
+            .await
+            .unwrap();
+        assert_eq!(accepted.status, TransferStatus::Accepted);
+        assert_eq!(accepted.qrcodes, vec![quinn_code.clone()]);
+
+        assert!(matches!(
+            db.select_dynamic_url(&quinn, &quinn_code).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(db.select_dynamic_url(&rupert, &quinn_code).await.is_ok());
+        assert_eq!(
+            db.get_subscription(&Owner::User(quinn.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            0
+        );
+        assert_eq!(
+            db.get_subscription(&Owner::User(rupert.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            2
+        );
+
+        // Answered transfers can't be accepted twice
+        assert!(matches!(
+            db.accept_transfer(&rupert, &offered.id, &SubscriptionTier::Pro)
+                .await,
+            Err(ApiError::NotFound)
+        ));
+
+        let declined = db
+            .insert_transfer(&rupert, "quinn@example.com", &codes)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.decline_transfer(&quinn, &declined.id)
+                .await
+                .unwrap()
+                .status,
+            TransferStatus::Declined
+        );
+
+        let cancelled = db
+            .insert_transfer(&rupert, "quinn@example.com", &codes)
+            .await
+            .unwrap();
+        assert!(matches!(
+            db.cancel_transfer(&quinn, &cancelled.id).await,
+            Err(ApiError::NotFound)
+        ));
+        assert_eq!(
+            db.cancel_transfer(&rupert, &cancelled.id)
+                .await
+                .unwrap()
+                .status,
+            TransferStatus::Cancelled
+        );
+        assert!(matches!(
+            db.accept_transfer(&quinn, &cancelled.id, &SubscriptionTier::Pro)
+                .await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(db.select_dynamic_url(&rupert, &quinn_code).await.is_ok());
+
+        // A recipient without room gets nothing, and the offer stays open
+        let returned = db
+            .insert_transfer(&rupert, "quinn@example.com", &codes)
+            .await
+            .unwrap();
+        for _ in 0..SubscriptionTier::Lite.max_usage() {
+            db.increment_usage(&Owner::User(quinn.to_string()))
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            db.accept_transfer(&quinn, &returned.id, &SubscriptionTier::Lite)
+                .await,
+            Err(ApiError::BadRequest)
+        ));
+        assert!(db.select_dynamic_url(&rupert, &quinn_code).await.is_ok());
+
+        // Accepting twice at once only moves the codes once
+        let (first, second) = tokio::join!(
+            db.accept_transfer(&quinn, &returned.id, &SubscriptionTier::Pro),
+            db.accept_transfer(&quinn, &returned.id, &SubscriptionTier::Pro)
+        );
+        assert_eq!(
+            [first.is_ok(), second.is_ok()]
+                .iter()
+                .filter(|ok| **ok)
+                .count(),
+            1
+        );
+        assert_eq!(
+            db.get_subscription(&Owner::User(quinn.to_string()))
+                .await
+                .unwrap()
+                .usage,
+            SubscriptionTier::Lite.max_usage() + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_landing_page() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, url_code) = user_with_qrcode(&db, "sybil").await;
+        let (trent, _) = user_with_qrcode(&db, "trent").await;
+
+        let mut page_code = DynamicQr {
+            target_url: "https://ignored.example".to_string(),
+            target_type: QrTarget::Page,
+            name: Some("Sybil's links".to_string()),
+            ..Default::default()
+        };
+        assert!(page_code.normalize());
+        assert!(page_code.target_url.is_empty());
+        assert!(!DynamicQr::default().normalize());
+
+        let created = db
+            .insert_dynamic_url(
+                &user_id,
+                &Owner::User(user_id.to_string()),
+                page_code,
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let code = created
+            .iter()
+            .find(|code| json!(code)["target_type"] == json!("page"))
+            .unwrap();
+        let qrcode_id = code.id().clone();
+        let slug = QrSlug::from_param(code.server_url()).unwrap();
+
+        // New page codes start with an empty page named after the code, and no history
+        let empty = db.select_landing_page(&user_id, &qrcode_id).await.unwrap();
+        assert_eq!(empty.title, "Sybil's links");
+        assert!(empty.links.is_empty());
+        assert!(db
+            .list_revisions(&user_id, &qrcode_id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            db.select_landing_page(&user_id, &url_code).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.select_landing_page(&trent, &qrcode_id).await,
+            Err(ApiError::NotFound)
+        ));
+
+        let link = |id: Option<String>, title: &str, url: &str| PageLink {
+            id,
+            title: title.to_string(),
+            url: url.to_string(),
+        };
+
+        let mut page = LandingPage {
+            title: "  Sybil  ".to_string(),
+            avatar: Some(" ".to_string()),
+            links: vec![
+                link(None, "Shop", "https://shop.example"),
+                link(None, "Blog", "blog.example"),
+            ],
+        };
+        assert!(page.normalize());
+        assert_eq!(page.title, "Sybil");
+        assert_eq!(page.avatar, None);
+
+        let updated = db
+            .update_landing_page(&user_id, &qrcode_id, &page)
+            .await
+            .unwrap();
+        assert_eq!(updated.links.len(), 2);
+        assert_eq!(updated.links[0].title, "Shop");
+        let shop = updated.links[0].id.clone();
+        let blog = updated.links[1].id.clone();
+
+        assert_eq!(
+            db.record_link_click(&slug, &shop, ClientKind::Human)
+                .await
+                .unwrap(),
+            "https://shop.example"
+        );
+        db.record_link_click(&slug, &shop, ClientKind::Bot)
+            .await
+            .unwrap();
+
+        // Links are only found through the page they are on
+        let other = QrSlug::from_param("someone-else").unwrap();
+        assert!(matches!(
+            db.record_link_click(&other, &shop, ClientKind::Human).await,
+            Err(ApiError::NotFound)
+        ));
+
+        // Reordering keeps the clicks of existing links, dropped links are removed
+        let mut page = LandingPage {
+            title: "Sybil".to_string(),
+            avatar: Some("https://cdn.example/sybil.png".to_string()),
+            links: vec![
+                link(None, "Podcast", "https://podcast.example"),
+                link(Some(shop.to_string()), "Store", "https://store.example"),
+            ],
+        };
+        assert!(page.normalize());
+        let updated = db
+            .update_landing_page(&user_id, &qrcode_id, &page)
+            .await
+            .unwrap();
+        assert_eq!(updated.links.len(), 2);
+        assert_eq!(updated.links[1].id, shop);
+        assert_eq!(updated.links[1].url, "https://store.example");
+        assert_eq!(updated.links[1].clicks, 1);
+        assert_eq!(updated.links[1].bot_clicks, 1);
+        assert!(updated.links.iter().all(|link| link.id != blog));
+
+        // IDs of removed links, or of links on other pages, are rejected
+        page.links = vec![link(Some(blog.to_string()), "Blog", "blog.example")];
+        assert!(page.normalize());
+        assert!(matches!(
+            db.update_landing_page(&user_id, &qrcode_id, &page).await,
+            Err(ApiError::BadRequest)
+        ));
+
+        let mut invalid = LandingPage {
+            title: "Sybil".to_string(),
+            avatar: None,
+            links: vec![link(None, "Files", "ftp://files.example")],
+        };
+        assert!(!invalid.normalize());
+        invalid.links = vec![
+            link(Some(shop.to_string()), "A", "a.example"),
+            link(Some(shop.to_string()), "B", "b.example"),
+        ];
+        assert!(!invalid.normalize());
+
+        // Scans render the page, escaping its content
+        let rendered = db.lookup_landing_page(&slug).await.unwrap();
+        assert_eq!(rendered.links.len(), 2);
+        let mut view_page = rendered.clone();
+        view_page.title = "<Sybil>".to_string();
+        let html = PageView::new(slug.as_str(), view_page, Some(QrColor::Green)).html();
+        assert!(html.contains("&lt;Sybil&gt;"));
+        assert!(html.contains(&format!("/scan/{}/link/{}", slug.as_str(), shop)));
+        assert!(html.contains("https://cdn.example/sybil.png"));
+        assert_eq!(
+            db.lookup_dynamic_url(&slug).await.unwrap().target_type,
+            QrTarget::Page
+        );
+
+        // Page codes have no target URL to retarget
+        let target = BulkTarget {
+            id: qrcode_id.clone(),
+            server_url: slug.as_str().to_string(),
+            target_url: String::new(),
+            target_type: QrTarget::Page,
+            tags: vec![],
+            status: QrStatus::Active,
+        };
+        assert!(plan(
+            &BulkOperation::Retarget {
+                target_url: "https://new.example".to_string()
+            },
+            &target
+        )
+        .is_err());
+
+        // Clones get a copy of the page with fresh counts
+        let cloned = db
+            .clone_dynamic_url(&user_id, &qrcode_id, &SubscriptionTier::Pro)
+            .await
+            .unwrap();
+        assert_eq!(json!(cloned)["target_type"], json!("page"));
+        let copy = db.select_landing_page(&user_id, cloned.id()).await.unwrap();
+        assert_eq!(copy.title, "Sybil");
+        assert_eq!(copy.links.len(), 2);
+        assert_eq!(copy.links[1].title, "Store");
+        assert_eq!(copy.links[1].clicks, 0);
+        assert_ne!(copy.links[1].id, shop);
+    }
+
+    #[test]
+    fn test_file_types_and_ranges() {
+        assert_eq!(sniff(b"%PDF-1.7\n"), Some(("application/pdf", "pdf")));
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0"), Some(("image/png", "png")));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some(("image/webp", "webp")));
+        assert_eq!(sniff(b"<html><script>"), None);
+        assert_eq!(sniff(b""), None);
+
+        assert_eq!(SubscriptionTier::Lite.max_file_size(), 5 * MIB);
+        assert!(SubscriptionTier::Pro.max_storage() > SubscriptionTier::Lite.max_storage());
+
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(
+            parse_range(Some("bytes=0-9"), 100),
+            ByteRange::Partial { start: 0, end: 9 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=90-"), 100),
+            ByteRange::Partial { start: 90, end: 99 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-10"), 100),
+            ByteRange::Partial { start: 90, end: 99 }
+        );
+        // Ends past the file are cut short, suffixes longer than the file are the whole file
+        assert_eq!(
+            parse_range(Some("bytes=50-500"), 100),
+            ByteRange::Partial { start: 50, end: 99 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-500"), 100),
+            ByteRange::Partial { start: 0, end: 99 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=100-"), 100),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        // Multiple, reversed and malformed ranges are ignored
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=9-2"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-1"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=a-b"), 100), ByteRange::Full);
+    }
+
+    #[tokio::test]
+    async fn test_hosted_files() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, url_code) = user_with_qrcode(&db, "uma").await;
+        let (victor, _) = user_with_qrcode(&db, "victor").await;
+
+        let dir = std::env::temp_dir().join(format!("qr-files-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+
+        let mut file_code = DynamicQr {
+            target_type: QrTarget::File,
+            name: Some("Menu".to_string()),
+            ..Default::default()
+        };
+        assert!(file_code.normalize());
+
+        let created = db
+            .insert_dynamic_url(
+                &user_id,
+                &Owner::User(user_id.to_string()),
+                file_code,
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let code = created
+            .iter()
+            .find(|code| json!(code)["target_type"] == json!("file"))
+            .unwrap();
+        let qrcode_id = code.id().clone();
+        let slug = QrSlug::from_param(code.server_url()).unwrap();
+
+        // Nothing is served until a file is uploaded
+        assert!(db
+            .select_file_code(&user_id, &qrcode_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            db.lookup_hosted_file(&slug).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.select_file_code(&user_id, &url_code).await,
+            Err(ApiError::BadRequest)
+        ));
+        assert!(matches!(
+            db.select_file_code(&victor, &qrcode_id).await,
+            Err(ApiError::NotFound)
+        ));
+
+        let path = dir.join(qrcode_id.to_string());
+        tokio::fs::write(&path, b"%PDF-1.7 menu").await.unwrap();
+        let stored = db
+            .store_hosted_file(
+                &user_id,
+                &qrcode_id,
+                HostedFile {
+                    file_name: "menu.pdf".to_string(),
+                    content_type: "application/pdf".to_string(),
+                    size: 13,
+                    path: path.to_string_lossy().into_owned(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(stored.size, 13);
+        // The path on disk stays on the server
+        assert!(json!(stored).get("path").is_none());
+
+        // Replacing the file keeps the server URL
+        let replaced = db
+            .store_hosted_file(
+                &user_id,
+                &qrcode_id,
+                HostedFile {
+                    file_name: "menu-v2.pdf".to_string(),
+                    content_type: "application/pdf".to_string(),
+                    size: 13,
+                    path: path.to_string_lossy().into_owned(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(replaced.file_name, "menu-v2.pdf");
+        assert_eq!(
+            db.lookup_hosted_file(&slug).await.unwrap().file_name,
+            "menu-v2.pdf"
+        );
+        assert_eq!(
+            db.lookup_dynamic_url(&slug).await.unwrap().target_type,
+            QrTarget::File
+        );
+
+        assert_eq!(
+            db.storage_usage(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap(),
+            13
+        );
+        assert_eq!(
+            db.storage_usage(&Owner::User(victor.to_string()))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            db.hosted_files_size(&[qrcode_id.clone(), url_code.clone()])
+                .await
+                .unwrap(),
+            13
+        );
+
+        // Clones get their own copy of the file
+        let cloned = db
+            .clone_dynamic_url(&user_id, &qrcode_id, &SubscriptionTier::Pro)
+            .await
+            .unwrap();
+        let copy = db
+            .select_file_code(&user_id, cloned.id())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(copy.path, replaced.path);
+        assert_eq!(tokio::fs::read(&copy.path).await.unwrap(), b"%PDF-1.7 menu");
+        assert_eq!(
+            db.storage_usage(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap(),
+            26
+        );
+
+        // Files stay on disk while trashed and are removed with the purge
+        db.delete_dynamic_url(&user_id, &qrcode_id).await.unwrap();
+        assert_eq!(
+            db.storage_usage(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap(),
+            26
+        );
+        assert_eq!(db.purge_trash(0).await.unwrap(), 1);
+        assert!(!path.exists());
+        assert_eq!(
+            db.storage_usage(&Owner::User(user_id.to_string()))
+                .await
+                .unwrap(),
+            13
+        );
+
+        db.delete_user_data(&user_id).await.unwrap();
+        assert!(!std::path::Path::new(&copy.path).exists());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_store_staging() {
+        let dir = std::env::temp_dir().join(format!("qr-staging-{}", std::process::id()));
+        let secrets: shuttle_runtime::SecretStore =
+            serde_json::from_value(json!({"FILE_STORAGE_DIR": dir.to_string_lossy()})).unwrap();
+        let store = FileStore::new(&Environments::new(secrets));
+
+        let mut first = TempFile::Buffered {
+            content: b"%PDF-1.7 first",
+        };
+        let mut second = TempFile::Buffered {
+            content: b"%PDF-1.7 second",
+        };
+
+        // Uploads to the same code are staged apart and leave the code's file alone
+        let kept = store.save("code", &mut first).await.unwrap();
+        let dropped = store.save("code", &mut second).await.unwrap();
+        assert_eq!(kept.file.path, dropped.file.path);
+        assert!(!std::path::Path::new(&kept.file.path).exists());
+
+        store.publish(kept).await.unwrap();
+        let path = dir.join("code");
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), b"%PDF-1.7 first");
+
+        // An upload whose record wasn't stored never replaces the file
+        store.discard(dropped).await;
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), b"%PDF-1.7 first");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let mut script = TempFile::Buffered {
+            content: b"<html><script>",
+        };
+        assert!(matches!(
+            store.save("code", &mut script).await,
+            Err(ApiError::BadRequest)
+        ));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[test]
+    fn test_deep_links_and_platforms() {
+        assert!(valid_deep_link("myapp://open/item?id=4"));
+        assert!(valid_deep_link(
+            "intent://open#Intent;scheme=myapp;package=com.example.app;end"
+        ));
+        assert!(valid_deep_link("https://app.example/open"));
+        assert!(!valid_deep_link("javascript://%0Aalert(1)"));
+        assert!(!valid_deep_link("JavaScript://x"));
+        assert!(!valid_deep_link("data://text/html,hi"));
+        assert!(!valid_deep_link("myapp://open item"));
+        assert!(!valid_deep_link("myapp://"));
+        assert!(!valid_deep_link("1app://open"));
+        assert!(!valid_deep_link("app.example/open"));
+
+        let iphone = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15";
+        let android = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 Chrome/120.0";
+        let desktop = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 Chrome/120.0";
+        assert_eq!(detect(Some(iphone)), Platform::Ios);
+        assert_eq!(detect(Some(android)), Platform::Android);
+        assert_eq!(detect(Some(desktop)), Platform::Other);
+        assert_eq!(detect(None), Platform::Other);
+
+        let mut app = AppLink {
+            ios_url: Some(" myapp://home ".to_string()),
+            android_url: Some(String::new()),
+            ios_store_url: None,
+            android_store_url: Some(
+                "https://play.google.com/store/apps/details?id=app".to_string(),
+            ),
+            fallback_url: "https://app.example".to_string(),
+        };
+        assert!(app.normalize());
+        assert_eq!(app.ios_url.as_deref(), Some("myapp://home"));
+        assert_eq!(app.android_url, None);
+
+        let mut invalid = app.clone();
+        invalid.ios_store_url = Some("http://apps.apple.com/app".to_string());
+        assert!(!invalid.normalize());
+        let mut invalid = app.clone();
+        invalid.android_url = Some("javascript://alert(1)".to_string());
+        assert!(!invalid.normalize());
+        let mut invalid = app.clone();
+        invalid.fallback_url = String::new();
+        assert!(!invalid.normalize());
+
+        // The deep link and fallback are escaped for both the markup and the script
+        let html = LaunchPage::new(
+            Some("<App>"),
+            "myapp://open?q=</script><script>alert(1)",
+            "app.example",
+        )
+        .html();
+        assert!(html.contains("&lt;App&gt;"));
+        assert!(!html.contains("</script><script>alert"));
+        assert!(html.contains("http://app.example"));
+    }
+
+    #[tokio::test]
+    async fn test_app_links() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, url_code) = user_with_qrcode(&db, "wanda").await;
+        let (xavier, _) = user_with_qrcode(&db, "xavier").await;
+
+        let mut app_code = DynamicQr {
+            target_type: QrTarget::App,
+            target_url: "https://ignored.example".to_string(),
+            ..Default::default()
+        };
+        assert!(app_code.normalize());
+        assert!(app_code.target_url.is_empty());
+
+        let created = db
+            .insert_dynamic_url(
+                &user_id,
+                &Owner::User(user_id.to_string()),
+                app_code,
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let code = created
+            .iter()
+            .find(|code| json!(code)["target_type"] == json!("app"))
+            .unwrap();
+        let qrcode_id = code.id().clone();
+        let slug = QrSlug::from_param(code.server_url()).unwrap();
+
+        // Nothing opens until the links are set
+        assert!(db
+            .select_app_link(&user_id, &qrcode_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            db.lookup_app_link(&slug).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.select_app_link(&user_id, &url_code).await,
+            Err(ApiError::BadRequest)
+        ));
+        assert!(matches!(
+            db.select_app_link(&xavier, &qrcode_id).await,
+            Err(ApiError::NotFound)
+        ));
+
+        // Clones of an app code without links don't get any either
+        let bare = db
+            .clone_dynamic_url(&user_id, &qrcode_id, &SubscriptionTier::Pro)
+            .await
+            .unwrap();
+        assert!(db
+            .select_app_link(&user_id, bare.id())
+            .await
+            .unwrap()
+            .is_none());
+
+        let mut app = AppLink {
+            ios_url: Some("myapp://home".to_string()),
+            android_url: None,
+            ios_store_url: Some("https://apps.apple.com/app/id1".to_string()),
+            android_store_url: Some(
+                "https://play.google.com/store/apps/details?id=app".to_string(),
+            ),
+            fallback_url: "https://app.example".to_string(),
+        };
+        assert!(app.normalize());
+        db.update_app_link(&user_id, &qrcode_id, &app)
+            .await
+            .unwrap();
+
+        // Setting links again replaces them, dropping ones that are left out
+        app.ios_store_url = None;
+        let updated = db
+            .update_app_link(&user_id, &qrcode_id, &app)
+            .await
+            .unwrap();
+        assert_eq!(updated.ios_store_url, None);
+
+        let links = db.lookup_app_link(&slug).await.unwrap();
+        assert_eq!(
+            links.launch(Platform::Ios),
+            AppLaunch::Page {
+                deep_link: "myapp://home".to_string(),
+                fallback: "https://app.example".to_string(),
+            }
+        );
+        assert_eq!(
+            links.launch(Platform::Android),
+            AppLaunch::Redirect("https://play.google.com/store/apps/details?id=app".to_string())
+        );
+        assert_eq!(
+            links.launch(Platform::Other),
+            AppLaunch::Redirect("https://app.example".to_string())
+        );
+        assert!(matches!(
+            db.update_app_link(&user_id, &url_code, &app).await,
+            Err(ApiError::BadRequest)
+        ));
+
+        // Clones get a copy of the links
+        let cloned = db
+            .clone_dynamic_url(&user_id, &qrcode_id, &SubscriptionTier::Pro)
+            .await
+            .unwrap();
+        let copy = db
+            .select_app_link(&user_id, cloned.id())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(copy.ios_url.as_deref(), Some("myapp://home"));
+        assert_eq!(copy.fallback_url, "https://app.example");
+
+        // App codes have no target URL to retarget
+        let target = BulkTarget {
+            id: qrcode_id.clone(),
+            server_url: slug.as_str().to_string(),
+            target_url: String::new(),
+            target_type: QrTarget::App,
+            tags: vec![],
+            status: QrStatus::Active,
+        };
+        assert!(plan(
+            &BulkOperation::ReplaceDomain {
+                find: "app.example".to_string(),
+                replace: "new.example".to_string()
+            },
+            &target
+        )
+        .is_err());
+
+        db.delete_dynamic_url(&user_id, &qrcode_id).await.unwrap();
+        db.purge_trash(0).await.unwrap();
+        assert!(matches!(
+            db.lookup_app_link(&slug).await,
+            Err(ApiError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_contact_cards() {
+        let db = Database::memory().await.unwrap();
+        let (user_id, url_code) = user_with_qrcode(&db, "yara").await;
+        let (zeke, _) = user_with_qrcode(&db, "zeke").await;
+
+        let mut contact_code = DynamicQr {
+            target_type: QrTarget::Contact,
+            ..Default::default()
+        };
+        assert!(contact_code.normalize());
+
+        let created = db
+            .insert_dynamic_url(
+                &user_id,
+                &Owner::User(user_id.to_string()),
+                contact_code,
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let code = created
+            .iter()
+            .find(|code| json!(code)["target_type"] == json!("contact"))
+            .unwrap();
+        let qrcode_id = code.id().clone();
+        let slug = QrSlug::from_param(code.server_url()).unwrap();
+
+        // Nothing is shown until the card is set
+        assert!(db
+            .select_contact_card(&user_id, &qrcode_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            db.lookup_contact_card(&slug).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.select_contact_card(&user_id, &url_code).await,
+            Err(ApiError::BadRequest)
+        ));
+        assert!(matches!(
+            db.select_contact_card(&zeke, &qrcode_id).await,
+            Err(ApiError::NotFound)
+        ));
+
+        let mut card = ContactCard {
+            first_name: " Yara ".to_string(),
+            last_name: Some("Núñez".to_string()),
+            organization: Some("Acme, Inc.".to_string()),
+            job_title: Some("Engineer".to_string()),
+            email: Some("yara@acme.example".to_string()),
+            phone: Some("+1 (555) 010-0199".to_string()),
+            mobile: Some(" ".to_string()),
+            website: Some("acme.example".to_string()),
+            address: Some("1 Main St\nSpringfield".to_string()),
+            note: None,
+            photo: Some("https://cdn.example/yara.jpg".to_string()),
+            download: false,
+        };
+        assert!(card.normalize());
+        assert_eq!(card.first_name, "Yara");
+        assert_eq!(card.mobile, None);
+
+        let mut invalid = card.clone();
+        invalid.email = Some("not an email".to_string());
+        assert!(!invalid.normalize());
+        let mut invalid = card.clone();
+        invalid.phone = Some("call me".to_string());
+        assert!(!invalid.normalize());
+        let mut invalid = card.clone();
+        invalid.first_name = "  ".to_string();
+        assert!(!invalid.normalize());
+
+        db.update_contact_card(&user_id, &qrcode_id, &card)
+            .await
+            .unwrap();
+
+        // A new role shows up on the next scan of the same code
+        card.job_title = Some("Director".to_string());
+        card.organization = None;
+        let updated = db
+            .update_contact_card(&user_id, &qrcode_id, &card)
+            .await
+            .unwrap();
+        assert_eq!(updated.job_title.as_deref(), Some("Director"));
+        assert_eq!(updated.organization, None);
+        assert!(matches!(
+            db.update_contact_card(&user_id, &url_code, &card).await,
+            Err(ApiError::BadRequest)
+        ));
+
+        let looked_up = db.lookup_contact_card(&slug).await.unwrap();
+        assert_eq!(looked_up.full_name(), "Yara Núñez");
+
+        // The vCard is 4.0, escapes text values and folds long lines
+        let mut long = looked_up.clone();
+        long.organization = Some("Acme, Inc.".to_string());
+        long.note = Some("x".repeat(100));
+        let vcf = vcard(&long);
+        assert!(vcf.starts_with("BEGIN:VCARD\r\nVERSION:4.0\r\n"));
+        assert!(vcf.ends_with("END:VCARD\r\n"));
+        assert!(vcf.contains("FN:Yara Núñez\r\n"));
+        assert!(vcf.contains("N:Núñez;Yara;;;\r\n"));
+        assert!(vcf.contains("ORG:Acme\\, Inc.\r\n"));
+        assert!(vcf.contains("TITLE:Director\r\n"));
+        assert!(vcf.contains(":tel:+15550100199\r\n"));
+        assert!(vcf.contains("URL:http://acme.example\r\n"));
+        assert!(vcf.contains("ADR;TYPE=work:;;1 Main St\\nSpringfield;;;;\r\n"));
+        assert!(vcf.contains("PHOTO:https://cdn.example/yara.jpg\r\n"));
+        assert!(vcf.contains("\r\n x"));
+        assert!(vcf.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(file_name(&long), "Yara-N-ez.vcf");
+        assert_eq!(tel_uri("+44 20 7946 0958"), "tel:+442079460958");
+
+        // The page escapes the card and links to the download
+        let mut view_card = looked_up.clone();
+        view_card.first_name = "<Yara>".to_string();
+        let html = ContactView::new(slug.as_str(), view_card, Some(QrColor::Blue)).html();
+        assert!(html.contains("&lt;Yara&gt; Núñez"));
+        assert!(html.contains(&format!("/scan/{}/contact.vcf", slug.as_str())));
+        assert!(html.contains("mailto:yara@acme.example"));
+        assert!(html.contains("1 Main St<br>Springfield"));
+        assert_eq!(
+            db.lookup_dynamic_url(&slug).await.unwrap().target_type,
+            QrTarget::Contact
+        );
+
+        // Clones get a copy of the card
+        let cloned = db
+            .clone_dynamic_url(&user_id, &qrcode_id, &SubscriptionTier::Pro)
+            .await
+            .unwrap();
+        let copy = db
+            .select_contact_card(&user_id, cloned.id())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(copy.job_title.as_deref(), Some("Director"));
+
+        db.delete_dynamic_url(&user_id, &qrcode_id).await.unwrap();
+        db.purge_trash(0).await.unwrap();
+        assert!(matches!(
+            db.lookup_contact_card(&slug).await,
+            Err(ApiError::NotFound)
+        ));
+
+        db.delete_user_data(&user_id).await.unwrap();
+        assert!(matches!(
+            db.select_contact_card(&user_id, cloned.id()).await,
+            Err(ApiError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_organizations() {
+        let db = Database::memory().await.unwrap();
+        let (alice, _) = user_with_qrcode(&db, "alice").await;
+        let (bob, _) = user_with_qrcode(&db, "bob").await;
+        let (carol, _) = user_with_qrcode(&db, "carol").await;
+
+        let mut organization = Organization {
+            name: "  Acme  ".to_string(),
+        };
+        assert!(organization.normalize());
+        assert!(!Organization {
+            name: " ".to_string()
+        }
+        .normalize());
+
+        let created = db.insert_organization(&alice, organization).await.unwrap();
+        let org_id = created.id.clone();
+        let org = Owner::Organization(org_id.clone());
+        assert_eq!(created.name, "Acme");
+
+        let memberships = db.list_organizations(&alice).await.unwrap();
+        assert_eq!(memberships.len(), 1);
+        assert_eq!(memberships[0].role, Role::Owner);
+        assert!(db.list_organizations(&bob).await.unwrap().is_empty());
+
+        // Members are existing accounts, added once
+        let mut member = NewMember {
+            email: " BOB@example.com ".to_string(),
+            role: Role::Viewer,
+        };
+        assert!(member.normalize());
+        let added = db.add_member(&org_id, member).await.unwrap();
+        assert_eq!(
+            (added.user.as_str(), added.role),
+            (bob.as_str(), Role::Viewer)
+        );
+        let again = NewMember {
+            email: "bob@example.com".to_string(),
+            role: Role::Admin,
+        };
+        assert!(matches!(
+            db.add_member(&org_id, again).await,
+            Err(ApiError::BadRequest)
+        ));
+        let stranger = NewMember {
+            email: "nobody@example.com".to_string(),
+            role: Role::Viewer,
+        };
+        assert!(matches!(
+            db.add_member(&org_id, stranger).await,
+            Err(ApiError::NotFound)
+        ));
+
+        // Organization codes count against the organization, not the member creating them
+        db.insert_subscription(
+            &org,
+            UserSubscription {
+                sub_id: "sub_acme".to_string(),
+                tier: "Pro".to_string(),
+                status: "complete".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let created = db
+            .insert_dynamic_urls(
+                &alice,
+                &org,
+                vec![new_code("https://acme.example", None)],
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let code = created[0].id().clone();
+        assert_eq!(db.get_subscription(&org).await.unwrap().usage, 1);
+
+        let query = Form::<QrListQuery>::parse("").unwrap();
+        assert_eq!(db.list_user_urls(&org, &query).await.unwrap().total, 1);
+        assert_eq!(
+            db.list_user_urls(&Owner::User(alice.clone()), &query)
+                .await
+                .unwrap()
+                .total,
+            1
+        );
+
+        // Viewers read, editors write and outsiders see nothing
+        let retarget = DynamicQr {
+            target_url: "https://acme.example/new".to_string(),
+            ..Default::default()
+        };
+        assert!(db.select_dynamic_url(&bob, &code).await.is_ok());
+        assert!(matches!(
+            db.update_dynamic_url(&bob, &code, &retarget).await,
+            Err(ApiError::Forbidden)
+        ));
+        assert!(matches!(
+            db.select_dynamic_url(&carol, &code).await,
+            Err(ApiError::NotFound)
+        ));
+
+        db.set_member_role(&org_id, &bob, Role::Editor)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.member_role(&bob, &org_id).await.unwrap(),
+            Some(Role::Editor)
+        );
+        db.update_dynamic_url(&bob, &code, &retarget).await.unwrap();
+        assert_eq!(
+            db.dynamic_url_owner(&bob, &code, Role::Editor)
+                .await
+                .unwrap(),
+            org
+        );
+
+        // Copies stay with the organization
+        let cloned = db
+            .clone_dynamic_url(&bob, &code, &SubscriptionTier::Pro)
+            .await
+            .unwrap();
+        assert_eq!(db.list_user_urls(&org, &query).await.unwrap().total, 2);
+        assert_eq!(db.get_subscription(&org).await.unwrap().usage, 2);
+        db.delete_dynamic_url(&bob, cloned.id()).await.unwrap();
+        assert_eq!(db.get_subscription(&org).await.unwrap().usage, 1);
+        assert_eq!(db.list_trash(&org).await.unwrap().len(), 1);
+
+        // An organization always keeps an owner
+        assert!(matches!(
+            db.set_member_role(&org_id, &alice, Role::Admin).await,
+            Err(ApiError::BadRequest)
+        ));
+        assert!(matches!(
+            db.remove_member(&org_id, &alice).await,
+            Err(ApiError::BadRequest)
+        ));
+        assert!(matches!(
+            db.remove_member(&org_id, &carol).await,
+            Err(ApiError::NotFound)
+        ));
+        db.set_member_role(&org_id, &bob, Role::Owner)
+            .await
+            .unwrap();
+        assert!(db.remove_member(&org_id, &alice).await.unwrap());
+        assert!(matches!(
+            db.select_dynamic_url(&alice, &code).await,
+            Err(ApiError::NotFound)
+        ));
+        assert_eq!(db.list_members(&org_id).await.unwrap().len(), 1);
+
+        let slug = QrSlug::from_param(created[0].server_url()).unwrap();
+        db.delete_organization(&org_id).await.unwrap();
+        assert!(db.lookup_dynamic_url(&slug).await.is_err());
+        assert!(db.list_organizations(&bob).await.unwrap().is_empty());
+        assert!(matches!(
+            db.select_organization(&org_id).await,
+            Err(ApiError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_organization_webhooks() {
+        let db = Database::memory().await.unwrap();
+        let (alice, _) = user_with_qrcode(&db, "alice").await;
+
+        let org_id = db
+            .insert_organization(
+                &alice,
+                Organization {
+                    name: "Acme".to_string(),
+                },
+            )
+            .await
+            .unwrap()
+            .id;
+        let org = Owner::Organization(org_id.clone());
+        let created = db
+            .insert_dynamic_urls(
+                &alice,
+                &org,
+                vec![new_code("https://acme.example", None)],
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let slug = QrSlug::from_param(created[0].server_url()).unwrap();
+
+        let hook = db
+            .insert_webhook(
+                &org,
+                Webhook {
+                    url: "https://hooks.acme.example/scans".to_string(),
+                    mode: WebhookMode::Scan,
+                    include_bots: false,
+                },
+                "whsec_acme",
+            )
+            .await
+            .unwrap();
+        assert_eq!(db.list_webhooks(&org).await.unwrap().len(), 1);
+        assert!(db
+            .list_webhooks(&Owner::User(alice.clone()))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Scans of organization codes are delivered to the organization's webhooks
+        db.record_scan(&slug, ClientKind::Human);
+        db.flush_scans().await.unwrap();
+
+        // Dispatchers claiming at the same time never both get a delivery
+        let (first, second) = tokio::join!(
+            db.claim_webhook_deliveries(10),
+            db.claim_webhook_deliveries(10)
+        );
+        let due: Vec<DueDelivery> = [first, second]
+            .into_iter()
+            .flat_map(|claimed| claimed.unwrap_or_default())
+            .collect();
+        assert_eq!(due.len(), 1);
+        assert!(db.claim_webhook_deliveries(10).await.unwrap().is_empty());
+        assert_eq!(due[0].url, "https://hooks.acme.example/scans");
+        assert_eq!(due[0].payload["server_url"], json!(slug.as_str()));
+        assert_eq!(
+            db.list_webhook_deliveries(&org, &hook.id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(matches!(
+            db.list_webhook_deliveries(&Owner::User(alice.clone()), &hook.id)
+                .await,
+            Err(ApiError::NotFound)
+        ));
+
+        db.delete_organization(&org_id).await.unwrap();
+        assert!(db.list_webhooks(&org).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invitations() {
+        let db = Database::memory().await.unwrap();
+        let (alice, _) = user_with_qrcode(&db, "alice").await;
+        let (bob, _) = user_with_qrcode(&db, "bob").await;
+        let (carol, _) = user_with_qrcode(&db, "carol").await;
+
+        let org_id = db
+            .insert_organization(
+                &alice,
+                Organization {
+                    name: "Acme".to_string(),
+                },
+            )
+            .await
+            .unwrap()
+            .id;
+
+        let token = generate_token();
+        assert!(token.starts_with("inv_"));
+        assert_ne!(token, generate_token());
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), token);
+
+        let invitation = db
+            .insert_invitation(
+                &alice,
+                &org_id,
+                "carol@example.com",
+                Role::Editor,
+                &hash_token(&token),
+            )
+            .await
+            .unwrap();
+        assert_eq!(invitation.status, InvitationStatus::Pending);
+        assert_eq!(invitation.invited_by, alice);
+        assert!(json!(invitation).get("token_hash").is_none());
+
+        // One pending invitation per email, and none for members
+        for email in ["carol@example.com", "alice@example.com"] {
+            assert!(matches!(
+                db.insert_invitation(&alice, &org_id, email, Role::Viewer, "other")
+                    .await,
+                Err(ApiError::BadRequest)
+            ));
+        }
+
+        // Tokens only work for the account the invitation was sent to
+        assert!(matches!(
+            db.accept_invitation(&bob, &hash_token(&token)).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.accept_invitation(&carol, &hash_token("inv_wrong")).await,
+            Err(ApiError::NotFound)
+        ));
+
+        // Resending replaces the token
+        let resent = generate_token();
+        db.renew_invitation(&alice, &org_id, &invitation.id, &hash_token(&resent))
+            .await
+            .unwrap();
+        assert!(matches!(
+            db.accept_invitation(&carol, &hash_token(&token)).await,
+            Err(ApiError::NotFound)
+        ));
+
+        let joined = db
+            .accept_invitation(&carol, &hash_token(&resent))
+            .await
+            .unwrap();
+        assert_eq!((joined.id, joined.role), (org_id.clone(), Role::Editor));
+        assert_eq!(
+            db.member_role(&carol, &org_id).await.unwrap(),
+            Some(Role::Editor)
+        );
+
+        // Tokens are single-use
+        assert!(matches!(
+            db.accept_invitation(&carol, &hash_token(&resent)).await,
+            Err(ApiError::NotFound)
+        ));
+
+        // Revoked invitations can't be answered and free the email for a new one
+        let revoked_token = generate_token();
+        let revoked = db
+            .insert_invitation(
+                &alice,
+                &org_id,
+                "bob@example.com",
+                Role::Admin,
+                &hash_token(&revoked_token),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            db.revoke_invitation(&org_id, &revoked.id)
+                .await
+                .unwrap()
+                .status,
+            InvitationStatus::Revoked
+        );
+        assert!(matches!(
+            db.revoke_invitation(&org_id, &revoked.id).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(matches!(
+            db.accept_invitation(&bob, &hash_token(&revoked_token))
+                .await,
+            Err(ApiError::NotFound)
+        ));
+
+        let declined_token = generate_token();
+        db.insert_invitation(
+            &alice,
+            &org_id,
+            "bob@example.com",
+            Role::Viewer,
+            &hash_token(&declined_token),
+        )
+        .await
+        .unwrap();
+        let declined = db
+            .decline_invitation(&bob, &hash_token(&declined_token))
+            .await
+            .unwrap();
+        assert_eq!(declined.status, InvitationStatus::Declined);
+        assert_eq!(db.member_role(&bob, &org_id).await.unwrap(), None);
+
+        let invitations = db.list_invitations(&org_id).await.unwrap();
+        assert_eq!(invitations.len(), 3);
+        assert!(invitations
+            .iter()
+            .all(|invitation| invitation.status != InvitationStatus::Pending));
+
+        // Deleting the organization takes its invitations with it
+        db.delete_organization(&org_id).await.unwrap();
+        assert!(db.list_invitations(&org_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_http_mailer() {
+        let (url, request) = webhook_stand_in("202 Accepted").await;
+        let mailer = HttpMailer::new(
+            url,
+            Some("key_test".to_string()),
+            Some("team@example.com".to_string()),
+        );
+        let mail = Mail {
+            to: "carol@example.com".to_string(),
+            subject: "You're invited to join Acme".to_string(),
+            text: "Accept or decline it with the token inv_test".to_string(),
+        };
+
+        mailer.send(&mail).await.unwrap();
+
+        let request = request.await.unwrap();
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        assert!(head
+            .to_ascii_lowercase()
+            .contains("authorization: bearer key_test"));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(body).unwrap(),
+            json!({
+                "from": "team@example.com",
+                "to": "carol@example.com",
+                "subject": "You're invited to join Acme",
+                "text": "Accept or decline it with the token inv_test",
+            })
+        );
+
+        let (url, _) = webhook_stand_in("500 Internal Server Error").await;
+        assert!(HttpMailer::new(url, None, None).send(&mail).await.is_err());
+    }
+
+    fn new_key(name: &str, scopes: Vec<ApiScope>) -> NewApiKey {
+        NewApiKey {
+            name: name.to_string(),
+            scopes,
+            expires_in_days: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() {
+        let db = Database::memory().await.unwrap();
+        let (alice, _) = user_with_qrcode(&db, "alice").await;
+        let (bob, _) = user_with_qrcode(&db, "bob").await;
+
+        let mut key = new_key(
+            "  Deploy  ",
+            vec![
+                ApiScope::QrcodeWrite,
+                ApiScope::QrcodeRead,
+                ApiScope::QrcodeWrite,
+            ],
+        );
+        assert!(key.normalize());
+        assert_eq!(key.name, "Deploy");
+        assert_eq!(
+            key.scopes,
+            vec![ApiScope::QrcodeRead, ApiScope::QrcodeWrite]
+        );
+        assert!(!new_key("CI", vec![]).normalize());
+        assert!(!new_key(" ", vec![ApiScope::QrcodeRead]).normalize());
+        for days in [0, MAX_KEY_EXPIRY_DAYS + 1] {
+            let mut expiring = new_key("CI", vec![ApiScope::QrcodeRead]);
+            expiring.expires_in_days = Some(days);
+            assert!(!expiring.normalize());
+        }
+        assert_eq!(json!(ApiScope::AnalyticsRead), json!("analytics:read"));
+
+        let secret = generate_key();
+        assert!(secret.starts_with(API_KEY_PREFIX));
+        assert_eq!(visible_prefix(&secret).len(), 12);
+        assert_ne!(hash_key(&secret), hash_key(&generate_key()));
+
+        // Keys are stored by hash and record when they are used
+        let user = Owner::User(alice.clone());
+        let created = db
+            .insert_api_key(
+                &alice,
+                &user,
+                key,
+                visible_prefix(&secret),
+                &hash_key(&secret),
+            )
+            .await
+            .unwrap();
+        assert_eq!(created.created_by, alice);
+        assert!(created.expires_at.is_none() && created.last_used_at.is_none());
+        assert!(json!(created).get("key_hash").is_none());
+
+        let grant = db
+            .authenticate_api_key(&hash_key(&secret))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((grant.user.as_str(), &grant.owner), (alice.as_str(), &user));
+        assert!(db
+            .authenticate_api_key(&hash_key("qrk_wrong"))
+            .await
+            .unwrap()
+            .is_none());
+        assert!(db.list_api_keys(&user).await.unwrap()[0]
+            .last_used_at
+            .is_some());
+
+        // Keys are held to their scopes and their own workspace
+        let caller = Caller {
+            user_id: alice.clone(),
+            key: Some(grant),
+        };
+        assert!(caller.authorize(&alice, ApiScope::QrcodeWrite).is_ok());
+        assert!(matches!(
+            caller.authorize(&alice, ApiScope::AnalyticsRead),
+            Err(ApiError::Forbidden)
+        ));
+        assert!(matches!(
+            caller.authorize(&bob, ApiScope::QrcodeRead),
+            Err(ApiError::Unauthorized)
+        ));
+        assert_eq!(caller.workspace(None).unwrap(), None);
+
+        let org_id = db
+            .insert_organization(
+                &alice,
+                Organization {
+                    name: "Acme".to_string(),
+                },
+            )
+            .await
+            .unwrap()
+            .id;
+        let org = Owner::Organization(org_id.clone());
+        assert!(matches!(
+            caller.workspace(Some(&org_id.to_string())),
+            Err(ApiError::Forbidden)
+        ));
+        assert!(caller.reaches(&user) && !caller.reaches(&org));
+
+        let org_secret = generate_key();
+        db.insert_api_key(
+            &alice,
+            &org,
+            new_key("Printer", vec![ApiScope::QrcodeRead]),
+            visible_prefix(&org_secret),
+            &hash_key(&org_secret),
+        )
+        .await
+        .unwrap();
+        let org_caller = Caller {
+            user_id: alice.clone(),
+            key: db
+                .authenticate_api_key(&hash_key(&org_secret))
+                .await
+                .unwrap(),
+        };
+        assert_eq!(
+            org_caller.workspace(None).unwrap(),
+            Some(org_id.to_string())
+        );
+        assert!(org_caller.reaches(&org) && !org_caller.reaches(&user));
+
+        // Signed-in users aren't limited
+        let signed_in = Caller {
+            user_id: alice.clone(),
+            key: None,
+        };
+        assert!(signed_in.authorize(&alice, ApiScope::AnalyticsRead).is_ok());
+        assert!(signed_in.reaches(&org));
+
+        // Keys a member made for an organization go when they leave it
+        db.add_member(
+            &org_id,
+            NewMember {
+                email: "bob@example.com".to_string(),
+                role: Role::Admin,
+            },
+        )
+        .await
+        .unwrap();
+        let bob_secret = generate_key();
+        db.insert_api_key(
+            &bob,
+            &org,
+            new_key("Bob's", vec![ApiScope::QrcodeRead]),
+            visible_prefix(&bob_secret),
+            &hash_key(&bob_secret),
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.list_api_keys(&org).await.unwrap().len(), 2);
+        db.remove_member(&org_id, &bob).await.unwrap();
+        assert_eq!(db.list_api_keys(&org).await.unwrap().len(), 1);
+        assert!(db
+            .authenticate_api_key(&hash_key(&bob_secret))
+            .await
+            .unwrap()
+            .is_none());
+
+        // Revoking only works through the key's own workspace
+        assert!(matches!(
+            db.delete_api_key(&org, &created.id).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(db.delete_api_key(&user, &created.id).await.unwrap());
+        assert!(db
+            .authenticate_api_key(&hash_key(&secret))
+            .await
+            .unwrap()
+            .is_none());
+
+        db.delete_organization(&org_id).await.unwrap();
+        assert!(db.list_api_keys(&org).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_api_key_routes() {
+        let db = Database::memory().await.unwrap();
+        let (alice, code) = user_with_qrcode(&db, "alice").await;
+        db.delete_dynamic_url(&alice, &code).await.unwrap();
+
+        let org_id = db
+            .insert_organization(
+                &alice,
+                Organization {
+                    name: "Acme".to_string(),
+                },
+            )
+            .await
+            .unwrap()
+            .id;
+
+        let mut secrets = Vec::new();
+        for (owner, scopes) in [
+            (Owner::User(alice.clone()), vec![ApiScope::QrcodeRead]),
+            (Owner::Organization(org_id), vec![ApiScope::QrcodeRead]),
+            (Owner::User(alice.clone()), vec![ApiScope::AnalyticsRead]),
+        ] {
+            let secret = generate_key();
+            db.insert_api_key(
+                &alice,
+                &owner,
+                new_key("CI", scopes),
+                visible_prefix(&secret),
+                &hash_key(&secret),
+            )
+            .await
+            .unwrap();
+            secrets.push(secret);
+        }
+
+        let rocket = rocket::build().manage(db).mount(
+            "/",
+            rocket::routes![
+                crate::trash::routes::read_trash,
+                crate::folder::folders::read_folders
+            ],
+        );
+        let client = Client::tracked(rocket).await.unwrap();
+        let get = |path: String, secret: &str| {
+            client
+                .get(path)
+                .header(Header::new("Authorization", format!("Bearer {secret}")))
+        };
+
+        // A personal key reads the trash it was made for
+        let response = get(format!("/user/{alice}/trash"), &secrets[0])
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["data"]["qrcodes"].as_array().unwrap().len(), 1);
+
+        // Organization keys see their organization's trash, and no personal folders
+        let response = get(format!("/user/{alice}/trash"), &secrets[1])
+            .dispatch()
+            .await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert!(body["data"]["qrcodes"].as_array().unwrap().is_empty());
+        let response = get(format!("/user/{alice}/folder"), &secrets[1])
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+
+        // Keys are held to their scopes, and unknown keys aren't let in
+        let response = get(format!("/user/{alice}/trash"), &secrets[2])
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = get(format!("/user/{alice}/trash"), "qrk_wrong")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn test_update_keeps_target_type() {
+        let db = Database::memory().await.unwrap();
+        let (alice, url_code) = user_with_qrcode(&db, "alice").await;
+
+        let created = db
+            .insert_dynamic_url(
+                &alice,
+                &Owner::User(alice.clone()),
+                DynamicQr {
+                    target_type: QrTarget::Page,
+                    ..Default::default()
+                },
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let page_code = created
+            .iter()
+            .find(|code| json!(code)["target_type"] == json!("page"))
+            .unwrap()
+            .id()
+            .clone();
+
+        let secret = generate_key();
+        db.insert_api_key(
+            &alice,
+            &Owner::User(alice.clone()),
+            new_key("CI", vec![ApiScope::QrcodeWrite]),
+            visible_prefix(&secret),
+            &hash_key(&secret),
+        )
+        .await
+        .unwrap();
+
+        let rocket = rocket::build()
+            .manage(db)
+            .mount("/", rocket::routes![crate::routes::user::update_qrcodes]);
+        let client = Client::tracked(rocket).await.unwrap();
+        let put = |code: &QrCodeId, body: serde_json::Value| {
+            client
+                .put(format!("/user/{alice}/qrcode/{code}"))
+                .header(Header::new("Authorization", format!("Bearer {secret}")))
+                .json(&body)
+        };
+
+        // Page codes are renamed without a target URL or their type in the body
+        let response = put(&page_code, json!({"name": "Bio"})).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["data"]["updated"]["name"], json!("Bio"));
+
+        // Claiming another type doesn't let a URL code lose its target URL
+        let response = put(&url_code, json!({"target_type": "page", "name": "Bio"}))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+        let response = put(
+            &url_code,
+            json!({"target_type": "page", "target_url": "https://v2.example"}),
+        )
+        .dispatch()
+        .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(
+            body["data"]["updated"]["target_url"],
+            json!("https://v2.example")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_personal_cancellation_keeps_organizations() {
+        let db = Database::memory().await.unwrap();
+        let (alice, personal_code) = user_with_qrcode(&db, "alice").await;
+
+        let org_id = db
+            .insert_organization(
+                &alice,
+                Organization {
+                    name: "Acme".to_string(),
+                },
+            )
+            .await
+            .unwrap()
+            .id;
+        let org = Owner::Organization(org_id.clone());
+        let created = db
+            .insert_dynamic_urls(
+                &alice,
+                &org,
+                vec![new_code("https://acme.example", None)],
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let code = created[0].id().clone();
+        let retarget = DynamicQr {
+            target_url: "https://acme.example/new".to_string(),
+            ..Default::default()
+        };
+        db.update_dynamic_url(&alice, &code, &retarget)
+            .await
+            .unwrap();
+        let history = db.list_revisions(&alice, &code).await.unwrap().len();
+
+        let secret = generate_key();
+        db.insert_api_key(
+            &alice,
+            &Owner::User(alice.clone()),
+            new_key("Personal", vec![ApiScope::QrcodeRead]),
+            visible_prefix(&secret),
+            &hash_key(&secret),
+        )
+        .await
+        .unwrap();
+        let org_secret = generate_key();
+        db.insert_api_key(
+            &alice,
+            &org,
+            new_key("Printer", vec![ApiScope::QrcodeRead]),
+            visible_prefix(&org_secret),
+            &hash_key(&org_secret),
+        )
+        .await
+        .unwrap();
+
+        // Cancelling a personal subscription only clears the user's own workspace
+        db.delete_user_data(&alice).await.unwrap();
+        assert!(matches!(
+            db.select_dynamic_url(&alice, &personal_code).await,
+            Err(ApiError::NotFound)
+        ));
+        assert!(db
+            .authenticate_api_key(&hash_key(&secret))
+            .await
+            .unwrap()
+            .is_none());
+
+        assert_eq!(
+            db.member_role(&alice, &org_id).await.unwrap(),
+            Some(Role::Owner)
+        );
+        assert_eq!(db.list_members(&org_id).await.unwrap().len(), 1);
+        assert!(db.select_dynamic_url(&alice, &code).await.is_ok());
+        assert_eq!(
+            db.list_revisions(&alice, &code).await.unwrap().len(),
+            history
+        );
+        assert!(db
+            .authenticate_api_key(&hash_key(&org_secret))
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_organization_cancellation_keeps_organization() {
+        let db = Database::memory().await.unwrap();
+        let (alice, _) = user_with_qrcode(&db, "alice").await;
+        let (bob, _) = user_with_qrcode(&db, "bob").await;
+
+        let org_id = db
+            .insert_organization(
+                &alice,
+                Organization {
+                    name: "Acme".to_string(),
+                },
+            )
+            .await
+            .unwrap()
+            .id;
+        let org = Owner::Organization(org_id.clone());
+        db.add_member(
+            &org_id,
+            NewMember {
+                email: "bob@example.com".to_string(),
+                role: Role::Editor,
+            },
+        )
+        .await
+        .unwrap();
+        db.insert_subscription(
+            &org,
+            UserSubscription {
+                sub_id: "sub_acme".to_string(),
+                tier: "Pro".to_string(),
+                status: "complete".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let created = db
+            .insert_dynamic_urls(
+                &alice,
+                &org,
+                vec![new_code("https://acme.example", None)],
+                &SubscriptionTier::Pro,
+            )
+            .await
+            .unwrap();
+        let slug = QrSlug::from_param(created[0].server_url()).unwrap();
+        let hook = db
+            .insert_webhook(
+                &org,
+                Webhook {
+                    url: "https://hooks.acme.example/scans".to_string(),
+                    mode: WebhookMode::Scan,
+                    include_bots: false,
+                },
+                "whsec_acme",
+            )
+            .await
+            .unwrap();
+        let secret = generate_key();
+        db.insert_api_key(
+            &alice,
+            &org,
+            new_key("Printer", vec![ApiScope::QrcodeRead]),
+            visible_prefix(&secret),
+            &hash_key(&secret),
+        )
+        .await
+        .unwrap();
+
+        // A lapsed subscription takes the codes, not the organization
+        assert_eq!(
+            db.get_owner_from_subscription("sub_acme").await.unwrap(),
+            org
+        );
+        db.delete_organization_data(&org_id).await.unwrap();
+        assert!(db.lookup_dynamic_url(&slug).await.is_err());
+        assert!(db.get_subscription(&org).await.is_err());
+
+        assert!(db.select_organization(&org_id).await.is_ok());
+        assert_eq!(db.list_members(&org_id).await.unwrap().len(), 2);
+        assert_eq!(
+            db.member_role(&bob, &org_id).await.unwrap(),
+            Some(Role::Editor)
+        );
+        assert_eq!(db.list_webhooks(&org).await.unwrap()[0].id, hook.id);
+        assert!(db
+            .authenticate_api_key(&hash_key(&secret))
+            .await
+            .unwrap()
+            .is_some());
+    }
 }
@@ -0,0 +1,67 @@
+use crate::database::models::{QrCodeId, TransferId};
+
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
+
+pub const MAX_EMAIL_LENGTH: usize = 254;
+
+// Pending transfers can no longer be accepted after this many days.
+pub const TRANSFER_EXPIRY_DAYS: i64 = 14;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferRequest {
+    // The email address of the account that receives the codes.
+    pub email: String,
+    pub qrcodes: Vec<String>,
+}
+
+pub fn valid_email(email: &str) -> bool {
+    // A loose check, the address is only ever proven by mail reaching it.
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && email.len() <= MAX_EMAIL_LENGTH
+                && !email.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+impl TransferRequest {
+    pub fn normalize(&mut self) -> bool {
+        // Emails are matched case-insensitively against the recipient's account.
+        self.email = self.email.trim().to_lowercase();
+
+        valid_email(&self.email)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    Pending,
+    Accepted,
+    Declined,
+    // Withdrawn by the sender before the recipient answered.
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferResult {
+    pub id: TransferId,
+    // The sender's user ID.
+    pub sender: String,
+    pub recipient_email: String,
+    pub qrcodes: Vec<QrCodeId>,
+    pub status: TransferStatus,
+    pub created_at: Datetime,
+    pub expires_at: Datetime,
+    pub resolved_at: Option<Datetime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferList {
+    pub outgoing: Vec<TransferResult>,
+    pub incoming: Vec<TransferResult>,
+}
@@ -0,0 +1,182 @@
+use crate::apikey::models::ApiScope;
+use crate::bulk::update::parse_ids;
+use crate::database::database::Database;
+use crate::database::models::{Owner, TransferId};
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::routes::guard::Caller;
+use crate::routes::user::{available_usage, reach_account};
+use crate::transfer::models::TransferRequest;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{delete, get, post};
+use serde_json::json;
+
+#[post("/user/<user_id>/transfer", format = "json", data = "<transfer>")]
+pub async fn create_transfer(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    transfer: Json<TransferRequest>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Offers some of a user's dynamic URLs to the account with the given email.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            transfer: the recipient's email and the dynamic URL IDs to hand over.
+
+        Returns:
+            Response<Json<ApiResponse>>: the pending transfer object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_account(&caller, user_id)?;
+
+    let mut transfer = transfer.into_inner();
+
+    if !transfer.normalize() {
+        return Err(ApiError::BadRequest);
+    }
+
+    let qrcode_ids = parse_ids(&transfer.qrcodes).ok_or(ApiError::BadRequest)?;
+
+    let created = db
+        .insert_transfer(user_id, &transfer.email, &qrcode_ids)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Created.code,
+        message: "Transfer created".to_string(),
+        data: json!({"transfer": created}),
+    }))
+}
+
+#[get("/user/<user_id>/transfer")]
+pub async fn read_transfers(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Lists the transfers a user started and the ones sent to them.
+
+        Params:
+            user_id: the user's Auth0 ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the outgoing and incoming transfers as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+    reach_account(&caller, user_id)?;
+
+    let transfers = db.list_transfers(user_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Transfers".to_string(),
+        data: json!(transfers),
+    }))
+}
+
+#[post("/user/<user_id>/transfer/<transfer_id>/accept")]
+pub async fn accept_transfer(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    transfer_id: TransferId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Accepts a transfer sent to the user, moving its codes into their account.
+
+        Params:
+            user_id: the recipient's Auth0 ID.
+            transfer_id: the transfer ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the accepted transfer object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_account(&caller, user_id)?;
+
+    // The recipient's tier has to fit every code and hosted file being handed over, which the
+    // transaction moving them checks.
+    let recipient = Owner::User(user_id.to_string());
+    let (tier, _available) = available_usage(db, &recipient).await?;
+
+    let accepted = db.accept_transfer(user_id, &transfer_id, &tier).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Transfer accepted".to_string(),
+        data: json!({"transfer": accepted}),
+    }))
+}
+
+#[post("/user/<user_id>/transfer/<transfer_id>/decline")]
+pub async fn decline_transfer(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    transfer_id: TransferId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Declines a transfer sent to the user. The codes stay with the sender.
+
+        Params:
+            user_id: the recipient's Auth0 ID.
+            transfer_id: the transfer ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the declined transfer object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_account(&caller, user_id)?;
+
+    let declined = db.decline_transfer(user_id, &transfer_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Transfer declined".to_string(),
+        data: json!({"transfer": declined}),
+    }))
+}
+
+#[delete("/user/<user_id>/transfer/<transfer_id>")]
+pub async fn cancel_transfer(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    transfer_id: TransferId,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Withdraws a pending transfer the user started.
+
+        Params:
+            user_id: the sender's Auth0 ID.
+            transfer_id: the transfer ID.
+
+        Returns:
+            Response<Json<ApiResponse>>: the cancelled transfer object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+    reach_account(&caller, user_id)?;
+
+    let cancelled = db.cancel_transfer(user_id, &transfer_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Transfer cancelled".to_string(),
+        data: json!({"transfer": cancelled}),
+    }))
+}
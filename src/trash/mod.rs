@@ -0,0 +1,2 @@
+pub mod purge;
+pub mod routes;
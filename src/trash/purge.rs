@@ -0,0 +1,26 @@
+use crate::database::database::Database;
+
+use std::time::Duration;
+
+// How long trashed codes can be restored before they are deleted for good.
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+pub const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub fn spawn_purger(db: Database) {
+    /*
+        Periodically deletes trashed dynamic URLs whose retention window has passed.
+    */
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(error) = db.purge_trash(TRASH_RETENTION_DAYS).await {
+                eprintln!("Failed to purge trash: {:?}", error);
+            }
+        }
+    });
+}
@@ -0,0 +1,87 @@
+use crate::apikey::models::ApiScope;
+use crate::database::database::Database;
+use crate::database::models::QrCodeId;
+use crate::errors::{ApiResponse, Response};
+use crate::organization::models::Role;
+use crate::routes::guard::Caller;
+use crate::routes::user::{available_usage, workspace};
+use crate::trash::purge::TRASH_RETENTION_DAYS;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{get, post};
+use serde_json::json;
+
+#[get("/user/<user_id>/trash?<org>")]
+pub async fn read_trash(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    org: Option<&str>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Lists the dynamic URLs a user, or an organization they are a member of, has deleted that can still be restored.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org: optional organization ID to list the organization's trash.
+
+        Returns:
+            Response<Json<ApiResponse>>: the trashed dynamic URLs and how many days they are kept, as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeRead)?;
+
+    let org = caller.workspace(org)?;
+
+    let owner = workspace(db, user_id, org.as_deref(), Role::Viewer).await?;
+
+    let qrcodes = db.list_trash(&owner).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Trash".to_string(),
+        data: json!({"qrcodes": qrcodes, "retention_days": TRASH_RETENTION_DAYS}),
+    }))
+}
+
+#[post("/user/<user_id>/trash/<qrcode_id>/restore?<org>")]
+pub async fn restore_qrcode(
+    caller: Caller,
+    db: &State<Database>,
+    user_id: &str,
+    qrcode_id: QrCodeId,
+    org: Option<&str>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Restores a trashed dynamic URL, keeping its server URL so printed codes work again.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            qrcode_id: the trashed dynamic URL ID.
+            org: optional organization ID, when the code belongs to the organization.
+
+        Returns:
+            Response<Json<ApiResponse>>: the restored dynamic URL object as a json response.
+
+    */
+
+    caller.authorize(user_id, ApiScope::QrcodeWrite)?;
+
+    let org = caller.workspace(org)?;
+
+    let owner = workspace(db, user_id, org.as_deref(), Role::Editor).await?;
+
+    // A restored code counts towards usage again, so the restore checks it fits in the tier.
+    let (tier, _available) = available_usage(db, &owner).await?;
+
+    let restored = db.restore_dynamic_url(&owner, &qrcode_id, &tier).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Dynamic URL restored".to_string(),
+        data: json!({"restored": restored}),
+    }))
+}
@@ -3,7 +3,6 @@ use crate::routes::guard::Claims;
 
 use base64::{engine::general_purpose, Engine};
 use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, TokenData, Validation};
-use reqwest;
 use serde::Deserialize;
 use shuttle_runtime::SecretStore;
 
@@ -23,6 +22,11 @@ impl Environments {
             None => panic!("Missing Key: {}", key),
         }
     }
+
+    pub fn get_optional(&self, key: &str) -> Option<String> {
+        // For settings that have a built-in default.
+        self.env.get(key)
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -92,14 +96,14 @@ pub async fn decode_jwt(token: &str, secrets: &Environments) -> Response<Claims>
 
     let kid = header.kid.ok_or("Missing Kid").expect("double bad");
 
-    let jwk = fetch_jwk(&kid, &secrets).await.map_err(|err| {
+    let jwk = fetch_jwk(&kid, secrets).await.map_err(|err| {
         eprint!("Error Fetching: {:?}", err);
         ApiError::Unauthorized
     });
 
     let a = &jwk.unwrap();
 
-    let (n_bytes, e_bytes) = cleanse_jwk(&a)?;
+    let (n_bytes, e_bytes) = cleanse_jwk(a)?;
 
     let decoding_key = DecodingKey::from_rsa_raw_components(&n_bytes, &e_bytes);
 
@@ -0,0 +1,237 @@
+use crate::database::database::Database;
+use crate::webhook::models::DueDelivery;
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const MAX_ATTEMPTS: i32 = 8;
+
+pub const DISPATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+pub const DISPATCH_BATCH: usize = 50;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, PartialEq)]
+pub enum DeliveryOutcome {
+    Delivered(u16),
+    Failed { status: Option<u16>, error: String },
+}
+
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+pub fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    /*
+        Signs a webhook body with HMAC-SHA256.
+
+        The timestamp is part of the signed content so receivers can reject replays.
+
+        Params:
+            secret (str): The webhook's signing secret.
+            timestamp (u64): Unix timestamp sent in the signature header.
+            body (str): The exact JSON body being sent.
+
+        Returns:
+            String: The hex encoded signature.
+    */
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn public_address(ip: IpAddr) -> bool {
+    // Loopback, private, link-local (including cloud metadata) and other non-routable addresses are refused.
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || first == 0
+                || (first == 100 && (64..128).contains(&second)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => public_address(IpAddr::V4(mapped)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+pub async fn public_endpoint(url: &str) -> bool {
+    /*
+        Checks that a webhook URL is http(s) and that every address its host resolves to is
+        public, so deliveries can't be pointed at the server's own network.
+
+        Returns:
+            bool: Whether deliveries may be sent to the URL.
+    */
+
+    let url = match reqwest::Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+        _ => return false,
+    };
+
+    let (host, port) = match (url.host_str(), url.port_or_known_default()) {
+        (Some(host), Some(port)) => (host.trim_start_matches('[').trim_end_matches(']'), port),
+        _ => return false,
+    };
+
+    let addresses: Vec<IpAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => match tokio::net::lookup_host((host, port)).await {
+            Ok(found) => found.map(|address| address.ip()).collect(),
+            Err(_) => return false,
+        },
+    };
+
+    !addresses.is_empty() && addresses.into_iter().all(public_address)
+}
+
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        /*
+            Resolves a webhook host at send time and drops every address that isn't public, so a
+            host that passed the check when it was registered can't be pointed at the server's own
+            network later. Hosts written as IP addresses don't change and are only checked then.
+        */
+
+        Box::pin(async move {
+            let found = tokio::net::lookup_host((name.as_str(), 0)).await?;
+            let public: Vec<SocketAddr> = found
+                .filter(|address| public_address(address.ip()))
+                .collect();
+
+            if public.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+
+            Ok(Box::new(public.into_iter()) as Addrs)
+        })
+    }
+}
+
+pub fn delivery_client() -> reqwest::Client {
+    // Endpoints are checked when registered, following a redirect would get around that.
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("HTTP client builds with the default TLS backend")
+}
+
+pub fn backoff(attempts: i32) -> Duration {
+    // 30s, 1m, 2m, 4m ... capped at an hour between attempts.
+    let exponent = attempts.clamp(1, 8) as u32 - 1;
+
+    Duration::from_secs(30 * 2u64.pow(exponent)).min(Duration::from_secs(60 * 60))
+}
+
+pub async fn deliver(client: &reqwest::Client, delivery: &DueDelivery) -> DeliveryOutcome {
+    /*
+        Sends a single webhook delivery.
+
+        Headers:
+            X-QR-Event: The event name, `scan` or `scan.batch`.
+            X-QR-Delivery: The delivery ID, stable across retries.
+            X-QR-Signature: `t=<unix timestamp>,v1=<hex HMAC-SHA256 of "<t>.<body>">`.
+
+        Returns:
+            DeliveryOutcome: Delivered on any 2xx response, otherwise Failed.
+    */
+
+    let body = delivery.payload.to_string();
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let response = client
+        .post(&delivery.url)
+        .timeout(REQUEST_TIMEOUT)
+        .header("Content-Type", "application/json")
+        .header("X-QR-Event", &delivery.event)
+        .header("X-QR-Delivery", delivery.id.to_string())
+        .header(
+            "X-QR-Signature",
+            format!(
+                "t={},v1={}",
+                timestamp,
+                sign(&delivery.secret, timestamp, &body)
+            ),
+        )
+        .body(body)
+        .send()
+        .await;
+
+    match response {
+        Ok(response) if response.status().is_success() => {
+            DeliveryOutcome::Delivered(response.status().as_u16())
+        }
+        Ok(response) => DeliveryOutcome::Failed {
+            status: Some(response.status().as_u16()),
+            error: format!("Endpoint responded with {}", response.status()),
+        },
+        Err(error) => DeliveryOutcome::Failed {
+            status: None,
+            error: error.to_string(),
+        },
+    }
+}
+
+pub fn spawn_dispatcher(db: Database) {
+    /*
+        Periodically sends due webhook deliveries and records the outcome of each attempt.
+    */
+
+    tokio::spawn(async move {
+        let client = delivery_client();
+        let mut interval = tokio::time::interval(DISPATCH_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let due = match db.claim_webhook_deliveries(DISPATCH_BATCH).await {
+                Ok(due) => due,
+                Err(error) => {
+                    eprintln!("Failed to claim webhook deliveries: {:?}", error);
+                    continue;
+                }
+            };
+
+            for delivery in due {
+                let outcome = deliver(&client, &delivery).await;
+
+                if let Err(error) = db.record_webhook_attempt(&delivery, outcome).await {
+                    eprintln!("Failed to record webhook delivery: {:?}", error);
+                }
+            }
+        }
+    });
+}
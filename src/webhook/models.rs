@@ -0,0 +1,81 @@
+use crate::database::models::{DeliveryId, WebhookId};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use surrealdb::{sql::Datetime, RecordId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookMode {
+    // One delivery per scan.
+    #[default]
+    Scan,
+    // One delivery per minute with every scan from that minute.
+    Minute,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub mode: WebhookMode,
+    #[serde(default)]
+    pub include_bots: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookResult {
+    pub id: WebhookId,
+    pub url: String,
+    pub mode: WebhookMode,
+    pub include_bots: bool,
+    pub active: bool,
+    pub created_at: Datetime,
+}
+
+// Only returned when the webhook is created, the secret isn't shown again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookSecretResult {
+    pub id: WebhookId,
+    pub url: String,
+    pub mode: WebhookMode,
+    pub include_bots: bool,
+    pub active: bool,
+    pub secret: String,
+    pub created_at: Datetime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Retrying,
+    Delivered,
+    // Gave up after `MAX_ATTEMPTS`, only sent again on manual redelivery.
+    Dead,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookDeliveryResult {
+    pub id: DeliveryId,
+    pub webhook: WebhookId,
+    pub event: String,
+    pub payload: Value,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: Datetime,
+    pub last_status: Option<u16>,
+    pub last_error: Option<String>,
+    pub created_at: Datetime,
+    pub delivered_at: Option<Datetime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DueDelivery {
+    pub id: RecordId,
+    pub event: String,
+    pub payload: Value,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
@@ -0,0 +1,206 @@
+use crate::database::database::Database;
+use crate::database::models::{format_user_id, DeliveryId, WebhookId};
+use crate::errors::{ApiError, ApiResponse, Response};
+use crate::organization::models::Role;
+use crate::routes::guard::Claims;
+use crate::routes::user::workspace;
+use crate::webhook::delivery::{generate_secret, public_endpoint};
+use crate::webhook::models::Webhook;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket::{delete, get, post};
+use serde_json::json;
+
+#[post("/user/<user_id>/webhook?<org>", format = "json", data = "<webhook>")]
+pub async fn create_webhook(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org: Option<&str>,
+    webhook: Json<Webhook>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Registers a webhook that receives signed scan events for the user's QR codes, or with
+        `?org=` for an organization's codes, for its admins and owners.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org: optional organization ID, the webhook then receives the organization's scans.
+            webhook: the endpoint URL, delivery mode (`scan` or `minute`) and whether to include bots.
+                The URL has to resolve to public addresses only.
+
+        Returns:
+            Response<Json<ApiResponse>>: the created webhook, including the signing secret which is only shown once.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let owner = workspace(db, user_id, org, Role::Admin).await?;
+
+    if !public_endpoint(&webhook.url).await {
+        return Err(ApiError::BadRequest);
+    }
+
+    let created = db
+        .insert_webhook(&owner, webhook.into_inner(), &generate_secret())
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Created.code,
+        message: "Webhook created".to_string(),
+        data: json!({"created": created}),
+    }))
+}
+
+#[get("/user/<user_id>/webhook?<org>")]
+pub async fn read_webhooks(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    org: Option<&str>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Lists the user's webhooks, or with `?org=` an organization's, for its admins and owners.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            org: optional organization ID to list the organization's webhooks.
+
+        Returns:
+            Response<Json<ApiResponse>>: the webhooks, without their secrets.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let owner = workspace(db, user_id, org, Role::Admin).await?;
+
+    let webhooks = db.list_webhooks(&owner).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Webhooks".to_string(),
+        data: json!({"webhooks": webhooks}),
+    }))
+}
+
+#[delete("/user/<user_id>/webhook/<webhook_id>?<org>")]
+pub async fn delete_webhook(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    webhook_id: WebhookId,
+    org: Option<&str>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Deletes a webhook and its delivery log, with `?org=` one of an organization's.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            webhook_id: the webhook ID.
+            org: optional organization ID the webhook belongs to.
+
+        Returns:
+            Response<Json<ApiResponse>>: whether the webhook was deleted.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let owner = workspace(db, user_id, org, Role::Admin).await?;
+
+    let deleted = db.delete_webhook(&owner, &webhook_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Webhook deleted".to_string(),
+        data: json!({"deleted": deleted}),
+    }))
+}
+
+#[get("/user/<user_id>/webhook/<webhook_id>/delivery?<org>")]
+pub async fn read_webhook_deliveries(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    webhook_id: WebhookId,
+    org: Option<&str>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Lists the most recent deliveries of a webhook, including failed and dead ones, with
+        `?org=` of one of an organization's.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            webhook_id: the webhook ID.
+            org: optional organization ID the webhook belongs to.
+
+        Returns:
+            Response<Json<ApiResponse>>: the delivery log, newest first.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let owner = workspace(db, user_id, org, Role::Admin).await?;
+
+    let deliveries = db.list_webhook_deliveries(&owner, &webhook_id).await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Webhook deliveries".to_string(),
+        data: json!({"deliveries": deliveries}),
+    }))
+}
+
+#[post("/user/<user_id>/webhook/<webhook_id>/delivery/<delivery_id>/redeliver?<org>")]
+pub async fn redeliver_webhook(
+    token: Claims,
+    db: &State<Database>,
+    user_id: &str,
+    webhook_id: WebhookId,
+    delivery_id: DeliveryId,
+    org: Option<&str>,
+) -> Response<Json<ApiResponse>> {
+    /*
+        Sends a delivery again on the next dispatch, e.g. after fixing the receiving endpoint,
+        with `?org=` for one of an organization's webhooks.
+
+        Params:
+            user_id: the user's Auth0 ID.
+            webhook_id: the webhook ID.
+            delivery_id: the delivery ID.
+            org: optional organization ID the webhook belongs to.
+
+        Returns:
+            Response<Json<ApiResponse>>: the requeued delivery.
+
+    */
+
+    if user_id != format_user_id(token.sub) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let owner = workspace(db, user_id, org, Role::Admin).await?;
+
+    let delivery = db
+        .redeliver_webhook_delivery(&owner, &webhook_id, &delivery_id)
+        .await?;
+
+    Ok(Json(ApiResponse {
+        status: Status::Ok.code,
+        message: "Webhook delivery queued".to_string(),
+        data: json!({"delivery": delivery}),
+    }))
+}